@@ -1,43 +1,85 @@
 use crate::config::{get_chat_db_path, AppConfig, DbConnection};
 use crate::db_element::chat_storage::ChatStorage;
-use crate::db_element::db::{DatabaseManager, PAGE_SIZE};
-use crate::llm::llm::LLMClient;
+use crate::db_element::db::{CancelToken, DatabaseManager, QueryResult, SortOrder, PAGE_SIZE};
+use crate::db_element::schema_cache::SchemaCache;
+use crate::llm::llm::{find_provider, stored_api_key, LLMClient};
 use crate::security::SecureStorage;
 use crate::ui::chat::Conversation;
+use crate::ui::console::Console;
+use crate::ui::export::ExportJob;
+use crate::ui::history::QueryHistory;
+use crate::ui::saved_queries::SavedQueries;
 use crate::ui::connection::Connection;
-use crate::ui::query_result::ResultTable;
+use crate::db_element::chat::QueryExecution;
+use crate::db_element::query_log::QueryLogEntry;
+use crate::ui::query_result::{QueryError, ResultTable, TableView};
+use crate::ui::schema_browser::SchemaBrowser;
 use crate::ui::setting::Settings;
 use crate::ui::ui::render_ui;
 use eframe::egui;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
 use log::error;
 use tokio::runtime::Runtime;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
 pub enum AppMode {
     Home,
     Chat,
+    Console,
+    History,
     Connections,
     Settings,
 }
 
+/// A query started by `run_query` that hasn't reported back yet.
+pub struct RunningQuery {
+    pub connection_id: Uuid,
+    pub handle: JoinHandle<()>,
+    pub cancel: CancelToken,
+    pub sql: String,
+    pub page: usize,
+    pub started_at: DateTime<Utc>,
+}
+
+/// Rows loaded by `fetch_rows`.
+pub struct RowsFetch {
+    connection_id: Uuid,
+    handle: JoinHandle<()>,
+    cancel: CancelToken,
+    pub rx: oneshot::Receiver<Result<QueryResult, String>>,
+}
+
 pub struct AppState {
     pub config: AppConfig,
 
     pub mode: AppMode,
     pub db_manager: Arc<DatabaseManager>,
     pub chat_storage: Arc<ChatStorage>,
+    pub schema_cache: Arc<SchemaCache>,
     pub llm_client: Option<LLMClient>,
 
     pub runtime: Runtime,
-    pub query_tx: tokio::sync::mpsc::Sender<Result<ResultTable, String>>,
-    pub query_rx: tokio::sync::mpsc::Receiver<Result<ResultTable, String>>,
+    pub query_tx: tokio::sync::mpsc::Sender<Result<ResultTable, QueryError>>,
+    pub query_rx: tokio::sync::mpsc::Receiver<Result<ResultTable, QueryError>>,
+    pub running_queries: RefCell<HashMap<Uuid, RunningQuery>>,
 
     // UI related struct
     pub settings: Settings,
     pub connection: Connection,
     pub conversation: Conversation,
+    pub schema_browser: SchemaBrowser,
+    pub console: Console,
+    pub query_history: QueryHistory,
+    pub saved_queries: SavedQueries,
     pub query_result: Vec<ResultTable>,
+    pub exports: Vec<ExportJob>,
 }
 
 pub struct DBQueryApp {
@@ -68,11 +110,14 @@ impl DBQueryApp {
 
         let provider = config.llm_api.provider.clone();
         let model = config.llm_api.model.clone();
-        let api_key = match SecureStorage::get_api_key() {
-            Ok(key) => {key}
-            Err(_) => {"".to_string()}
-        };
-        let chat_path = get_chat_db_path();
+        let base_url = config.llm_api.base_url.clone();
+        let history_turns = config.llm_api.history_turns;
+        let history_tokens = config.llm_api.history_tokens;
+        let max_correction_attempts = config.llm_api.max_correction_attempts;
+        let max_schema_tables = config.llm_api.max_schema_tables;
+        let api_key = provider.as_deref().and_then(find_provider).and_then(stored_api_key).unwrap_or_default();
+        let chat_storage = Arc::new(ChatStorage::new(get_chat_db_path()).unwrap());
+        let schema_cache = Arc::new(SchemaCache::new(db_manager.clone(), chat_storage.clone()));
         let (tx, rx) = tokio::sync::mpsc::channel(1);
         Self {
             state: AppState {
@@ -80,20 +125,32 @@ impl DBQueryApp {
                 settings: Settings{
                     provider,
                     model,
+                    base_url,
                     api_key,
+                    history_turns,
+                    history_tokens,
+                    max_correction_attempts,
+                    max_schema_tables,
                     success_message: None,
                     error_message: None,
                 },
                 mode: AppMode::Home,
                 db_manager,
-                chat_storage: Arc::new(ChatStorage::new(chat_path).unwrap()),
+                chat_storage,
+                schema_cache,
                 llm_client,
                 query_result: Vec::new(),
+                exports: Vec::new(),
                 connection: Connection::new(),
                 runtime,
                 query_tx: tx,
                 query_rx: rx,
+                running_queries: RefCell::new(HashMap::new()),
                 conversation: Conversation::new(None),
+                schema_browser: SchemaBrowser::new(None),
+                console: Console::new(),
+                query_history: QueryHistory::new(),
+                saved_queries: SavedQueries::new(),
             },
         }
     }
@@ -102,9 +159,12 @@ impl DBQueryApp {
 impl AppState {
     pub fn save_db(&mut self) -> Result<(), String> {
         let connection = self.connection.clone();
+        let schemas = connection.schema_list();
         let password = connection.password;
-        if let Err(err) = SecureStorage::store_db_password(&connection.uuid.to_string(), &password) {
-            return Err(err.to_string());
+        if !connection.db_type.is_file_based() {
+            if let Err(err) = SecureStorage::store_db_password(&connection.uuid.to_string(), &password) {
+                return Err(err.to_string());
+            }
         }
 
         let db_connection = DbConnection {
@@ -115,9 +175,15 @@ impl AppState {
             port: connection.port,
             username: connection.username,
             database: connection.database,
+            read_only: connection.read_only,
+            statement_timeout: connection.statement_timeout,
+            schemas,
         };
         // Update or add the connection
         if !connection.is_new  {
+            // The settings may point at another database or schemas now
+            self.schema_cache.invalidate(&connection.uuid);
+            self.schema_browser.forget(&connection.uuid);
             if let Some(idx) = self.config.connections.iter().position(|c| c.uuid == connection.uuid) {
                 self.config.connections[idx] = db_connection;
             } else {
@@ -141,41 +207,66 @@ impl AppState {
         };
         let _ = SecureStorage::remove_db_password(&uuid.to_string());
         let _ = self.chat_storage.remove_conversation(&uuid);
+        self.schema_cache.invalidate(&uuid);
+        self.schema_browser.forget(&uuid);
         self.config.connections.remove(index);
         self.config.save();
         Ok(())
     }
     pub fn save_settings(&mut self) -> Result<(), String> {
         self.config.llm_api.provider = self.settings.provider.clone();
-        self.config.llm_api.model = self.settings.model.clone();
-        // Save API key securely
-        if !self.settings.api_key.is_empty() {
-            if let Err(err) = SecureStorage::store_api_key(
-                &self.settings.api_key
-            ) {
-                return Err(err.to_string());
-            }
-            self.config.save();
+        self.config.llm_api.model = self.settings.model.trim().to_string();
+        self.config.llm_api.base_url = self.settings.base_url.trim().to_string();
+        self.config.llm_api.history_turns = self.settings.history_turns;
+        self.config.llm_api.history_tokens = self.settings.history_tokens;
+        self.config.llm_api.max_correction_attempts = self.settings.max_correction_attempts;
+        self.config.llm_api.max_schema_tables = self.settings.max_schema_tables;
+        // Save API key securely, an emptied field removes the key of the provider
+        if let Some(provider) = &self.settings.provider {
+            let res = if self.settings.api_key.is_empty() {
+                SecureStorage::remove_api_key(provider)
+            } else {
+                SecureStorage::store_api_key(provider, &self.settings.api_key)
+            };
+            res.map_err(|err| err.to_string())?;
         }
+        self.config.save();
+        self.llm_client = Some(LLMClient::new(self.config.llm_api.clone()));
         Ok(())
     }
 
     pub fn run_query(&self, connection_id: &Uuid, query: &str, message_uuid: &Uuid, page: usize) {
+        self.run_sorted_query(connection_id, query, message_uuid, page, None);
+    }
 
+    /// Like `run_query`, with the rows of every page ordered by a column on the server.
+    pub fn run_sorted_query(&self, connection_id: &Uuid, query: &str, message_uuid: &Uuid, page: usize, sort: Option<SortOrder>) {
         let tx = self.query_tx.clone();
         let message_uuid = message_uuid.clone();
-        let db_manager = self.db_manager.clone();
         let connection_id = connection_id.clone();
         let query = query.to_string();
-        let offset = (page-1) * PAGE_SIZE;
-        self.runtime.spawn(async move {
-            let res = match db_manager.execute_query(&connection_id, &query, offset, None).await {
+        let cancel = CancelToken::default();
+        let started_at = Utc::now();
+        let execution = self.execute(connection_id, query.clone(), page, None, sort, cancel.clone());
+        let task_query = query.clone();
+        let handle = self.runtime.spawn(async move {
+            let query = task_query;
+            // Cancelled by the user, who already got told
+            let Some(res) = execution.await else {
+                return;
+            };
+            let res = match res {
                 Ok(res) => {
                     res
                 },
                 Err(e) => {
                     error!("Failed to execute query with message id {} : {}", message_uuid, e);
-                    tx.send(Err(format!("Failed to execute query: {}", e))).await.ok();
+                    tx.send(Err(QueryError {
+                        id: message_uuid,
+                        connection_id,
+                        query,
+                        message: e,
+                    })).await.ok();
                     return;
                 },
             };
@@ -185,11 +276,177 @@ impl AppState {
                 edited_page: res.current_page,
                 query,
                 data: res,
+                view: TableView::default(),
                 is_open: true,
             };
             tx.send(Ok(table)).await.ok();
         });
 
+        let previous = self.running_queries.borrow_mut().insert(message_uuid, RunningQuery {
+            connection_id,
+            handle,
+            cancel,
+            sql: query,
+            page,
+            started_at,
+        });
+        // Another page or order of the same message replaces the one still running
+        if let Some(previous) = previous {
+            self.stop(previous.connection_id, previous.handle, previous.cancel);
+        }
+    }
+
+    /// Up to `limit` rows of a query for a view of its result, like `run_sorted_query` logged,
+    /// bounded by the client side timeout and cancelled with `stop_fetch`.
+    pub fn fetch_rows(&self, connection_id: &Uuid, query: &str, limit: usize, sort: Option<SortOrder>) -> RowsFetch {
+        let (tx, rx) = oneshot::channel();
+        let cancel = CancelToken::default();
+        let execution = self.execute(*connection_id, query.to_string(), 1, Some(limit), sort, cancel.clone());
+        let handle = self.runtime.spawn(async move {
+            if let Some(res) = execution.await {
+                tx.send(res).ok();
+            }
+        });
+        RowsFetch { connection_id: *connection_id, handle, cancel, rx }
+    }
+
+    /// Stops a `fetch_rows` whose rows aren't wanted anymore, on the server too.
+    pub fn stop_fetch(&self, fetch: RowsFetch) {
+        self.stop(fetch.connection_id, fetch.handle, fetch.cancel);
+    }
+
+    /// Stopped on the server while the task still holds its session, then the task goes.
+    /// The task's outcome is dropped.
+    fn stop(&self, connection_id: Uuid, handle: JoinHandle<()>, cancel: CancelToken) {
+        cancel.cancel();
+        let db_manager = self.db_manager.clone();
+        self.runtime.spawn(async move {
+            if !handle.is_finished() {
+                if let Err(err) = db_manager.cancel_query(&connection_id, &cancel).await {
+                    error!("Failed to cancel query: {}", err);
+                }
+            }
+            handle.abort();
+        });
+    }
+
+    /// Runs a page of a query with the client side timeout of dialects without a session one, and logs it.
+    /// `None` when it was cancelled.
+    fn execute(&self, connection_id: Uuid, query: String, page: usize, limit: Option<usize>, sort: Option<SortOrder>, cancel: CancelToken)
+        -> impl Future<Output = Option<Result<QueryResult, String>>> + Send + 'static {
+        let offset = (page-1) * limit.unwrap_or(PAGE_SIZE);
+        let db_manager = self.db_manager.clone();
+        let task_query = query.clone();
+        let task_cancel = cancel.clone();
+        let execution = async move {
+            db_manager.execute_query(&connection_id, &task_query, offset, limit, sort.as_ref(), &task_cancel).await
+        };
+        self.supervise(connection_id, query, page, cancel, execution, |res| res.total_rows)
+    }
+
+    /// Awaits an execution of `query`, stopping it after the statement timeout on dialects without
+    /// a session one, and logs it with the rows `row_count` tells. `None` when it was cancelled.
+    pub fn supervise<T: Send + 'static>(
+        &self,
+        connection_id: Uuid,
+        query: String,
+        page: usize,
+        cancel: CancelToken,
+        execution: impl Future<Output = Result<T, String>> + Send + 'static,
+        row_count: impl Fn(&T) -> usize + Send + 'static,
+    ) -> impl Future<Output = Option<Result<T, String>>> + Send + 'static {
+        let db_manager = self.db_manager.clone();
+        // Dialects without a session timeout get it enforced here instead
+        let client_timeout = self.config.connections.iter()
+            .find(|c| c.uuid == connection_id)
+            .filter(|c| c.statement_timeout > 0 && !c.db_type.has_session_timeout())
+            .map(|c| c.statement_timeout);
+        let chat_storage = self.chat_storage.clone();
+        let started_at = Utc::now();
+        async move {
+            let started = Instant::now();
+            let res = {
+                tokio::pin!(execution);
+                match client_timeout {
+                    // The timed out query is still running while it's cancelled, so its session is still known
+                    Some(seconds) => match tokio::time::timeout(Duration::from_secs(seconds), &mut execution).await {
+                        Ok(res) => res,
+                        Err(_) => {
+                            if let Err(err) = db_manager.cancel_query(&connection_id, &cancel).await {
+                                error!("Failed to cancel timed out query: {}", err);
+                            }
+                            Err(format!("Query exceeded the statement timeout of {} s", seconds))
+                        }
+                    },
+                    None => execution.await,
+                }
+            };
+            if cancel.is_cancelled() {
+                return None;
+            }
+            log_query(&chat_storage, QueryLogEntry {
+                id: Uuid::new_v4(),
+                connection_id,
+                sql: query,
+                started_at,
+                duration_ms: started.elapsed().as_millis() as u64,
+                row_count: res.as_ref().ok().map(row_count),
+                page,
+                error: res.as_ref().err().cloned(),
+            });
+            Some(res)
+        }
+    }
+
+    /// Stops a query started by `run_query`, both the task and the statement on the server.
+    pub fn cancel_query(&mut self, message_uuid: &Uuid) {
+        let Some(running) = self.running_queries.borrow_mut().remove(message_uuid) else {
+            return;
+        };
+        // The interrupted statement's error never reaches the UI
+        self.stop(running.connection_id, running.handle, running.cancel);
+        self.conversation.loading_query.borrow_mut().retain(|id| id != message_uuid);
+
+        let error = "Cancelled by the user".to_string();
+        log_query(&self.chat_storage, QueryLogEntry {
+            id: Uuid::new_v4(),
+            connection_id: running.connection_id,
+            sql: running.sql,
+            started_at: running.started_at,
+            duration_ms: (Utc::now() - running.started_at).num_milliseconds().max(0) as u64,
+            row_count: None,
+            page: running.page,
+            error: Some(error.clone()),
+        });
+        self.query_history.invalidate();
+
+        self.record_execution(&running.connection_id, message_uuid, QueryExecution {
+            success: false,
+            row_count: 0,
+            error: Some(error),
+        });
+    }
+}
+
+fn log_query(chat_storage: &ChatStorage, entry: QueryLogEntry) {
+    if let Err(err) = chat_storage.add_query_log(&entry) {
+        error!("Failed to log query: {}", err);
+    }
+}
+
+impl AppState {
+    /// Remembers the outcome of running a SQL message of the open conversation.
+    pub fn record_execution(&mut self, connection_id: &Uuid, message_uuid: &Uuid, execution: QueryExecution) {
+        if self.conversation.id != Some(*connection_id)
+            || !self.conversation.messages.iter().any(|message| message.uuid == *message_uuid) {
+            return;
+        }
+
+        let meta = self.conversation.meta.entry(*message_uuid).or_default();
+        meta.execution = Some(execution);
+        if let Err(err) = self.chat_storage.set_message_meta(connection_id, message_uuid, meta) {
+            error!("Failed to store execution of message {}: {}", message_uuid, err);
+        }
     }
 }
 
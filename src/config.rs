@@ -1,4 +1,3 @@
-use crate::llm::llm::Provider;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -8,8 +7,50 @@ use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct LLMConfig {
-    pub provider: Option<Provider>,
+    /// Id of the selected `LlmProvider`.
+    pub provider: Option<String>,
     pub model: String,
+    /// Endpoint of self-hosted providers, empty to use the provider default.
+    #[serde(default)]
+    pub base_url: String,
+    /// Maximum number of previous question/answer turns sent with a new question.
+    #[serde(default = "default_history_turns")]
+    pub history_turns: usize,
+    /// Approximate token budget for the previous turns, older turns are dropped first.
+    #[serde(default = "default_history_tokens")]
+    pub history_tokens: usize,
+    /// How many times a failing query is sent back to the LLM for a fix, 0 disables it.
+    #[serde(default)]
+    pub max_correction_attempts: usize,
+    /// Larger schemas are pruned to the tables relevant to the question, 0 always sends everything.
+    #[serde(default = "default_max_schema_tables")]
+    pub max_schema_tables: usize,
+}
+
+fn default_history_turns() -> usize {
+    10
+}
+
+fn default_history_tokens() -> usize {
+    2000
+}
+
+fn default_max_schema_tables() -> usize {
+    30
+}
+
+impl Default for LLMConfig {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            model: "".to_string(),
+            base_url: "".to_string(),
+            history_turns: default_history_turns(),
+            history_tokens: default_history_tokens(),
+            max_correction_attempts: 0,
+            max_schema_tables: default_max_schema_tables(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -21,6 +62,19 @@ pub struct DbConnection {
     pub port: u16,
     pub username: String,
     pub database: String,
+    /// Open every session read-only so the server itself refuses writes.
+    #[serde(default = "default_read_only")]
+    pub read_only: bool,
+    /// Seconds a single statement may run, 0 means no limit.
+    #[serde(default)]
+    pub statement_timeout: u64,
+    /// Postgres schemas to include, set as the session search_path. Empty keeps the server default.
+    #[serde(default)]
+    pub schemas: Vec<String>,
+}
+
+fn default_read_only() -> bool {
+    true
 }
 
 impl DbConnection {
@@ -28,6 +82,10 @@ impl DbConnection {
         match self.db_type {
             DbType::MySQL => {"mysql://{username}:{password}@{host}:{port}/{database}".to_string()}
             DbType::PostgreSQL => {"postgres://{username}:{password}@{host}:{port}/{database}?client_encoding=UTF8".to_string()}
+            DbType::SQLite if self.read_only => {"sqlite://{database}?mode=ro".to_string()}
+            DbType::SQLite => {"sqlite://{database}".to_string()}
+            // Configured field by field, a password can't be pasted into an ADO string as is
+            DbType::SQLServer => {String::new()}
         }
     }
 }
@@ -35,9 +93,51 @@ impl DbConnection {
 pub enum DbType {
     MySQL,
     PostgreSQL,
+    SQLite,
+    SQLServer,
+}
+
+impl DbType {
+    /// SQL dialect name, used to tell the LLM which flavour of SQL to write.
+    pub fn dialect_name(&self) -> &'static str {
+        match self {
+            DbType::MySQL => "MySQL",
+            DbType::PostgreSQL => "PostgreSQL",
+            DbType::SQLite => "SQLite",
+            DbType::SQLServer => "Microsoft SQL Server (T-SQL)",
+        }
+    }
+
+    /// File based databases only need a path, no host, port or credentials.
+    pub fn is_file_based(&self) -> bool {
+        matches!(self, DbType::SQLite)
+    }
+
+    /// Whether a statement timeout can be set on the session itself.
+    pub fn has_session_timeout(&self) -> bool {
+        matches!(self, DbType::MySQL | DbType::PostgreSQL)
+    }
+
+    /// Quotes a table or column name so it can be used in a query as is.
+    pub fn quote_identifier(&self, name: &str) -> String {
+        match self {
+            DbType::MySQL => format!("`{}`", name.replace('`', "``")),
+            DbType::SQLServer => format!("[{}]", name.replace(']', "]]")),
+            DbType::PostgreSQL | DbType::SQLite => format!("\"{}\"", name.replace('"', "\"\"")),
+        }
+    }
+
+    /// Quotes a value as a string literal.
+    pub fn quote_literal(&self, value: &str) -> String {
+        match self {
+            // A backslash escapes the next character in MySQL strings
+            DbType::MySQL => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''")),
+            DbType::PostgreSQL | DbType::SQLite | DbType::SQLServer => format!("'{}'", value.replace('\'', "''")),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct AppConfig {
     pub llm_api: LLMConfig,
     pub connections: Vec<DbConnection>,
@@ -70,18 +170,6 @@ impl AppConfig {
     }
 }
 
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            llm_api: LLMConfig {
-                provider: None,
-                model: "".to_string(),
-            },
-            connections: Vec::new(),
-        }
-    }
-}
-
 fn get_config_path() -> PathBuf {
     let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("./"));
     path.push("neVil");
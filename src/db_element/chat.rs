@@ -1,6 +1,6 @@
 use bincode::{Decode, Encode};
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 #[derive(Serialize, Encode, Decode)]
@@ -31,4 +31,55 @@ impl Message {
             timestamp: Utc::now(),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Edited text of a message, the stored `Message` keeps what was originally generated.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MessageRevision {
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Outcome of the last run of a SQL message.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QueryExecution {
+    pub success: bool,
+    pub row_count: usize,
+    pub error: Option<String>,
+}
+
+/// Chart suggested by the LLM for a query, with the columns by name. Only a suggestion, it's
+/// checked against the columns of the result before it's used.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VisualizationSpec {
+    /// line, bar, scatter, histogram or pie.
+    pub chart: String,
+    #[serde(default)]
+    pub x: Option<String>,
+    #[serde(default)]
+    pub y: Option<String>,
+    #[serde(default)]
+    pub series: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+/// Extra information attached to a message. Stored next to the message as JSON so new fields
+/// can be added without breaking conversations that are already saved.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MessageMeta {
+    #[serde(default)]
+    pub execution: Option<QueryExecution>,
+    /// Status message written by neVil rather than by the LLM, left out of the LLM history.
+    #[serde(default)]
+    pub note: bool,
+    /// Set on queries produced by the self-correction loop, counting from 1.
+    #[serde(default)]
+    pub correction_attempt: Option<usize>,
+    #[serde(default)]
+    pub visualization: Option<VisualizationSpec>,
+    /// Set on plain-language summaries of a result, to the id of the query message it came from.
+    /// Left out of the LLM history like notes, the execution results already cover it.
+    #[serde(default)]
+    pub explains: Option<Uuid>,
+}
@@ -1,5 +1,9 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
-use crate::db_element::chat::Message;
+use crate::db_element::chat::{Message, MessageMeta, MessageRevision};
+use crate::db_element::query_log::QueryLogEntry;
+use crate::db_element::saved_query::SavedQuery;
+use crate::db_element::schema_cache::CachedSchema;
 use bincode::config;
 use log::debug;
 use sled::Db;
@@ -42,19 +46,141 @@ impl ChatStorage {
         Ok(messages)
     }
 
-    pub fn remove_conversation(&self, conversation_id: &Uuid) -> Result<(), String> {
-        let messages_tree = self.db.open_tree("messages").expect("Unable to open messages");
+    pub fn set_message_meta(&self, conversation_uuid: &Uuid, message_uuid: &Uuid, meta: &MessageMeta) -> Result<(), String> {
+        let tree = self.db.open_tree("message_meta").map_err(|e| e.to_string())?;
+        let key = format!("{}:{}", conversation_uuid, message_uuid);
+        let encode = serde_json::to_vec(meta).map_err(|e| e.to_string())?;
+        tree.insert(key.as_bytes(), encode).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn get_message_meta(&self, conversation_uuid: &Uuid) -> Result<HashMap<Uuid, MessageMeta>, String> {
+        let tree = self.db.open_tree("message_meta").map_err(|e| e.to_string())?;
+        let prefix = format!("{}:", conversation_uuid);
+        let mut metas = HashMap::new();
+        for entry in tree.scan_prefix(prefix.as_bytes()) {
+            let (key, bytes) = entry.map_err(|e| e.to_string())?;
+            let key = String::from_utf8_lossy(&key);
+            let Some(message_uuid) = key.strip_prefix(&prefix).and_then(|id| Uuid::parse_str(id).ok()) else {
+                continue;
+            };
+            let meta: MessageMeta = serde_json::from_slice(&bytes).map_err(|e| e.to_string())?;
+            metas.insert(message_uuid, meta);
+        }
+        Ok(metas)
+    }
+
+    pub fn add_revision(&self, conversation_uuid: &Uuid, message_uuid: &Uuid, revision: &MessageRevision) -> Result<(), String> {
+        let tree = self.db.open_tree("message_revisions").map_err(|e| e.to_string())?;
+        // Zero padded so the revisions of a message sort by time
+        let key = format!("{}:{}:{:020}", conversation_uuid, message_uuid, revision.created_at.timestamp_micros());
+        let encode = serde_json::to_vec(revision).map_err(|e| e.to_string())?;
+        tree.insert(key.as_bytes(), encode).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Revisions of every edited message in the conversation, oldest first.
+    pub fn get_revisions(&self, conversation_uuid: &Uuid) -> Result<HashMap<Uuid, Vec<MessageRevision>>, String> {
+        let tree = self.db.open_tree("message_revisions").map_err(|e| e.to_string())?;
+        let prefix = format!("{}:", conversation_uuid);
+        let mut revisions: HashMap<Uuid, Vec<MessageRevision>> = HashMap::new();
+        for entry in tree.scan_prefix(prefix.as_bytes()) {
+            let (key, bytes) = entry.map_err(|e| e.to_string())?;
+            let key = String::from_utf8_lossy(&key);
+            let Some(message_uuid) = key.strip_prefix(&prefix)
+                .and_then(|rest| rest.split(':').next())
+                .and_then(|id| Uuid::parse_str(id).ok()) else {
+                continue;
+            };
+            let revision: MessageRevision = serde_json::from_slice(&bytes).map_err(|e| e.to_string())?;
+            revisions.entry(message_uuid).or_default().push(revision);
+        }
+        Ok(revisions)
+    }
+
+    pub fn add_query_log(&self, entry: &QueryLogEntry) -> Result<(), String> {
+        let tree = self.db.open_tree("query_history").map_err(|e| e.to_string())?;
+        let key = format!("{}:{:020}:{}", entry.connection_id, entry.started_at.timestamp_micros(), entry.id);
+        let encode = serde_json::to_vec(entry).map_err(|e| e.to_string())?;
+        tree.insert(key.as_bytes(), encode).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Executed queries of a connection, newest first.
+    pub fn get_query_log(&self, connection_uuid: &Uuid) -> Result<Vec<QueryLogEntry>, String> {
+        let tree = self.db.open_tree("query_history").map_err(|e| e.to_string())?;
+        let prefix = format!("{}:", connection_uuid);
+        let mut entries = Vec::new();
+        for entry in tree.scan_prefix(prefix.as_bytes()).rev() {
+            let (_, bytes) = entry.map_err(|e| e.to_string())?;
+            entries.push(serde_json::from_slice(&bytes).map_err(|e| e.to_string())?);
+        }
+        Ok(entries)
+    }
+
+    pub fn save_query(&self, query: &SavedQuery) -> Result<(), String> {
+        let tree = self.db.open_tree("saved_queries").map_err(|e| e.to_string())?;
+        let key = format!("{}:{}", query.connection_id, query.id);
+        let encode = serde_json::to_vec(query).map_err(|e| e.to_string())?;
+        tree.insert(key.as_bytes(), encode).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Saved queries of a connection, sorted by name.
+    pub fn get_saved_queries(&self, connection_uuid: &Uuid) -> Result<Vec<SavedQuery>, String> {
+        let tree = self.db.open_tree("saved_queries").map_err(|e| e.to_string())?;
+        let prefix = format!("{}:", connection_uuid);
+        let mut queries = Vec::new();
+        for entry in tree.scan_prefix(prefix.as_bytes()) {
+            let (_, bytes) = entry.map_err(|e| e.to_string())?;
+            queries.push(serde_json::from_slice::<SavedQuery>(&bytes).map_err(|e| e.to_string())?);
+        }
+        queries.sort_by_key(|query| query.name.to_lowercase());
+        Ok(queries)
+    }
+
+    pub fn remove_saved_query(&self, connection_uuid: &Uuid, query_uuid: &Uuid) -> Result<(), String> {
+        let tree = self.db.open_tree("saved_queries").map_err(|e| e.to_string())?;
+        let key = format!("{}:{}", connection_uuid, query_uuid);
+        tree.remove(key.as_bytes()).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn set_cached_schema(&self, connection_uuid: &Uuid, schema: &CachedSchema) -> Result<(), String> {
+        let tree = self.db.open_tree("schema_cache").map_err(|e| e.to_string())?;
+        let encode = serde_json::to_vec(schema).map_err(|e| e.to_string())?;
+        tree.insert(connection_uuid.as_bytes(), encode).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn get_cached_schema(&self, connection_uuid: &Uuid) -> Result<Option<CachedSchema>, String> {
+        let tree = self.db.open_tree("schema_cache").map_err(|e| e.to_string())?;
+        match tree.get(connection_uuid.as_bytes()).map_err(|e| e.to_string())? {
+            Some(bytes) => Ok(Some(serde_json::from_slice(&bytes).map_err(|e| e.to_string())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn remove_cached_schema(&self, connection_uuid: &Uuid) -> Result<(), String> {
+        let tree = self.db.open_tree("schema_cache").map_err(|e| e.to_string())?;
+        tree.remove(connection_uuid.as_bytes()).map_err(|e| e.to_string())?;
+        Ok(())
+    }
 
+    pub fn remove_conversation(&self, conversation_id: &Uuid) -> Result<(), String> {
         let prefix = format!("{}:", conversation_id);
-        let message_prefix = prefix.as_bytes();
         debug!("Removing conversation with prefix {}", prefix);
-        let keys_to_remove: Vec<_> = messages_tree
-            .scan_prefix(message_prefix)
-            .keys()
-            .collect::<Result<Vec<_>, sled::Error>>().expect("Unable to retrieve keys");
+        // Everything kept per connection is keyed by the connection id first
+        for tree_name in ["messages", "message_meta", "message_revisions", "query_history", "saved_queries"] {
+            let tree = self.db.open_tree(tree_name).expect("Unable to open tree");
+            let keys_to_remove: Vec<_> = tree
+                .scan_prefix(prefix.as_bytes())
+                .keys()
+                .collect::<Result<Vec<_>, sled::Error>>().expect("Unable to retrieve keys");
 
-        for key in keys_to_remove {
-            messages_tree.remove(&key).expect("Unable to remove message");
+            for key in keys_to_remove {
+                tree.remove(&key).expect("Unable to remove entry");
+            }
         }
 
         Ok(())
@@ -66,7 +192,9 @@ mod tests {
     use crate::db_element::chat_storage::ChatStorage;
     use tempfile::tempdir;
     use uuid::Uuid;
-    use crate::db_element::chat::{Message, Sender};
+    use crate::db_element::chat::{Message, MessageMeta, MessageRevision, QueryExecution, Sender};
+    use crate::db_element::query_log::QueryLogEntry;
+    use crate::db_element::saved_query::SavedQuery;
 
     fn setup_chat_storage() -> ChatStorage {
         let temp_dir = tempdir().expect("Failed to create temp dir");
@@ -106,10 +234,87 @@ mod tests {
         };
 
         chat_storage.add_message(&conversation_id, &message).expect("Failed to add message");
+        chat_storage.set_message_meta(&conversation_id, &message.uuid, &MessageMeta::default()).expect("Failed to set message meta");
         chat_storage.remove_conversation(&conversation_id).expect("Failed to remove conversation");
         let messages = chat_storage.get_conversation(&conversation_id).expect("Failed to get messages");
 
         assert!(messages.is_empty());
+        assert!(chat_storage.get_message_meta(&conversation_id).expect("Failed to get message meta").is_empty());
+    }
+
+    #[test]
+    fn test_message_meta() {
+        let chat_storage = setup_chat_storage();
+        let conversation_id = Uuid::new_v4();
+        let message_id = Uuid::new_v4();
+        let meta = MessageMeta {
+            execution: Some(QueryExecution { success: true, row_count: 42, error: None }),
+            ..Default::default()
+        };
+
+        chat_storage.set_message_meta(&conversation_id, &message_id, &meta).expect("Failed to set message meta");
+        chat_storage.set_message_meta(&Uuid::new_v4(), &message_id, &MessageMeta::default()).expect("Failed to set message meta");
+        let metas = chat_storage.get_message_meta(&conversation_id).expect("Failed to get message meta");
+
+        assert_eq!(metas.len(), 1);
+        assert_eq!(metas[&message_id].execution, meta.execution);
+    }
+
+    #[test]
+    fn test_query_log() {
+        let chat_storage = setup_chat_storage();
+        let connection_id = Uuid::new_v4();
+        let entry = |sql: &str, seconds: i64| QueryLogEntry {
+            id: Uuid::new_v4(),
+            connection_id,
+            sql: sql.to_string(),
+            started_at: chrono::Utc::now() + chrono::Duration::seconds(seconds),
+            duration_ms: 12,
+            row_count: Some(3),
+            page: 1,
+            error: None,
+        };
+
+        chat_storage.add_query_log(&entry("SELECT 1", 0)).expect("Failed to log query");
+        chat_storage.add_query_log(&entry("SELECT 2", 1)).expect("Failed to log query");
+        let log = chat_storage.get_query_log(&connection_id).expect("Failed to get query log");
+        assert_eq!(log.iter().map(|entry| entry.sql.as_str()).collect::<Vec<_>>(), vec!["SELECT 2", "SELECT 1"]);
+        assert!(chat_storage.get_query_log(&Uuid::new_v4()).expect("Failed to get query log").is_empty());
+    }
+
+    #[test]
+    fn test_saved_queries() {
+        let chat_storage = setup_chat_storage();
+        let connection_id = Uuid::new_v4();
+        let mut query = SavedQuery::new(connection_id, "top customers".to_string(), "SELECT 1".to_string());
+        let other = SavedQuery::new(connection_id, "Active users".to_string(), "SELECT 2".to_string());
+
+        chat_storage.save_query(&query).expect("Failed to save query");
+        chat_storage.save_query(&other).expect("Failed to save query");
+        query.tags = vec!["sales".to_string()];
+        chat_storage.save_query(&query).expect("Failed to update query");
+        let queries = chat_storage.get_saved_queries(&connection_id).expect("Failed to get saved queries");
+        assert_eq!(queries, vec![other.clone(), query.clone()]);
+
+        chat_storage.remove_saved_query(&connection_id, &other.id).expect("Failed to remove saved query");
+        assert_eq!(chat_storage.get_saved_queries(&connection_id).expect("Failed to get saved queries"), vec![query]);
+    }
+
+    #[test]
+    fn test_message_revisions() {
+        let chat_storage = setup_chat_storage();
+        let conversation_id = Uuid::new_v4();
+        let message_id = Uuid::new_v4();
+        let first = MessageRevision { content: "SELECT 1".to_string(), created_at: chrono::Utc::now() };
+        let second = MessageRevision { content: "SELECT 2".to_string(), created_at: first.created_at + chrono::Duration::seconds(1) };
+
+        chat_storage.add_revision(&conversation_id, &message_id, &second).expect("Failed to add revision");
+        chat_storage.add_revision(&conversation_id, &message_id, &first).expect("Failed to add revision");
+        let revisions = chat_storage.get_revisions(&conversation_id).expect("Failed to get revisions");
+        assert_eq!(revisions[&message_id], vec![first, second]);
+
+        chat_storage.remove_conversation(&conversation_id).expect("Failed to remove conversation");
+        assert!(chat_storage.get_revisions(&conversation_id).expect("Failed to get revisions").is_empty());
     }
 
 }
\ No newline at end of file
@@ -1,34 +1,153 @@
 use std::collections::HashMap;
 use crate::config::{DbConnection, DbType};
 use crate::security::SecureStorage;
-use sqlx::{mysql::MySqlPoolOptions, postgres::PgPoolOptions, MySqlPool, PgPool, Row};
+use sqlx::{mysql::MySqlPoolOptions, postgres::PgPoolOptions, sqlite::SqlitePoolOptions, Executor, MySqlPool, PgPool, SqlitePool};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use log::{debug};
 use tokio::sync::Mutex;
 use uuid::Uuid;
-use crate::utils::db_utils::{mysql_query, postgres_query};
+use crate::utils::db_utils::{mssql_query, mssql_stream, mysql_query, mysql_stream, postgres_query, postgres_stream, sqlite_query, sqlite_stream};
+use crate::utils::pagination::mssql_pagination;
+use crate::utils::sql_guard::ensure_read_only;
+use crate::db_element::value::CellValue;
+use crate::db_element::introspect;
+use crate::db_element::schema::DatabaseSchema;
 
 pub const PAGE_SIZE: usize = 100;
+
+pub type MsSqlPool = bb8::Pool<bb8_tiberius::ConnectionManager>;
+
+#[derive(Clone)]
 pub enum DbPool {
     MySQL(MySqlPool),
     PostgreSQL(PgPool),
+    SQLite(SqlitePool),
+    SQLServer(MsSqlPool),
+}
+
+impl DbPool {
+    pub fn db_type(&self) -> DbType {
+        match self {
+            DbPool::MySQL(_) => DbType::MySQL,
+            DbPool::PostgreSQL(_) => DbType::PostgreSQL,
+            DbPool::SQLite(_) => DbType::SQLite,
+            DbPool::SQLServer(_) => DbType::SQLServer,
+        }
+    }
 }
 
 pub struct DatabaseManager {
     connections: Arc<Mutex<HashMap<Uuid, DbPool>>>,
 }
 
-#[derive(Debug)]
+/// Shared with a running `execute_query` so the query can be cancelled on the server.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    backend_id: Arc<std::sync::Mutex<Option<i64>>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Records the session running the query until the guard is dropped, when the query ends or
+    /// its task is aborted. After that the session is back in the pool running something else.
+    fn attach(&self, id: i64) -> BackendGuard<'_> {
+        *self.backend_id.lock().unwrap() = Some(id);
+        BackendGuard(self)
+    }
+
+    fn backend_id(&self) -> Option<i64> {
+        *self.backend_id.lock().unwrap()
+    }
+
+    /// Marks the outcome of the query as no longer wanted.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+struct BackendGuard<'a>(&'a CancelToken);
+
+impl Drop for BackendGuard<'_> {
+    fn drop(&mut self) {
+        *self.0.backend_id.lock().unwrap() = None;
+    }
+}
+
+fn mssql_config(connection: &DbConnection, password: &str) -> tiberius::Config {
+    let mut config = tiberius::Config::new();
+    config.host(&connection.host);
+    config.port(connection.port);
+    config.database(&connection.database);
+    config.authentication(tiberius::AuthMethod::sql_server(&connection.username, password));
+    config.trust_cert();
+    // SQL Server has no read-only session, the intent is only enforced by read-only replicas
+    config.readonly(connection.read_only);
+    config
+}
+
+/// Statements run on every new session of a connection.
+fn session_setup(connection: &DbConnection) -> Vec<String> {
+    let mut statements = Vec::new();
+    let timeout_ms = connection.statement_timeout * 1000;
+    match connection.db_type {
+        DbType::MySQL => {
+            if connection.read_only {
+                statements.push("SET SESSION TRANSACTION READ ONLY".to_string());
+            }
+            if timeout_ms > 0 {
+                statements.push(format!("SET SESSION max_execution_time = {}", timeout_ms));
+            }
+        }
+        DbType::PostgreSQL => {
+            if connection.read_only {
+                statements.push("SET default_transaction_read_only = on".to_string());
+            }
+            if timeout_ms > 0 {
+                statements.push(format!("SET statement_timeout = {}", timeout_ms));
+            }
+            if !connection.schemas.is_empty() {
+                let schemas: Vec<String> = connection.schemas.iter()
+                    .map(|schema| format!("\"{}\"", schema.replace('"', "\"\"")))
+                    .collect();
+                statements.push(format!("SET search_path TO {}", schemas.join(", ")));
+            }
+        }
+        DbType::SQLite | DbType::SQLServer => {}
+    }
+    statements
+}
+
+#[derive(Debug, Clone)]
 pub struct QueryResult {
     pub columns: Vec<String>,
-    pub rows: Vec<Vec<String>>,
+    pub rows: Vec<Vec<CellValue>>,
+    pub total_rows: usize,
     pub current_page: usize,
     pub total_pages: usize,
     #[allow(dead_code)]
     pub limit: usize,
 }
 
+/// Server side order of a paginated query, by the position of a column in its result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortOrder {
+    pub column: usize,
+    pub descending: bool,
+}
+
+/// Receives the rows of `DatabaseManager::stream_query` one at a time.
+pub trait RowSink: Send {
+    /// Called once, before the first row.
+    fn columns(&mut self, columns: Vec<String>) -> Result<(), String>;
+    fn row(&mut self, row: Vec<CellValue>) -> Result<(), String>;
+}
+
 impl DatabaseManager {
     pub fn new() -> Self {
         Self {
@@ -59,23 +178,70 @@ impl DatabaseManager {
         // Create pool based on database type
         let pool = match connection.db_type {
             DbType::MySQL => {
-                let pool = MySqlPoolOptions::new()
+                let mut options = MySqlPoolOptions::new()
                     .acquire_timeout(timeout_duration)
-                    .max_connections(5)
+                    .max_connections(5);
+                let statements = session_setup(connection);
+                if !statements.is_empty() {
+                    options = options.after_connect(move |conn, _meta| {
+                        let statements = statements.clone();
+                        Box::pin(async move {
+                            for statement in statements {
+                                conn.execute(statement.as_str()).await?;
+                            }
+                            Ok(())
+                        })
+                    });
+                }
+                let pool = options
                     .connect(&connection_string)
                     .await
                     .map_err(|e| e.to_string())?;
                 DbPool::MySQL(pool)
             },
             DbType::PostgreSQL => {
-                let pool = PgPoolOptions::new()
+                let mut options = PgPoolOptions::new()
                     .acquire_timeout(timeout_duration)
-                    .max_connections(5)
+                    .max_connections(5);
+                let statements = session_setup(connection);
+                if !statements.is_empty() {
+                    options = options.after_connect(move |conn, _meta| {
+                        let statements = statements.clone();
+                        Box::pin(async move {
+                            for statement in statements {
+                                conn.execute(statement.as_str()).await?;
+                            }
+                            Ok(())
+                        })
+                    });
+                }
+                let pool = options
                     .connect(&connection_string)
                     .await
                     .map_err(|e| e.to_string())?;
                 DbPool::PostgreSQL(pool)
             },
+            DbType::SQLite => {
+                let pool = SqlitePoolOptions::new()
+                    .acquire_timeout(timeout_duration)
+                    .max_connections(5)
+                    .connect(&connection_string)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                DbPool::SQLite(pool)
+            },
+            DbType::SQLServer => {
+                let manager = bb8_tiberius::ConnectionManager::new(mssql_config(connection, &password));
+                let pool = bb8::Pool::builder()
+                    .connection_timeout(timeout_duration)
+                    .max_size(5)
+                    .build(manager)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                // bb8 connects lazily, so check out one connection to validate the settings
+                pool.get().await.map_err(|e| e.to_string())?;
+                DbPool::SQLServer(pool)
+            },
         };
 
         if !is_temp {
@@ -87,144 +253,289 @@ impl DatabaseManager {
         Ok(())
     }
 
-    pub async fn get_schema_info(&self, connection_uuid: &Uuid) -> Result<String, String> {
+    /// Whether `connect` finished for the connection. Doesn't wait for the lock, busy counts as not yet.
+    pub fn is_connected(&self, connection_uuid: &Uuid) -> bool {
+        self.connections.try_lock().is_ok_and(|connections| connections.contains_key(connection_uuid))
+    }
+
+    /// Clones the pool out of the map, so the lock isn't held while a query runs.
+    async fn pool(&self, connection_uuid: &Uuid) -> Result<DbPool, String> {
         let connections = self.connections.lock().await;
+        connections.get(connection_uuid).cloned().ok_or_else(|| "Connection not found".to_string())
+    }
+
+    pub async fn dialect(&self, connection_uuid: &Uuid) -> Result<&'static str, String> {
+        Ok(self.pool(connection_uuid).await?.db_type().dialect_name())
+    }
+
+    pub async fn get_schema(&self, connection_uuid: &Uuid) -> Result<DatabaseSchema, String> {
+        // Every dialect has its own introspector building the same model
+        match self.pool(connection_uuid).await? {
+            DbPool::MySQL(pool) => introspect::mysql::introspect(&pool).await,
+            DbPool::PostgreSQL(pool) => introspect::postgres::introspect(&pool).await,
+            DbPool::SQLite(pool) => introspect::sqlite::introspect(&pool).await,
+            DbPool::SQLServer(pool) => introspect::mssql::introspect(&pool).await,
+        }
+    }
 
-        let connection = connections.get(connection_uuid).ok_or_else(|| "Connection not found".to_string())?;
-        // Query schema information based on database type
-        match connection {
+    /// A cheap value that changes whenever the introspected schema would, to tell a cached schema is stale.
+    pub async fn schema_fingerprint(&self, connection_uuid: &Uuid) -> Result<String, String> {
+        match self.pool(connection_uuid).await? {
             DbPool::MySQL(pool) => {
-                // For MySQL, query information_schema for table and column information
-                let tables = sqlx::query(
-                    "SELECT table_name, table_comment FROM information_schema.tables
-                     WHERE table_schema = DATABASE() ORDER BY table_name"
+                let (columns, checksum, keys): (i64, i64, i64) = sqlx::query_as(
+                    "SELECT COUNT(*),
+                            CAST(COALESCE(SUM(CRC32(CONCAT_WS(':', table_name, column_name, column_type, is_nullable))), 0) AS SIGNED),
+                            (SELECT COUNT(*) FROM information_schema.statistics WHERE table_schema = DATABASE())
+                              + (SELECT COUNT(*) FROM information_schema.key_column_usage WHERE table_schema = DATABASE())
+                     FROM information_schema.columns
+                     WHERE table_schema = DATABASE()"
                 )
-                    .fetch_all(pool)
+                    .fetch_one(&pool)
                     .await
                     .map_err(|e| e.to_string())?;
-
-                let mut schema = String::new();
-
-                for table in tables {
-                    let table_name: String = table.get("table_name");
-                    schema.push_str(&format!("Table: {}\n", table_name));
-
-                    let columns = sqlx::query(
-                        "SELECT column_name, data_type, column_comment,
-                                is_nullable, column_key
-                         FROM information_schema.columns
-                         WHERE table_schema = DATABASE() AND table_name = ?
-                         ORDER BY ordinal_position"
-                    )
-                        .bind(&table_name)
-                        .fetch_all(pool)
-                        .await
-                        .map_err(|e| e.to_string())?;
-
-                    for column in columns {
-                        let column_name: String = column.get("column_name");
-                        let data_type: String = column.get("data_type");
-                        let is_nullable: String = column.get("is_nullable");
-                        let column_key: Option<String> = column.try_get("column_key").ok();
-
-                        schema.push_str(&format!(
-                            "  - {} ({}, {}{})\n",
-                            column_name,
-                            data_type,
-                            if is_nullable == "YES" { "NULL" } else { "NOT NULL" },
-                            if let Some(key) = column_key {
-                                if key == "PRI" { ", PRIMARY KEY" } else if key == "UNI" { ", UNIQUE" } else { "" }
-                            } else { "" }
-                        ));
-                    }
-
-                    schema.push('\n');
-                }
-
-                Ok(schema)
-            },
+                Ok(format!("{}:{}:{}", columns, checksum, keys))
+            }
             DbPool::PostgreSQL(pool) => {
-                // For PostgreSQL, query information schema for table and column information
-                let tables = sqlx::query(
-                    "SELECT table_name, obj_description(pgc.oid) as table_comment
-                     FROM pg_catalog.pg_class pgc
-                     JOIN information_schema.tables t ON pgc.relname = t.table_name
-                     WHERE t.table_schema = 'public' AND t.table_type = 'BASE TABLE'
-                     ORDER BY table_name"
+                sqlx::query_scalar(
+                    "SELECT md5(
+                        coalesce(string_agg(c.oid::text || ':' || c.relkind || ':' || a.attname || ':' || a.atttypid || ':' || a.attnotnull,
+                                            ',' ORDER BY c.oid, a.attnum), '')
+                        || (SELECT count(*) FROM pg_catalog.pg_constraint con
+                            JOIN pg_catalog.pg_namespace cn ON cn.oid = con.connamespace
+                            WHERE cn.nspname = ANY(current_schemas(false)))
+                        || (SELECT count(*) FROM pg_catalog.pg_index ix
+                            JOIN pg_catalog.pg_class ic ON ic.oid = ix.indexrelid
+                            JOIN pg_catalog.pg_namespace inn ON inn.oid = ic.relnamespace
+                            WHERE inn.nspname = ANY(current_schemas(false))))
+                     FROM pg_catalog.pg_attribute a
+                     JOIN pg_catalog.pg_class c ON c.oid = a.attrelid
+                     JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+                     WHERE n.nspname = ANY(current_schemas(false)) AND c.relkind IN ('r', 'p', 'v', 'm')
+                       AND a.attnum > 0 AND NOT a.attisdropped"
                 )
-                    .fetch_all(pool)
+                    .fetch_one(&pool)
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+            DbPool::SQLite(pool) => {
+                // Bumped by SQLite on every schema change
+                let version: i64 = sqlx::query_scalar("PRAGMA schema_version")
+                    .fetch_one(&pool)
                     .await
                     .map_err(|e| e.to_string())?;
-
-                let mut schema = String::new();
-
-                for table in tables {
-                    let table_name: String = table.get("table_name");
-                    schema.push_str(&format!("Table: {}\n", table_name));
-
-                    let columns = sqlx::query(
-                        "SELECT c.column_name, c.data_type, c.is_nullable,
-                                pg_catalog.col_description(format('%s.%s', c.table_schema, c.table_name)::regclass::oid, c.ordinal_position) as column_comment,
-                                tc.constraint_type
-                         FROM information_schema.columns c
-                         LEFT JOIN information_schema.constraint_column_usage ccu ON c.column_name = ccu.column_name AND c.table_name = ccu.table_name
-                         LEFT JOIN information_schema.table_constraints tc ON ccu.constraint_name = tc.constraint_name
-                         WHERE c.table_schema = 'public' AND c.table_name = $1
-                         ORDER BY c.ordinal_position"
-                    )
-                        .bind(&table_name)
-                        .fetch_all(pool)
-                        .await
-                        .map_err(|e| e.to_string())?;
-
-                    for column in columns {
-                        let column_name: String = column.get("column_name");
-                        let data_type: String = column.get("data_type");
-                        let is_nullable: String = column.get("is_nullable");
-                        let constraint_type: Option<String> = column.try_get("constraint_type").ok();
-
-                        schema.push_str(&format!(
-                            "  - {} ({}, {}{})\n",
-                            column_name,
-                            data_type,
-                            if is_nullable == "YES" { "NULL" } else { "NOT NULL" },
-                            if let Some(ctype) = constraint_type {
-                                if ctype == "PRIMARY KEY" { ", PRIMARY KEY" } else if ctype == "UNIQUE" { ", UNIQUE" } else { "" }
-                            } else { "" }
-                        ));
-                    }
-
-                    schema.push('\n');
-                }
-
-                Ok(schema)
+                Ok(version.to_string())
+            }
+            DbPool::SQLServer(pool) => {
+                let mut client = pool.get().await.map_err(|e| e.to_string())?;
+                let row = client.simple_query(
+                    "SELECT CONCAT(COUNT(*), ':', CONVERT(VARCHAR(33), MAX(modify_date), 126))
+                     FROM sys.objects WHERE is_ms_shipped = 0"
+                )
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .into_row()
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(row.and_then(|row| row.get::<&str, _>(0).map(|value| value.to_string())).unwrap_or_default())
             }
         }
     }
 
-    pub async fn execute_query(&self, connection_uuid: &Uuid, query: &str, offset: usize, limit: Option<usize>) -> Result<QueryResult, String> {
+    pub async fn execute_query(&self, connection_uuid: &Uuid, query: &str, offset: usize, limit: Option<usize>, sort: Option<&SortOrder>, cancel: &CancelToken) -> Result<QueryResult, String> {
         debug!("Start running query: {}", query);
-        let connections = self.connections.lock().await;
-        // Find the connection
-        let connection = connections.get(connection_uuid).ok_or_else(|| "Connection not found".to_string())?;
+        let connection = self.pool(connection_uuid).await?;
         let limit = limit.unwrap_or(PAGE_SIZE);
 
-        let query = query.trim_end_matches(';');
-        let count_query = format!("SELECT COUNT(*) FROM ({}) AS subquery", query);
-        let paginated_query = format!("SELECT * FROM ({}) AS subquery LIMIT {} OFFSET {}", query, limit, offset);
+        // Every query is parsed before it reaches the database, only pure reads are let through
+        ensure_read_only(query, &connection.db_type())?;
+
+        let query = query.trim().trim_end_matches(';');
+        let (count_query, paginated_query) = match &connection {
+            DbPool::SQLServer(_) => mssql_pagination(query, sort, offset, limit)?,
+            _ => {
+                // By position, result columns may be unnamed or share a name
+                let order_by = sort.map(|sort| format!("{}{}", sort.column + 1, if sort.descending { " DESC" } else { " ASC" }));
+                (
+                    format!("SELECT COUNT(*) FROM ({}) AS subquery", query),
+                    match &order_by {
+                        Some(order_by) => format!("SELECT * FROM ({}) AS subquery ORDER BY {} LIMIT {} OFFSET {}", query, order_by, limit, offset),
+                        None => format!("SELECT * FROM ({}) AS subquery LIMIT {} OFFSET {}", query, limit, offset),
+                    },
+                )
+            }
+        };
 
         debug!("Count query: {}", count_query);
-        debug!("Paginated query: {}", count_query);
+        debug!("Paginated query: {}", paginated_query);
         // Execute query based on database type
-        match connection {
+        // Server dialects run on one checked out session whose id is kept for `cancel_query`
+        match &connection {
             DbPool::MySQL(pool) => {
-                mysql_query(pool, count_query, paginated_query, offset, limit).await
+                let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+                let id: u64 = sqlx::query_scalar("SELECT CONNECTION_ID()")
+                    .fetch_one(&mut *conn)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let _session = cancel.attach(id as i64);
+                mysql_query(&mut conn, count_query, paginated_query, offset, limit).await
 
             },
             DbPool::PostgreSQL(pool) => {
-                postgres_query(pool, count_query, paginated_query, offset, limit).await
+                let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+                let pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+                    .fetch_one(&mut *conn)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let _session = cancel.attach(pid as i64);
+                postgres_query(&mut conn, count_query, paginated_query, offset, limit).await
+            },
+            DbPool::SQLite(pool) => {
+                sqlite_query(pool, count_query, paginated_query, offset, limit).await
+            },
+            DbPool::SQLServer(pool) => {
+                let mut conn = pool.get().await.map_err(|e| e.to_string())?;
+                let spid = conn.simple_query("SELECT CAST(@@SPID AS INT)")
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .into_row()
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .and_then(|row| row.get::<i32, _>(0));
+                let _session = spid.map(|spid| cancel.attach(spid as i64));
+                mssql_query(&mut conn, count_query, paginated_query, offset, limit).await
             }
         }
     }
+
+    /// Runs the query without pagination, handing every row to `sink` as it arrives.
+    pub async fn stream_query(&self, connection_uuid: &Uuid, query: &str, cancel: &CancelToken, sink: &mut dyn RowSink) -> Result<(), String> {
+        debug!("Start streaming query: {}", query);
+        let connection = self.pool(connection_uuid).await?;
+        ensure_read_only(query, &connection.db_type())?;
+        let query = query.trim().trim_end_matches(';');
+
+        match &connection {
+            DbPool::MySQL(pool) => {
+                let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+                let id: u64 = sqlx::query_scalar("SELECT CONNECTION_ID()")
+                    .fetch_one(&mut *conn)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let _session = cancel.attach(id as i64);
+                mysql_stream(&mut conn, query, sink).await
+            },
+            DbPool::PostgreSQL(pool) => {
+                let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+                let pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+                    .fetch_one(&mut *conn)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let _session = cancel.attach(pid as i64);
+                postgres_stream(&mut conn, query, sink).await
+            },
+            DbPool::SQLite(pool) => sqlite_stream(pool, query, sink).await,
+            DbPool::SQLServer(pool) => {
+                let mut conn = pool.get().await.map_err(|e| e.to_string())?;
+                let spid = conn.simple_query("SELECT CAST(@@SPID AS INT)")
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .into_row()
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .and_then(|row| row.get::<i32, _>(0));
+                let _session = spid.map(|spid| cancel.attach(spid as i64));
+                mssql_stream(&mut conn, query, sink).await
+            }
+        }
+    }
+
+    /// Stops the query started with `cancel` on the server side, to call before aborting its task.
+    /// Nothing is sent once the query ended. SQLite runs in process, there aborting the task is all it takes.
+    pub async fn cancel_query(&self, connection_uuid: &Uuid, cancel: &CancelToken) -> Result<(), String> {
+        let Some(backend_id) = cancel.backend_id() else {
+            return Ok(());
+        };
+        debug!("Cancelling query on backend {}", backend_id);
+        match self.pool(connection_uuid).await? {
+            DbPool::MySQL(pool) => {
+                sqlx::query(&format!("KILL QUERY {}", backend_id))
+                    .execute(&pool)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            DbPool::PostgreSQL(pool) => {
+                sqlx::query("SELECT pg_cancel_backend($1)")
+                    .bind(backend_id as i32)
+                    .execute(&pool)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            DbPool::SQLite(_) => {}
+            DbPool::SQLServer(pool) => {
+                // T-SQL can only kill the whole session, the pool drops it once it's broken
+                let mut conn = pool.get().await.map_err(|e| e.to_string())?;
+                conn.simple_query(format!("KILL {}", backend_id))
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{DbConnection, DbType};
+    use crate::db_element::db::{CancelToken, DatabaseManager, SortOrder};
+    use crate::db_element::value::CellValue;
+    use tempfile::tempdir;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn sqlite_file_is_queried_page_by_page() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let database = temp_dir.path().join("analysis.db");
+        let write = sqlx::SqlitePool::connect(&format!("sqlite://{}?mode=rwc", database.display())).await.unwrap();
+        sqlx::query("CREATE TABLE measures (id INTEGER PRIMARY KEY, label TEXT)").execute(&write).await.unwrap();
+        for i in 1..=5 {
+            sqlx::query("INSERT INTO measures (label) VALUES (?)").bind(format!("m{}", i)).execute(&write).await.unwrap();
+        }
+
+        // A file path instead of host, port and user
+        let connection = DbConnection {
+            uuid: Uuid::new_v4(),
+            name: "analysis".to_string(),
+            db_type: DbType::SQLite,
+            host: String::new(),
+            port: 0,
+            username: String::new(),
+            database: database.display().to_string(),
+            read_only: true,
+            statement_timeout: 0,
+            schemas: Vec::new(),
+        };
+        assert_eq!(connection.connection_string_template(), "sqlite://{database}?mode=ro");
+        let db_manager = DatabaseManager::new();
+        db_manager.connect(&connection, Some(String::new()), false).await.unwrap();
+
+        let cancel = CancelToken::default();
+        let page = db_manager.execute_query(&connection.uuid, "SELECT id, label FROM measures;", 2, Some(2), None, &cancel).await.unwrap();
+        assert_eq!(page.columns, vec!["id", "label"]);
+        assert_eq!(page.total_rows, 5);
+        assert_eq!(page.total_pages, 3);
+        assert_eq!(page.current_page, 2);
+        assert_eq!(page.rows, vec![
+            vec![CellValue::Integer(3), CellValue::Text("m3".to_string())],
+            vec![CellValue::Integer(4), CellValue::Text("m4".to_string())],
+        ]);
+
+        let sort = SortOrder { column: 0, descending: true };
+        let page = db_manager.execute_query(&connection.uuid, "SELECT id, label FROM measures", 0, Some(2), Some(&sort), &cancel).await.unwrap();
+        assert_eq!(page.rows[0][0], CellValue::Integer(5));
+
+        let schema = db_manager.get_schema(&connection.uuid).await.unwrap();
+        let table = schema.tables().find(|table| table.name == "measures").unwrap();
+        assert_eq!(table.columns.iter().map(|column| column.name.as_str()).collect::<Vec<_>>(), vec!["id", "label"]);
+    }
+}
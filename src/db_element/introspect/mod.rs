@@ -0,0 +1,91 @@
+pub mod mysql;
+pub mod postgres;
+pub mod sqlite;
+pub mod mssql;
+
+use std::collections::HashMap;
+use crate::db_element::schema::{ColumnInfo, DatabaseSchema, ForeignKey, Index, SchemaInfo, Table, TableKind};
+
+/// Collects the rows of the introspection queries into a `DatabaseSchema`.
+/// Columns, keys and indexes of tables that weren't added first are ignored.
+#[derive(Default)]
+pub struct SchemaBuilder {
+    tables: Vec<Table>,
+    positions: HashMap<(String, String), usize>,
+}
+
+impl SchemaBuilder {
+    pub fn add_table(&mut self, schema: &str, name: &str, kind: TableKind, comment: Option<String>) {
+        self.positions.insert((schema.to_string(), name.to_string()), self.tables.len());
+        self.tables.push(Table::new(schema, name, kind, non_empty(comment)));
+    }
+
+    pub fn table(&mut self, schema: &str, name: &str) -> Option<&mut Table> {
+        let position = *self.positions.get(&(schema.to_string(), name.to_string()))?;
+        Some(&mut self.tables[position])
+    }
+
+    pub fn add_column(&mut self, schema: &str, table: &str, column: ColumnInfo) {
+        if let Some(table) = self.table(schema, table) {
+            table.columns.push(ColumnInfo {
+                comment: non_empty(column.comment),
+                ..column
+            });
+        }
+    }
+
+    /// Adds one column of an index, columns have to arrive in key order.
+    pub fn add_index_column(&mut self, schema: &str, table: &str, index: &str, column: &str, primary: bool, unique: bool) {
+        let Some(table) = self.table(schema, table) else {
+            return;
+        };
+        if primary {
+            table.primary_key.push(column.to_string());
+            return;
+        }
+        match table.indexes.iter_mut().find(|i| i.name == index) {
+            Some(existing) => existing.columns.push(column.to_string()),
+            None => table.indexes.push(Index {
+                name: index.to_string(),
+                columns: vec![column.to_string()],
+                unique,
+            }),
+        }
+    }
+
+    /// Adds a foreign key, the pairs of a composite key arrive one at a time in key order.
+    pub fn add_foreign_key(&mut self, schema: &str, table: &str, fk: ForeignKey) {
+        let Some(table) = self.table(schema, table) else {
+            return;
+        };
+        match table.foreign_keys.iter_mut().find(|existing| existing.name == fk.name) {
+            Some(existing) => {
+                existing.columns.extend(fk.columns);
+                existing.referenced_columns.extend(fk.referenced_columns);
+            }
+            None => table.foreign_keys.push(fk),
+        }
+    }
+
+    pub fn build(self, qualify_names: bool) -> DatabaseSchema {
+        let mut schemas: Vec<SchemaInfo> = Vec::new();
+        for mut table in self.tables {
+            table.unique_keys = table.indexes.iter()
+                .filter(|index| index.unique)
+                .map(|index| index.columns.clone())
+                .collect();
+            match schemas.iter_mut().find(|schema| schema.name == table.schema) {
+                Some(schema) => schema.tables.push(table),
+                None => schemas.push(SchemaInfo {
+                    name: table.schema.clone(),
+                    tables: vec![table],
+                }),
+            }
+        }
+        DatabaseSchema { schemas, qualify_names }
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.trim().is_empty())
+}
@@ -0,0 +1,125 @@
+use tiberius::Row;
+use crate::db_element::db::MsSqlPool;
+use crate::db_element::introspect::SchemaBuilder;
+use crate::db_element::schema::{ColumnInfo, DatabaseSchema, ForeignKey, TableKind};
+
+pub async fn introspect(pool: &MsSqlPool) -> Result<DatabaseSchema, String> {
+    let mut client = pool.get().await.map_err(|e| e.to_string())?;
+    let mut builder = SchemaBuilder::default();
+
+    let mut fetch = async |query: &str| -> Result<Vec<Row>, String> {
+        client.simple_query(query)
+            .await
+            .map_err(|e| e.to_string())?
+            .into_first_result()
+            .await
+            .map_err(|e| e.to_string())
+    };
+
+    let tables = fetch(
+        "SELECT TABLE_SCHEMA, TABLE_NAME, TABLE_TYPE FROM INFORMATION_SCHEMA.TABLES
+         ORDER BY TABLE_SCHEMA, TABLE_NAME"
+    ).await?;
+
+    for table in tables {
+        let kind = if text(&table, "TABLE_TYPE") == "VIEW" { TableKind::View } else { TableKind::Table };
+        builder.add_table(&text(&table, "TABLE_SCHEMA"), &text(&table, "TABLE_NAME"), kind, None);
+    }
+
+    let columns = fetch(
+        "SELECT TABLE_SCHEMA, TABLE_NAME, COLUMN_NAME, DATA_TYPE, IS_NULLABLE, COLUMN_DEFAULT
+         FROM INFORMATION_SCHEMA.COLUMNS
+         ORDER BY TABLE_SCHEMA, TABLE_NAME, ORDINAL_POSITION"
+    ).await?;
+
+    for column in columns {
+        builder.add_column(&text(&column, "TABLE_SCHEMA"), &text(&column, "TABLE_NAME"), ColumnInfo {
+            name: text(&column, "COLUMN_NAME"),
+            data_type: text(&column, "DATA_TYPE"),
+            nullable: text(&column, "IS_NULLABLE") == "YES",
+            default: opt_text(&column, "COLUMN_DEFAULT"),
+            comment: None,
+        });
+    }
+
+    // Comments live in the MS_Description extended property, minor_id 0 is the table itself
+    let comments = fetch(
+        "SELECT s.name AS table_schema, o.name AS table_name, c.name AS column_name,
+                CAST(ep.value AS NVARCHAR(4000)) AS comment
+         FROM sys.extended_properties ep
+         JOIN sys.objects o ON o.object_id = ep.major_id
+         JOIN sys.schemas s ON s.schema_id = o.schema_id
+         LEFT JOIN sys.columns c ON c.object_id = ep.major_id AND c.column_id = ep.minor_id
+         WHERE ep.class = 1 AND ep.name = 'MS_Description'"
+    ).await?;
+
+    for comment in comments {
+        let Some(table) = builder.table(&text(&comment, "table_schema"), &text(&comment, "table_name")) else {
+            continue;
+        };
+        let value = opt_text(&comment, "comment");
+        match opt_text(&comment, "column_name") {
+            Some(column_name) => {
+                if let Some(column) = table.columns.iter_mut().find(|c| c.name == column_name) {
+                    column.comment = value;
+                }
+            }
+            None => table.comment = value,
+        }
+    }
+
+    let indexes = fetch(
+        "SELECT OBJECT_SCHEMA_NAME(i.object_id) AS table_schema, OBJECT_NAME(i.object_id) AS table_name,
+                i.name AS index_name, i.is_primary_key, i.is_unique,
+                COL_NAME(ic.object_id, ic.column_id) AS column_name
+         FROM sys.indexes i
+         JOIN sys.index_columns ic ON ic.object_id = i.object_id AND ic.index_id = i.index_id
+         WHERE i.name IS NOT NULL AND ic.is_included_column = 0
+           AND OBJECTPROPERTY(i.object_id, 'IsUserTable') = 1
+         ORDER BY table_schema, table_name, i.name, ic.key_ordinal"
+    ).await?;
+
+    for index in indexes {
+        builder.add_index_column(
+            &text(&index, "table_schema"),
+            &text(&index, "table_name"),
+            &text(&index, "index_name"),
+            &text(&index, "column_name"),
+            index.get::<bool, _>("is_primary_key").unwrap_or(false),
+            index.get::<bool, _>("is_unique").unwrap_or(false),
+        );
+    }
+
+    let foreign_keys = fetch(
+        "SELECT OBJECT_SCHEMA_NAME(fk.parent_object_id) AS from_schema,
+                OBJECT_NAME(fk.parent_object_id) AS from_table,
+                fk.name AS fk_name,
+                COL_NAME(fkc.parent_object_id, fkc.parent_column_id) AS from_column,
+                OBJECT_SCHEMA_NAME(fk.referenced_object_id) AS to_schema,
+                OBJECT_NAME(fk.referenced_object_id) AS to_table,
+                COL_NAME(fkc.referenced_object_id, fkc.referenced_column_id) AS to_column
+         FROM sys.foreign_keys fk
+         JOIN sys.foreign_key_columns fkc ON fk.object_id = fkc.constraint_object_id
+         ORDER BY from_schema, from_table, fk.name, fkc.constraint_column_id"
+    ).await?;
+
+    for fk in foreign_keys {
+        builder.add_foreign_key(&text(&fk, "from_schema"), &text(&fk, "from_table"), ForeignKey {
+            name: text(&fk, "fk_name"),
+            columns: vec![text(&fk, "from_column")],
+            referenced_schema: text(&fk, "to_schema"),
+            referenced_table: text(&fk, "to_table"),
+            referenced_columns: vec![text(&fk, "to_column")],
+        });
+    }
+
+    Ok(builder.build(true))
+}
+
+fn opt_text(row: &Row, column: &str) -> Option<String> {
+    row.get::<&str, _>(column).map(|value| value.to_string())
+}
+
+fn text(row: &Row, column: &str) -> String {
+    opt_text(row, column).unwrap_or_default()
+}
@@ -0,0 +1,116 @@
+use sqlx::mysql::MySqlRow;
+use sqlx::{MySqlPool, Row};
+use crate::db_element::introspect::SchemaBuilder;
+use crate::db_element::schema::{ColumnInfo, DatabaseSchema, ForeignKey, TableKind};
+
+/// Introspects the current database, which MySQL calls a schema.
+pub async fn introspect(pool: &MySqlPool) -> Result<DatabaseSchema, String> {
+    let mut builder = SchemaBuilder::default();
+
+    let tables = sqlx::query(
+        "SELECT table_schema AS table_schema, table_name AS table_name,
+                table_type AS table_type, table_comment AS table_comment
+         FROM information_schema.tables
+         WHERE table_schema = DATABASE()
+         ORDER BY table_name"
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for table in tables {
+        let kind = if text(&table, "table_type") == "BASE TABLE" { TableKind::Table } else { TableKind::View };
+        builder.add_table(&text(&table, "table_schema"), &text(&table, "table_name"), kind, opt_text(&table, "table_comment"));
+    }
+
+    let columns = sqlx::query(
+        "SELECT table_schema AS table_schema, table_name AS table_name, column_name AS column_name,
+                data_type AS data_type, is_nullable AS is_nullable,
+                column_default AS column_default, column_comment AS column_comment
+         FROM information_schema.columns
+         WHERE table_schema = DATABASE()
+         ORDER BY table_name, ordinal_position"
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for column in columns {
+        builder.add_column(&text(&column, "table_schema"), &text(&column, "table_name"), ColumnInfo {
+            name: text(&column, "column_name"),
+            data_type: text(&column, "data_type"),
+            nullable: text(&column, "is_nullable") == "YES",
+            default: opt_text(&column, "column_default"),
+            comment: opt_text(&column, "column_comment"),
+        });
+    }
+
+    let indexes = sqlx::query(
+        "SELECT table_schema AS table_schema, table_name AS table_name, index_name AS index_name,
+                CAST(non_unique AS SIGNED) AS non_unique, column_name AS column_name
+         FROM information_schema.statistics
+         WHERE table_schema = DATABASE()
+         ORDER BY table_name, index_name, seq_in_index"
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for index in indexes {
+        let name = text(&index, "index_name");
+        let non_unique: i64 = index.try_get("non_unique").unwrap_or(1);
+        builder.add_index_column(
+            &text(&index, "table_schema"),
+            &text(&index, "table_name"),
+            &name,
+            &text(&index, "column_name"),
+            name == "PRIMARY",
+            non_unique == 0,
+        );
+    }
+
+    let foreign_keys = sqlx::query(
+        "SELECT kcu.table_schema AS table_schema, kcu.table_name AS table_name,
+                kcu.constraint_name AS constraint_name, kcu.column_name AS column_name,
+                kcu.referenced_table_schema AS referenced_schema,
+                kcu.referenced_table_name AS referenced_table,
+                kcu.referenced_column_name AS referenced_column
+         FROM information_schema.key_column_usage kcu
+         JOIN information_schema.referential_constraints rc
+           ON rc.constraint_schema = kcu.constraint_schema
+          AND rc.constraint_name = kcu.constraint_name
+          AND rc.table_name = kcu.table_name
+         WHERE kcu.table_schema = DATABASE()
+         ORDER BY kcu.table_name, kcu.constraint_name, kcu.ordinal_position"
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for fk in foreign_keys {
+        builder.add_foreign_key(&text(&fk, "table_schema"), &text(&fk, "table_name"), ForeignKey {
+            name: text(&fk, "constraint_name"),
+            columns: vec![text(&fk, "column_name")],
+            referenced_schema: text(&fk, "referenced_schema"),
+            referenced_table: text(&fk, "referenced_table"),
+            referenced_columns: vec![text(&fk, "referenced_column")],
+        });
+    }
+
+    Ok(builder.build(false))
+}
+
+/// Some MySQL versions report information_schema text as binary, so accept both.
+fn opt_text(row: &MySqlRow, column: &str) -> Option<String> {
+    match row.try_get::<Option<String>, _>(column) {
+        Ok(value) => value,
+        Err(_) => row.try_get::<Option<Vec<u8>>, _>(column)
+            .ok()
+            .flatten()
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned()),
+    }
+}
+
+fn text(row: &MySqlRow, column: &str) -> String {
+    opt_text(row, column).unwrap_or_default()
+}
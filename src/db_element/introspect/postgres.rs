@@ -0,0 +1,126 @@
+use sqlx::{PgPool, Row};
+use crate::db_element::introspect::SchemaBuilder;
+use crate::db_element::schema::{ColumnInfo, DatabaseSchema, ForeignKey, TableKind};
+
+/// Introspects every schema on the search_path, tables are named `schema.table` since the
+/// same name can exist in several of them.
+pub async fn introspect(pool: &PgPool) -> Result<DatabaseSchema, String> {
+    let mut builder = SchemaBuilder::default();
+
+    let tables = sqlx::query(
+        "SELECT n.nspname AS table_schema, c.relname AS table_name, c.relkind::text AS kind,
+                obj_description(c.oid, 'pg_class') AS table_comment
+         FROM pg_catalog.pg_class c
+         JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+         WHERE n.nspname = ANY(current_schemas(false)) AND c.relkind IN ('r', 'p', 'v', 'm')
+         ORDER BY n.nspname, c.relname"
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for table in tables {
+        let schema: String = table.get("table_schema");
+        let name: String = table.get("table_name");
+        let kind: String = table.get("kind");
+        let kind = match kind.as_str() {
+            "v" => TableKind::View,
+            "m" => TableKind::MaterializedView,
+            _ => TableKind::Table,
+        };
+        builder.add_table(&schema, &name, kind, table.get("table_comment"));
+    }
+
+    let columns = sqlx::query(
+        "SELECT n.nspname AS table_schema, c.relname AS table_name, a.attname::text AS column_name,
+                pg_catalog.format_type(a.atttypid, a.atttypmod) AS data_type,
+                NOT a.attnotnull AS nullable,
+                pg_catalog.pg_get_expr(d.adbin, d.adrelid) AS column_default,
+                pg_catalog.col_description(c.oid, a.attnum) AS column_comment
+         FROM pg_catalog.pg_attribute a
+         JOIN pg_catalog.pg_class c ON c.oid = a.attrelid
+         JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+         LEFT JOIN pg_catalog.pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+         WHERE n.nspname = ANY(current_schemas(false)) AND c.relkind IN ('r', 'p', 'v', 'm')
+           AND a.attnum > 0 AND NOT a.attisdropped
+         ORDER BY n.nspname, c.relname, a.attnum"
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for column in columns {
+        let schema: String = column.get("table_schema");
+        let table: String = column.get("table_name");
+        builder.add_column(&schema, &table, ColumnInfo {
+            name: column.get("column_name"),
+            data_type: column.get("data_type"),
+            nullable: column.get("nullable"),
+            default: column.get("column_default"),
+            comment: column.get("column_comment"),
+        });
+    }
+
+    // Expression indexes have no column to report, the join on pg_attribute drops them
+    let indexes = sqlx::query(
+        "SELECT n.nspname AS table_schema, t.relname AS table_name, i.relname AS index_name,
+                ix.indisprimary AS is_primary, ix.indisunique AS is_unique,
+                array_agg(a.attname::text ORDER BY k.ord) AS columns
+         FROM pg_catalog.pg_index ix
+         JOIN pg_catalog.pg_class t ON t.oid = ix.indrelid
+         JOIN pg_catalog.pg_class i ON i.oid = ix.indexrelid
+         JOIN pg_catalog.pg_namespace n ON n.oid = t.relnamespace
+         CROSS JOIN LATERAL unnest(ix.indkey) WITH ORDINALITY AS k(attnum, ord)
+         JOIN pg_catalog.pg_attribute a ON a.attrelid = t.oid AND a.attnum = k.attnum
+         WHERE n.nspname = ANY(current_schemas(false))
+         GROUP BY n.nspname, t.relname, i.relname, ix.indisprimary, ix.indisunique
+         ORDER BY n.nspname, t.relname, i.relname"
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for index in indexes {
+        let schema: String = index.get("table_schema");
+        let table: String = index.get("table_name");
+        let name: String = index.get("index_name");
+        let columns: Vec<String> = index.get("columns");
+        for column in columns {
+            builder.add_index_column(&schema, &table, &name, &column, index.get("is_primary"), index.get("is_unique"));
+        }
+    }
+
+    // conkey and confkey are parallel arrays, unnesting them together keeps the column pairs aligned
+    let foreign_keys = sqlx::query(
+        "SELECT n.nspname AS table_schema, c.relname AS table_name, con.conname AS constraint_name,
+                a.attname::text AS column_name, rn.nspname AS referenced_schema,
+                rc.relname AS referenced_table, ra.attname::text AS referenced_column
+         FROM pg_catalog.pg_constraint con
+         JOIN pg_catalog.pg_class c ON c.oid = con.conrelid
+         JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+         JOIN pg_catalog.pg_class rc ON rc.oid = con.confrelid
+         JOIN pg_catalog.pg_namespace rn ON rn.oid = rc.relnamespace
+         CROSS JOIN LATERAL unnest(con.conkey, con.confkey) WITH ORDINALITY AS k(attnum, ref_attnum, ord)
+         JOIN pg_catalog.pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum
+         JOIN pg_catalog.pg_attribute ra ON ra.attrelid = con.confrelid AND ra.attnum = k.ref_attnum
+         WHERE con.contype = 'f' AND n.nspname = ANY(current_schemas(false))
+         ORDER BY n.nspname, c.relname, con.conname, k.ord"
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for fk in foreign_keys {
+        let schema: String = fk.get("table_schema");
+        let table: String = fk.get("table_name");
+        builder.add_foreign_key(&schema, &table, ForeignKey {
+            name: fk.get("constraint_name"),
+            columns: vec![fk.get("column_name")],
+            referenced_schema: fk.get("referenced_schema"),
+            referenced_table: fk.get("referenced_table"),
+            referenced_columns: vec![fk.get("referenced_column")],
+        });
+    }
+
+    Ok(builder.build(true))
+}
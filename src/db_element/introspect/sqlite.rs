@@ -0,0 +1,162 @@
+use sqlx::{Row, SqlitePool};
+use crate::db_element::introspect::SchemaBuilder;
+use crate::db_element::schema::{ColumnInfo, DatabaseSchema, ForeignKey, TableKind};
+
+const SCHEMA: &str = "main";
+
+pub async fn introspect(pool: &SqlitePool) -> Result<DatabaseSchema, String> {
+    let mut builder = SchemaBuilder::default();
+
+    let tables = sqlx::query(
+        "SELECT name, type FROM sqlite_master
+         WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%'
+         ORDER BY name"
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for table in tables {
+        let table_name: String = table.get("name");
+        let table_type: String = table.get("type");
+        let kind = if table_type == "view" { TableKind::View } else { TableKind::Table };
+        builder.add_table(SCHEMA, &table_name, kind, None);
+
+        let columns = sqlx::query(
+            "SELECT name, type, \"notnull\", dflt_value, pk
+             FROM pragma_table_info(?1)
+             ORDER BY cid"
+        )
+            .bind(&table_name)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut primary_key = Vec::new();
+        for column in columns {
+            let column_name: String = column.get("name");
+            let data_type: String = column.get("type");
+            let not_null: i64 = column.get("notnull");
+            let pk: i64 = column.get("pk");
+            if pk > 0 {
+                primary_key.push((pk, column_name.clone()));
+            }
+
+            builder.add_column(SCHEMA, &table_name, ColumnInfo {
+                name: column_name,
+                data_type: if data_type.is_empty() { "ANY".to_string() } else { data_type },
+                // SQLite allows NULL in primary keys of rowid tables, but nobody stores any
+                nullable: not_null == 0 && pk == 0,
+                default: column.get("dflt_value"),
+                comment: None,
+            });
+        }
+        // pk holds the position of the column in the key
+        primary_key.sort();
+        for (_, column) in primary_key {
+            builder.add_index_column(SCHEMA, &table_name, "PRIMARY", &column, true, true);
+        }
+
+        // The primary key is already covered by pragma_table_info
+        let indexes = sqlx::query(
+            "SELECT il.name AS index_name, il.\"unique\" AS is_unique, ii.name AS column_name
+             FROM pragma_index_list(?1) il
+             JOIN pragma_index_info(il.name) ii
+             WHERE il.origin != 'pk'
+             ORDER BY il.name, ii.seqno"
+        )
+            .bind(&table_name)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        for index in indexes {
+            let index_name: String = index.get("index_name");
+            let is_unique: i64 = index.get("is_unique");
+            let column_name: Option<String> = index.get("column_name");
+            // Expression indexes have no column name
+            if let Some(column_name) = column_name {
+                builder.add_index_column(SCHEMA, &table_name, &index_name, &column_name, false, is_unique == 1);
+            }
+        }
+
+        let foreign_keys = sqlx::query(
+            "SELECT id, seq, \"table\" AS referenced_table, \"from\" AS column_name, \"to\" AS referenced_column
+             FROM pragma_foreign_key_list(?1)
+             ORDER BY id, seq"
+        )
+            .bind(&table_name)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        for fk in foreign_keys {
+            let id: i64 = fk.get("id");
+            let seq: i64 = fk.get("seq");
+            let referenced_table: String = fk.get("referenced_table");
+            let column_name: String = fk.get("column_name");
+            let referenced_column: Option<String> = fk.get("referenced_column");
+            // Without a "to" column the key refers to the primary key of the parent table
+            let referenced_column = match referenced_column {
+                Some(column) => column,
+                None => sqlx::query_scalar(
+                    "SELECT name FROM pragma_table_info(?1) WHERE pk > 0 ORDER BY pk LIMIT 1 OFFSET ?2"
+                )
+                    .bind(&referenced_table)
+                    .bind(seq)
+                    .fetch_optional(pool)
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .unwrap_or_else(|| "rowid".to_string()),
+            };
+            builder.add_foreign_key(SCHEMA, &table_name, ForeignKey {
+                name: format!("fk_{}_{}", table_name, id),
+                columns: vec![column_name],
+                referenced_schema: SCHEMA.to_string(),
+                referenced_table,
+                referenced_columns: vec![referenced_column],
+            });
+        }
+    }
+
+    Ok(builder.build(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_introspect() {
+        // Every in-memory connection is its own database, so keep a single one
+        let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        sqlx::raw_sql(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL UNIQUE, name TEXT DEFAULT 'anonymous');
+             CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER NOT NULL REFERENCES users, total REAL);
+             CREATE INDEX orders_user ON orders(user_id);
+             CREATE VIEW big_orders AS SELECT * FROM orders WHERE total > 100;"
+        )
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let schema = introspect(&pool).await.unwrap();
+        let find_table = |name: &str| schema.tables().find(|table| table.name == name).unwrap();
+        let names: Vec<_> = schema.tables().map(|table| table.name.as_str()).collect();
+        assert_eq!(names, vec!["big_orders", "orders", "users"]);
+        assert_eq!(find_table("big_orders").kind, TableKind::View);
+
+        let users = find_table("users");
+        assert_eq!(users.primary_key, vec!["id"]);
+        assert_eq!(users.unique_keys, vec![vec!["email".to_string()]]);
+        assert!(!users.columns[1].nullable);
+        assert_eq!(users.columns[2].default.as_deref(), Some("'anonymous'"));
+
+        let orders = find_table("orders");
+        assert_eq!(orders.indexes[0].columns, vec!["user_id"]);
+        assert_eq!(orders.foreign_keys[0].columns, vec!["user_id"]);
+        assert_eq!(orders.foreign_keys[0].referenced_table, "users");
+        assert_eq!(orders.foreign_keys[0].referenced_columns, vec!["id"]);
+    }
+}
@@ -1,3 +1,9 @@
 pub mod db;
 pub mod chat;
-pub mod chat_storage;
\ No newline at end of file
+pub mod chat_storage;
+pub mod value;
+pub mod schema;
+pub mod introspect;
+pub mod schema_cache;
+pub mod query_log;
+pub mod saved_query;
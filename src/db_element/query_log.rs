@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// One statement run through `AppState::run_query`, successful or not.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QueryLogEntry {
+    pub id: Uuid,
+    pub connection_id: Uuid,
+    pub sql: String,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// Total rows of the result, not only the fetched page.
+    pub row_count: Option<usize>,
+    /// Page fetched, 0 for an export of every row.
+    pub page: usize,
+    pub error: Option<String>,
+}
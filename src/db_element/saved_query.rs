@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::config::DbType;
+
+/// A named query kept for later, may contain `:name` placeholders filled in before running.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SavedQuery {
+    pub id: Uuid,
+    pub connection_id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub sql: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SavedQuery {
+    pub fn new(connection_id: Uuid, name: String, sql: String) -> Self {
+        Self { id: Uuid::new_v4(), connection_id, name, tags: Vec::new(), sql, created_at: Utc::now() }
+    }
+
+    /// Placeholder names in order of first appearance.
+    pub fn params(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for (_, name) in placeholders(&self.sql) {
+            if !names.iter().any(|existing| existing == name) {
+                names.push(name.to_string());
+            }
+        }
+        names
+    }
+
+    /// The SQL with every placeholder replaced by a literal. Numbers are inserted as is,
+    /// anything else as a string quoted for `db_type`. Placeholders without a value are left alone.
+    pub fn bind(&self, values: &HashMap<String, String>, db_type: &DbType) -> String {
+        let mut sql = String::new();
+        let mut last = 0;
+        for (range, name) in placeholders(&self.sql) {
+            let Some(value) = values.get(name) else {
+                continue;
+            };
+            sql.push_str(&self.sql[last..range.start]);
+            let value = value.trim();
+            if is_number(value) {
+                sql.push_str(value);
+            } else {
+                sql.push_str(&db_type.quote_literal(value));
+            }
+            last = range.end;
+        }
+        sql.push_str(&self.sql[last..]);
+        sql
+    }
+}
+
+/// A plain decimal literal: optional sign, digits and an optional fraction. Leading zeros make it
+/// text, `007` is an id rather than 7, and `NaN`, `inf` or `1e5` would not be read as numbers.
+fn is_number(value: &str) -> bool {
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits, None),
+    };
+    let all_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    all_digits(integer)
+        && (integer == "0" || !integer.starts_with('0'))
+        && fraction.is_none_or(all_digits)
+}
+
+/// `:name` placeholders outside of string literals, quoted identifiers and comments.
+/// `::` casts and times like `'12:30'` are not placeholders.
+fn placeholders(sql: &str) -> Vec<(Range<usize>, &str)> {
+    let mut found = Vec::new();
+    let bytes = sql.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"' | b'`') => {
+                i += 1;
+                while i < bytes.len() {
+                    if bytes[i] == quote {
+                        // A doubled quote is an escaped one
+                        if bytes.get(i + 1) == Some(&quote) {
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = sql[i..].find('\n').map(|end| i + end).unwrap_or(bytes.len());
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = sql[i + 2..].find("*/").map(|end| i + end + 4).unwrap_or(bytes.len());
+            }
+            b':' if bytes.get(i + 1) == Some(&b':') => i += 2,
+            b':' if bytes.get(i + 1).is_some_and(|c| c.is_ascii_alphabetic() || *c == b'_') => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                found.push((start..i, &sql[start + 1..i]));
+            }
+            _ => i += 1,
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params_and_bind() {
+        let query = SavedQuery::new(
+            Uuid::new_v4(),
+            "Orders of a customer".to_string(),
+            "SELECT id::text, ':skipped' FROM orders -- :comment\nWHERE customer = :customer AND total > :min_total OR referrer = :customer".to_string(),
+        );
+        assert_eq!(query.params(), vec!["customer", "min_total"]);
+
+        let values = HashMap::from([
+            ("customer".to_string(), "O'Brien".to_string()),
+            ("min_total".to_string(), " 10.5 ".to_string()),
+        ]);
+        assert_eq!(
+            query.bind(&values, &DbType::PostgreSQL),
+            "SELECT id::text, ':skipped' FROM orders -- :comment\nWHERE customer = 'O''Brien' AND total > 10.5 OR referrer = 'O''Brien'",
+        );
+
+        let query = SavedQuery::new(Uuid::new_v4(), "Value".to_string(), "SELECT :value".to_string());
+        for (value, bound) in [
+            ("-3", "SELECT -3"),
+            ("0.25", "SELECT 0.25"),
+            ("007", "SELECT '007'"),
+            ("NaN", "SELECT 'NaN'"),
+            ("inf", "SELECT 'inf'"),
+            ("infinity", "SELECT 'infinity'"),
+            ("1e5", "SELECT '1e5'"),
+            ("1.", "SELECT '1.'"),
+            (".5", "SELECT '.5'"),
+        ] {
+            assert_eq!(query.bind(&HashMap::from([("value".to_string(), value.to_string())]), &DbType::PostgreSQL), bound);
+        }
+    }
+
+    #[test]
+    fn test_bind_quotes_per_dialect() {
+        let query = SavedQuery::new(Uuid::new_v4(), "Users".to_string(), "SELECT * FROM users WHERE name = :name".to_string());
+        let values = HashMap::from([("name".to_string(), "\\' OR 1=1 -- ".to_string())]);
+        assert_eq!(query.bind(&values, &DbType::MySQL), "SELECT * FROM users WHERE name = '\\\\'' OR 1=1 --'");
+        assert_eq!(query.bind(&values, &DbType::PostgreSQL), "SELECT * FROM users WHERE name = '\\'' OR 1=1 --'");
+        assert_eq!(query.bind(&values, &DbType::SQLServer), "SELECT * FROM users WHERE name = '\\'' OR 1=1 --'");
+
+        let values = HashMap::from([("name".to_string(), "C:\\temp\\O'Brien".to_string())]);
+        assert_eq!(query.bind(&values, &DbType::MySQL), "SELECT * FROM users WHERE name = 'C:\\\\temp\\\\O''Brien'");
+        assert_eq!(query.bind(&values, &DbType::SQLite), "SELECT * FROM users WHERE name = 'C:\\temp\\O''Brien'");
+    }
+}
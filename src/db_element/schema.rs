@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+/// Everything introspected from a database, grouped by schema.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseSchema {
+    pub schemas: Vec<SchemaInfo>,
+    /// Whether tables have to be written as `schema.table` in queries.
+    pub qualify_names: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SchemaInfo {
+    pub name: String,
+    pub tables: Vec<Table>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TableKind {
+    Table,
+    View,
+    MaterializedView,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Table {
+    pub schema: String,
+    pub name: String,
+    pub kind: TableKind,
+    pub comment: Option<String>,
+    pub columns: Vec<ColumnInfo>,
+    pub primary_key: Vec<String>,
+    pub unique_keys: Vec<Vec<String>>,
+    pub foreign_keys: Vec<ForeignKey>,
+    /// Secondary indexes, the primary key isn't repeated here.
+    pub indexes: Vec<Index>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    pub default: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForeignKey {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub referenced_schema: String,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Index {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+}
+
+impl DatabaseSchema {
+    pub fn tables(&self) -> impl Iterator<Item = &Table> {
+        self.schemas.iter().flat_map(|schema| schema.tables.iter())
+    }
+
+    /// Name of a table the way a query has to refer to it.
+    pub fn table_name(&self, schema: &str, name: &str) -> String {
+        if self.qualify_names {
+            format!("{}.{}", schema, name)
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+impl Table {
+    pub fn new(schema: &str, name: &str, kind: TableKind, comment: Option<String>) -> Self {
+        Self {
+            schema: schema.to_string(),
+            name: name.to_string(),
+            kind,
+            comment,
+            columns: Vec::new(),
+            primary_key: Vec::new(),
+            unique_keys: Vec::new(),
+            foreign_keys: Vec::new(),
+            indexes: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::db_element::chat_storage::ChatStorage;
+use crate::db_element::db::DatabaseManager;
+use crate::db_element::schema::DatabaseSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedSchema {
+    pub schema: DatabaseSchema,
+    /// `DatabaseManager::schema_fingerprint` at the time of introspection.
+    pub fingerprint: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Introspected schemas per connection, kept in memory and in the chat store so they survive restarts.
+/// Introspection only runs on first use and on an explicit refresh.
+pub struct SchemaCache {
+    db_manager: Arc<DatabaseManager>,
+    storage: Arc<ChatStorage>,
+    entries: Mutex<HashMap<Uuid, CachedSchema>>,
+    stale: Mutex<HashSet<Uuid>>,
+}
+
+impl SchemaCache {
+    pub fn new(db_manager: Arc<DatabaseManager>, storage: Arc<ChatStorage>) -> Self {
+        Self {
+            db_manager,
+            storage,
+            entries: Mutex::new(HashMap::new()),
+            stale: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// The cached schema, introspected if there is none yet.
+    /// A cached schema is still returned when it is stale, it's only flagged.
+    pub async fn get(&self, connection_uuid: &Uuid) -> Result<DatabaseSchema, String> {
+        match self.cached(connection_uuid) {
+            Some(cached) => {
+                if let Err(err) = self.check(connection_uuid).await {
+                    error!("Failed to check the schema fingerprint: {}", err);
+                }
+                Ok(cached.schema)
+            }
+            None => self.refresh(connection_uuid).await,
+        }
+    }
+
+    pub async fn refresh(&self, connection_uuid: &Uuid) -> Result<DatabaseSchema, String> {
+        // Fingerprint first, so a change during introspection shows up as stale next time
+        let fingerprint = self.db_manager.schema_fingerprint(connection_uuid).await?;
+        let schema = self.db_manager.get_schema(connection_uuid).await?;
+        debug!("Introspected schema of {} with fingerprint {}", connection_uuid, fingerprint);
+
+        let cached = CachedSchema {
+            schema: schema.clone(),
+            fingerprint,
+            fetched_at: Utc::now(),
+        };
+        if let Err(err) = self.storage.set_cached_schema(connection_uuid, &cached) {
+            error!("Failed to store the schema of {}: {}", connection_uuid, err);
+        }
+        self.entries.lock().unwrap().insert(*connection_uuid, cached);
+        self.stale.lock().unwrap().remove(connection_uuid);
+        Ok(schema)
+    }
+
+    /// Compares the fingerprint of the database with the cached one, returns whether the cache is stale.
+    pub async fn check(&self, connection_uuid: &Uuid) -> Result<bool, String> {
+        let Some(cached) = self.cached(connection_uuid) else {
+            return Ok(false);
+        };
+        let fingerprint = self.db_manager.schema_fingerprint(connection_uuid).await?;
+        let stale = fingerprint != cached.fingerprint;
+        let mut stale_connections = self.stale.lock().unwrap();
+        if stale {
+            stale_connections.insert(*connection_uuid);
+        } else {
+            stale_connections.remove(connection_uuid);
+        }
+        Ok(stale)
+    }
+
+    pub fn is_stale(&self, connection_uuid: &Uuid) -> bool {
+        self.stale.lock().unwrap().contains(connection_uuid)
+    }
+
+    /// Drops the cached schema, for when the connection settings change.
+    pub fn invalidate(&self, connection_uuid: &Uuid) {
+        self.entries.lock().unwrap().remove(connection_uuid);
+        self.stale.lock().unwrap().remove(connection_uuid);
+        if let Err(err) = self.storage.remove_cached_schema(connection_uuid) {
+            error!("Failed to remove the schema of {}: {}", connection_uuid, err);
+        }
+    }
+
+    fn cached(&self, connection_uuid: &Uuid) -> Option<CachedSchema> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(cached) = entries.get(connection_uuid) {
+            return Some(cached.clone());
+        }
+        let cached = self.storage.get_cached_schema(connection_uuid).unwrap_or_else(|err| {
+            error!("Failed to load the schema of {}: {}", connection_uuid, err);
+            None
+        })?;
+        entries.insert(*connection_uuid, cached.clone());
+        Some(cached)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DbConnection, DbType};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_refresh_on_schema_change() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let database = temp_dir.path().join("data.db");
+        let connection = DbConnection {
+            uuid: Uuid::new_v4(),
+            name: "local".to_string(),
+            db_type: DbType::SQLite,
+            host: String::new(),
+            port: 0,
+            username: String::new(),
+            database: format!("{}?mode=rwc", database.display()),
+            read_only: false,
+            statement_timeout: 0,
+            schemas: Vec::new(),
+        };
+        let db_manager = Arc::new(DatabaseManager::new());
+        db_manager.connect(&connection, Some(String::new()), false).await.unwrap();
+        let storage = Arc::new(ChatStorage::new(temp_dir.path().join("chat")).unwrap());
+        let cache = SchemaCache::new(db_manager.clone(), storage.clone());
+
+        let write = sqlx::SqlitePool::connect(&format!("sqlite://{}", database.display())).await.unwrap();
+        sqlx::query("CREATE TABLE users (id INTEGER PRIMARY KEY)").execute(&write).await.unwrap();
+
+        assert_eq!(cache.get(&connection.uuid).await.unwrap().tables().count(), 1);
+        assert!(storage.get_cached_schema(&connection.uuid).unwrap().is_some());
+        assert!(!cache.is_stale(&connection.uuid));
+
+        sqlx::query("CREATE TABLE orders (id INTEGER PRIMARY KEY)").execute(&write).await.unwrap();
+        // Still served from the cache, but flagged
+        assert_eq!(cache.get(&connection.uuid).await.unwrap().tables().count(), 1);
+        assert!(cache.is_stale(&connection.uuid));
+
+        assert_eq!(cache.refresh(&connection.uuid).await.unwrap().tables().count(), 2);
+        assert!(!cache.is_stale(&connection.uuid));
+    }
+}
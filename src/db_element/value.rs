@@ -0,0 +1,135 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use std::cmp::Ordering;
+use std::fmt;
+use uuid::Uuid;
+
+/// A single decoded cell of a `QueryResult`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    /// Exact numeric kept as text so no precision is lost.
+    Decimal(String),
+    Float(f64),
+    Text(String),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    Timestamp(NaiveDateTime),
+    TimestampTz(DateTime<Utc>),
+    Json(serde_json::Value),
+    Uuid(Uuid),
+    Bytes(Vec<u8>),
+    Array(Vec<CellValue>),
+}
+
+impl CellValue {
+    pub fn is_null(&self) -> bool {
+        matches!(self, CellValue::Null)
+    }
+
+    /// Numeric value of the cell, `None` for non-numeric cells.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            CellValue::Integer(v) => Some(*v as f64),
+            CellValue::Float(v) => Some(*v),
+            CellValue::Decimal(v) => v.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Order for sorting result rows: NULLs last, numbers by value, anything else by its text.
+    pub fn sort_cmp(&self, other: &CellValue) -> Ordering {
+        match (self, other) {
+            (CellValue::Null, CellValue::Null) => Ordering::Equal,
+            (CellValue::Null, _) => Ordering::Greater,
+            (_, CellValue::Null) => Ordering::Less,
+            _ => match (self.as_f64(), other.as_f64()) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                _ => self.to_string().cmp(&other.to_string()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for CellValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellValue::Null => write!(f, "NULL"),
+            CellValue::Bool(v) => write!(f, "{}", v),
+            CellValue::Integer(v) => write!(f, "{}", v),
+            CellValue::Decimal(v) => write!(f, "{}", v),
+            CellValue::Float(v) => write!(f, "{}", v),
+            CellValue::Text(v) => write!(f, "{}", v),
+            CellValue::Date(v) => write!(f, "{}", v),
+            CellValue::Time(v) => write!(f, "{}", v),
+            CellValue::Timestamp(v) => write!(f, "{}", v),
+            CellValue::TimestampTz(v) => write!(f, "{}", v.to_rfc3339()),
+            CellValue::Json(v) => write!(f, "{}", v),
+            CellValue::Uuid(v) => write!(f, "{}", v),
+            CellValue::Bytes(v) => {
+                // Only a short prefix, blobs can be large
+                write!(f, "0x")?;
+                for byte in v.iter().take(32) {
+                    write!(f, "{:02x}", byte)?;
+                }
+                if v.len() > 32 {
+                    write!(f, "… ({} bytes)", v.len())?;
+                }
+                Ok(())
+            }
+            CellValue::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(CellValue::Null.to_string(), "NULL");
+        assert_eq!(CellValue::Text("NULL".to_string()).to_string(), "NULL");
+        assert_ne!(CellValue::Null, CellValue::Text("NULL".to_string()));
+        assert_eq!(CellValue::Bytes(vec![0xde, 0xad]).to_string(), "0xdead");
+        assert_eq!(
+            CellValue::Array(vec![CellValue::Integer(1), CellValue::Null]).to_string(),
+            "[1, NULL]"
+        );
+    }
+
+    #[test]
+    fn test_as_f64() {
+        assert_eq!(CellValue::Integer(3).as_f64(), Some(3.0));
+        assert_eq!(CellValue::Decimal("12.50".to_string()).as_f64(), Some(12.5));
+        assert_eq!(CellValue::Text("12".to_string()).as_f64(), None);
+    }
+
+    #[test]
+    fn test_sort_cmp() {
+        let mut values = vec![
+            CellValue::Null,
+            CellValue::Integer(10),
+            CellValue::Decimal("9.5".to_string()),
+            CellValue::Float(-1.0),
+        ];
+        values.sort_by(|a, b| a.sort_cmp(b));
+        assert_eq!(values, vec![
+            CellValue::Float(-1.0),
+            CellValue::Decimal("9.5".to_string()),
+            CellValue::Integer(10),
+            CellValue::Null,
+        ]);
+        assert_eq!(CellValue::Text("b".to_string()).sort_cmp(&CellValue::Text("a".to_string())), Ordering::Greater);
+    }
+}
@@ -1,12 +1,14 @@
 use log::debug;
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use crate::llm::llm::ContentResponse;
+use crate::config::LLMConfig;
+use crate::llm::llm::{ChatTurn, LlmProvider};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ClaudeRequest {
     pub model: String,
+    pub system: String,
     pub messages: Vec<Message>,
     pub max_tokens: u32,
     pub temperature: f32,
@@ -40,9 +42,30 @@ pub struct Message {
     content: String,
 }
 
-pub async fn llm_request(api_key: String, client: &Client, model: String, user_query: &str, schema_info: &str) -> Result<Value, String> {
-    let claude_prompt = format!(
-        r#"
+pub struct Claude;
+
+impl LlmProvider for Claude {
+    fn id(&self) -> &'static str {
+        "Claude"
+    }
+
+    fn name(&self) -> &'static str {
+        "Claude"
+    }
+
+    fn models(&self) -> Vec<&'static str> {
+        Model::variants_name()
+    }
+
+    fn authorize(&self, request: RequestBuilder, api_key: &str) -> RequestBuilder {
+        request
+            .header("x-api-key", api_key)
+            .header("anthropic-version", "2023-06-01")
+    }
+
+    fn build_request(&self, client: &Client, config: &LLMConfig, turns: &[ChatTurn], schema_info: &str, dialect: &str) -> RequestBuilder {
+        let claude_prompt = format!(
+            r#"
     You are a helpful database assistant. Convert natural language queries to SQL.
     Do not include any explanations. Always return a JSON array where each object follows this format:
 
@@ -53,7 +76,8 @@ pub async fn llm_request(api_key: String, client: &Client, model: String, user_q
         }},
         {{
             "type": "query",
-            "message": "SELECT * FROM orders WHERE user_id = 1;"
+            "message": "SELECT region, SUM(total) AS revenue FROM orders GROUP BY region;",
+            "visualization": {{ "chart": "bar", "x": "region", "y": "revenue", "series": null, "title": "Revenue by region" }}
         }}
     ]
     OR
@@ -64,57 +88,55 @@ pub async fn llm_request(api_key: String, client: &Client, model: String, user_q
         }}
     ]
 
+    The database is {}. Write SQL for that dialect.
+
     Use the following database schema information:
     {}
 
     - Only write SQL that selects data (no INSERT, UPDATE, DELETE, etc.).
+    - Join tables on the columns listed under Relationships instead of guessing join columns.
     - Return multiple queries as separate objects in the JSON array.
+    - When a chart shows a query's answer better than a table, add a "visualization" object to it: "chart" is one of
+      line, bar, scatter, histogram or pie, "x", "y" and "series" are names of columns the query returns ("series" may be null),
+      "y" must be numeric. Leave "visualization" out otherwise.
     - Ensure the response is valid JSON, without additional explanations or text.
-
-    Here is the user's request: {}
+    - Earlier messages are the conversation so far, answer the last user message.
     "#,
-        schema_info,
-        user_query
-    );
-
-
-    let messages = vec![
-        Message {
-            role: "user".to_string(),
-            content: claude_prompt,
-        },
-    ];
-
-    let request = ClaudeRequest {
-        model,
-        messages,
-        max_tokens: 1000,
-        temperature: 0.0, // Use low temperature for deterministic results
-    };
-
-
-    debug!("Sending request to Claude: {:?}", request);
-    let response = client
-        .post("https://api.anthropic.com/v1/messages")
-        .header("x-api-key", api_key)
-        .header("anthropic-version", "2023-06-01")
-        .header("content-type", "application/json")
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let response_json: Value = response.json().await.map_err(|e| e.to_string())?;
-    debug!("Claude response: {:?}", response_json);
-    Ok(response_json)
+            dialect,
+            schema_info
+        );
 
-}
+        self.build_text_request(client, config, &claude_prompt, turns)
+    }
+
+    fn build_text_request(&self, client: &Client, config: &LLMConfig, system: &str, turns: &[ChatTurn]) -> RequestBuilder {
+        let messages = turns
+            .iter()
+            .map(|turn| Message {
+                role: turn.role.as_str().to_string(),
+                content: turn.content.clone(),
+            })
+            .collect();
+
+        let request = ClaudeRequest {
+            model: config.model.clone(),
+            system: system.to_string(),
+            messages,
+            max_tokens: 1000,
+            temperature: 0.0, // Use low temperature for deterministic results
+        };
+
+        debug!("Sending request to Claude: {:?}", request);
+        client
+            .post("https://api.anthropic.com/v1/messages")
+            .header("content-type", "application/json")
+            .json(&request)
+    }
 
-pub fn parse_content(response_json: Value) -> Result<Vec<ContentResponse>, String> {
-    if let Some(content) = response_json["content"][0]["text"].as_str() {
-        let parsed: Vec<ContentResponse> = serde_json::from_str(content).map_err(|e| e.to_string())?;
-        Ok(parsed)
-    } else {
-        Err("message does not contain content".to_string())
+    fn parse_text(&self, response_json: Value) -> Result<String, String> {
+        response_json["content"][0]["text"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| "message does not contain content".to_string())
     }
-}
\ No newline at end of file
+}
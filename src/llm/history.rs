@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use serde_json::json;
+use uuid::Uuid;
+use crate::db_element::chat::{Message, MessageMeta, Sender};
+use crate::llm::llm::{ChatTurn, Role};
+
+/// Rough token estimate, good enough to keep the prompt within budget.
+fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Appends a turn, merging it into the previous one when both come from the same role.
+pub fn push_turn(turns: &mut Vec<ChatTurn>, role: Role, content: String) {
+    match turns.last_mut() {
+        Some(last) if last.role == role => {
+            last.content.push_str("\n\n");
+            last.content.push_str(&content);
+        }
+        _ => turns.push(ChatTurn { role, content }),
+    }
+}
+
+/// Turns one user question and the system messages answering it into role tagged turns.
+fn exchange_turns(exchange: &[&Message], meta: &HashMap<Uuid, MessageMeta>) -> Vec<ChatTurn> {
+    let mut turns = Vec::new();
+    let (question, answers) = exchange.split_first().expect("An exchange always starts with a question");
+
+    // SQL sent by the user was typed in the console rather than asked for
+    let question_text = if question.is_sql {
+        format!("I ran this query myself in the SQL console:\n{}", question.content)
+    } else {
+        question.content.clone()
+    };
+    push_turn(&mut turns, Role::User, question_text);
+
+    if !answers.is_empty() {
+        let responses: Vec<_> = answers
+            .iter()
+            .map(|message| {
+                let mut response = json!({
+                    "type": if message.is_sql { "query" } else { "clarification" },
+                    "message": message.content,
+                });
+                if let Some(visualization) = meta.get(&message.uuid).and_then(|meta| meta.visualization.as_ref()) {
+                    response["visualization"] = json!(visualization);
+                }
+                response
+            })
+            .collect();
+        push_turn(&mut turns, Role::Assistant, json!(responses).to_string());
+    }
+
+    let results: Vec<String> = exchange
+        .iter()
+        .filter_map(|message| {
+            let execution = meta.get(&message.uuid)?.execution.as_ref()?;
+            Some(if execution.success {
+                format!("- `{}` ran successfully and returned {} rows.", message.content, execution.row_count)
+            } else {
+                format!("- `{}` failed: {}", message.content, execution.error.as_deref().unwrap_or("unknown error"))
+            })
+        })
+        .collect();
+    if !results.is_empty() {
+        push_turn(&mut turns, Role::User, format!("Execution results of the previous queries:\n{}", results.join("\n")));
+    }
+
+    turns
+}
+
+/// Builds the previous turns of a conversation, keeping at most `max_turns` questions and
+/// roughly `max_tokens` tokens. The oldest turns are dropped first.
+pub fn build_history(messages: &[Message], meta: &HashMap<Uuid, MessageMeta>, max_turns: usize, max_tokens: usize) -> Vec<ChatTurn> {
+    let mut exchanges: Vec<Vec<&Message>> = Vec::new();
+    for message in messages {
+        if meta.get(&message.uuid).is_some_and(|meta| meta.note || meta.explains.is_some()) {
+            continue;
+        }
+        match message.sender {
+            Sender::User => exchanges.push(vec![message]),
+            // System messages before the first question have nothing to answer
+            Sender::System => if let Some(exchange) = exchanges.last_mut() {
+                exchange.push(message);
+            },
+        }
+    }
+
+    let mut kept = Vec::new();
+    let mut used_tokens = 0;
+    for exchange in exchanges.iter().rev().take(max_turns) {
+        let turns = exchange_turns(exchange, meta);
+        let tokens: usize = turns.iter().map(|turn| estimate_tokens(&turn.content)).sum();
+        if used_tokens + tokens > max_tokens {
+            break;
+        }
+        used_tokens += tokens;
+        kept.push(turns);
+    }
+
+    let mut history = Vec::new();
+    for turns in kept.into_iter().rev() {
+        for turn in turns {
+            push_turn(&mut history, turn.role, turn.content);
+        }
+    }
+    history
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::db_element::chat::{Message, MessageMeta, QueryExecution, Sender};
+    use crate::llm::history::build_history;
+    use crate::llm::llm::Role;
+
+    fn conversation() -> Vec<Message> {
+        vec![
+            Message::new(Sender::User, "How many users signed up?".to_string(), false),
+            Message::new(Sender::System, "SELECT COUNT(*) FROM users;".to_string(), true),
+            Message::new(Sender::User, "Now group that by month".to_string(), false),
+            Message::new(Sender::System, "Which date column should I use?".to_string(), false),
+        ]
+    }
+
+    #[test]
+    fn history_is_role_tagged() {
+        let messages = conversation();
+        let mut meta = HashMap::new();
+        meta.insert(messages[1].uuid, MessageMeta {
+            execution: Some(QueryExecution { success: true, row_count: 1, error: None }),
+            ..Default::default()
+        });
+
+        let history = build_history(&messages, &meta, 10, 10_000);
+        let roles: Vec<_> = history.iter().map(|turn| turn.role.clone()).collect();
+
+        assert_eq!(roles, vec![Role::User, Role::Assistant, Role::User, Role::Assistant]);
+        assert!(history[1].content.contains("\"type\":\"query\""));
+        // The execution result is merged with the follow up question
+        assert!(history[2].content.contains("ran successfully and returned 1 rows"));
+        assert!(history[2].content.ends_with("Now group that by month"));
+        assert!(history[3].content.contains("\"type\":\"clarification\""));
+    }
+
+    #[test]
+    fn history_is_trimmed_from_the_oldest_turn() {
+        let messages = conversation();
+
+        let history = build_history(&messages, &HashMap::new(), 1, 10_000);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].content, "Now group that by month");
+
+        let history = build_history(&messages, &HashMap::new(), 10, 40);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].content, "Now group that by month");
+
+        assert!(build_history(&messages, &HashMap::new(), 10, 1).is_empty());
+    }
+
+    #[test]
+    fn notes_are_left_out() {
+        let mut messages = conversation();
+        let note = Message::new(Sender::System, "Query failed, asking the LLM for a correction".to_string(), false);
+        let mut meta = HashMap::new();
+        meta.insert(note.uuid, MessageMeta { note: true, ..Default::default() });
+        messages.insert(2, note);
+
+        let history = build_history(&messages, &meta, 10, 10_000);
+
+        assert_eq!(history.len(), 4);
+        assert!(history.iter().all(|turn| !turn.content.contains("asking the LLM")));
+    }
+
+    #[test]
+    fn console_queries_are_user_turns() {
+        let mut messages = conversation();
+        let console = Message::new(Sender::User, "SELECT * FROM users LIMIT 5".to_string(), true);
+        let mut meta = HashMap::new();
+        meta.insert(console.uuid, MessageMeta {
+            execution: Some(QueryExecution { success: true, row_count: 5, error: None }),
+            ..Default::default()
+        });
+        messages.push(console);
+
+        let history = build_history(&messages, &meta, 10, 10_000);
+        let last = history.last().unwrap();
+
+        assert_eq!(last.role, Role::User);
+        assert!(last.content.contains("in the SQL console:\nSELECT * FROM users LIMIT 5"));
+        assert!(last.content.contains("ran successfully and returned 5 rows"));
+    }
+}
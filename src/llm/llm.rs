@@ -1,25 +1,102 @@
-use log::debug;
+use log::{debug, error};
 use crate::config::LLMConfig;
-use crate::llm::{claude, openai};
+use std::collections::HashMap;
+use crate::db_element::chat::{Message, MessageMeta, VisualizationSpec};
+use crate::db_element::db::QueryResult;
+use crate::db_element::schema::DatabaseSchema;
+use crate::llm::schema_pruning::prune_schema;
+use crate::llm::result_prompt::render_results;
+use crate::llm::schema_prompt::render_schema;
+use crate::llm::{claude, history, openai, openai_compatible};
 use crate::security::SecureStorage;
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Clone, PartialEq)]
-pub enum Provider {
-    OpenAI,
-    Claude,
+const EXPLAIN_PROMPT: &str = "You are a helpful data analyst. Summarize the result of a SQL query for someone who \
+did not write it, in plain language and in a few sentences. Point out the main figures, trends and anything unusual, \
+using only the statistics and rows given. Do not include SQL, JSON or markdown tables.";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Role {
+    User,
+    Assistant,
 }
 
-impl Provider {
-    pub fn name(&self) -> &'static str {
+impl Role {
+    pub fn as_str(&self) -> &'static str {
         match self {
-            Provider::OpenAI => "OpenAI",
-            Provider::Claude => "Claude",
+            Role::User => "user",
+            Role::Assistant => "assistant",
         }
     }
 }
 
+/// One message of the conversation sent to the LLM.
+#[derive(Clone, Debug)]
+pub struct ChatTurn {
+    pub role: Role,
+    pub content: String,
+}
+
+/// A LLM backend. Each implementation lives in its own module and is listed in `providers()`.
+pub trait LlmProvider: Send + Sync {
+    /// Stable identifier, persisted in the config file.
+    fn id(&self) -> &'static str;
+
+    /// Name shown in the settings screen.
+    fn name(&self) -> &'static str;
+
+    /// Models offered in the settings screen. An empty list lets the user type any model name.
+    fn models(&self) -> Vec<&'static str>;
+
+    /// Whether the provider can only be used with an API key.
+    fn requires_api_key(&self) -> bool {
+        true
+    }
+
+    /// Base URL used when `LLMConfig::base_url` is empty, `None` if the endpoint is fixed.
+    fn default_base_url(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Adds the authentication headers to a request.
+    fn authorize(&self, request: RequestBuilder, api_key: &str) -> RequestBuilder;
+
+    /// Builds the request asking the model to answer the last user turn with SQL.
+    fn build_request(&self, client: &Client, config: &LLMConfig, turns: &[ChatTurn], schema_info: &str, dialect: &str) -> RequestBuilder;
+
+    /// Builds a request answering the last user turn with free text, following the `system` instructions.
+    fn build_text_request(&self, client: &Client, config: &LLMConfig, system: &str, turns: &[ChatTurn]) -> RequestBuilder;
+
+    /// Extracts the text of the answer from the provider response.
+    fn parse_text(&self, response_json: Value) -> Result<String, String>;
+
+    /// Extracts the generated queries from the provider response.
+    fn parse_content(&self, response_json: Value) -> Result<Vec<ContentResponse>, String> {
+        serde_json::from_str(&self.parse_text(response_json)?).map_err(|e| e.to_string())
+    }
+}
+
+pub fn providers() -> Vec<&'static dyn LlmProvider> {
+    vec![&claude::Claude, &openai::OpenAI, &openai_compatible::OpenAICompatible]
+}
+
+pub fn find_provider(id: &str) -> Option<&'static dyn LlmProvider> {
+    providers().into_iter().find(|provider| provider.id() == id)
+}
+
+/// The API key saved for `provider`. The single key saved by older versions was entered for a
+/// hosted provider, it's never handed to one that can point at any server.
+pub fn stored_api_key(provider: &dyn LlmProvider) -> Option<String> {
+    match SecureStorage::get_api_key(provider.id()) {
+        Ok(key) => Some(key),
+        Err(_) if provider.requires_api_key() => SecureStorage::get_legacy_api_key().ok(),
+        Err(_) => None,
+    }
+}
+
 #[derive(Clone)]
 pub struct LLMClient {
     client: Client,
@@ -33,45 +110,81 @@ impl LLMClient {
         }
     }
 
-    pub async fn generate_sql(&self, user_query: &str, schema_info: &str) -> Result<Vec<ContentResponse>, String> {
+    /// Previous turns of the conversation, trimmed to the configured budget.
+    pub fn conversation_history(&self, messages: &[Message], meta: &HashMap<Uuid, MessageMeta>) -> Vec<ChatTurn> {
+        history::build_history(messages, meta, self.config.history_turns, self.config.history_tokens)
+    }
+
+    pub async fn generate_sql(&self, history: &[ChatTurn], user_query: &str, schema_info: &str, dialect: &str) -> Result<Vec<ContentResponse>, String> {
+        let provider = self.provider()?;
+        let mut turns = history.to_vec();
+        history::push_turn(&mut turns, Role::User, user_query.to_string());
+
+        let request = provider.build_request(&self.client, &self.config, &turns, schema_info, dialect);
+        provider.parse_content(self.send(provider, request).await?)
+    }
+
+    /// Asks the model for a plain-language summary of a query result.
+    pub async fn explain_results(&self, query: &str, data: &QueryResult) -> Result<String, String> {
+        let provider = self.provider()?;
+        let turns = [ChatTurn { role: Role::User, content: render_results(query, data) }];
+
+        let request = provider.build_text_request(&self.client, &self.config, EXPLAIN_PROMPT, &turns);
+        let text = provider.parse_text(self.send(provider, request).await?)?;
+        Ok(text.trim().to_string())
+    }
+
+    fn provider(&self) -> Result<&'static dyn LlmProvider, String> {
+        let provider_id = self.config.provider.clone().ok_or_else(|| "LLM configuration missing".to_string())?;
+        find_provider(&provider_id).ok_or_else(|| format!("Unknown LLM provider '{}'", provider_id))
+    }
+
+    /// Authorizes and sends a request, returning the provider response.
+    async fn send(&self, provider: &dyn LlmProvider, request: RequestBuilder) -> Result<Value, String> {
         // Retrieve the API key securely
-        let api_key = match SecureStorage::get_api_key() {
-            Ok(key) => key,
-            Err(_) => {
+        let api_key = match stored_api_key(provider) {
+            Some(key) => key,
+            None if !provider.requires_api_key() => "".to_string(),
+            None => {
                 debug!("No API key found in storage");
                 return Err("API key not found".to_string())
             },
         };
 
-        let provider = self.config.provider.clone().expect("LLM configuration missing");
+        let response = provider.authorize(request, &api_key)
+            .send()
+            .await
+            .map_err(|e| {
+                error!("Failed to send request to {}: {}", provider.name(), e);
+                "Failed to generate LLM response".to_string()
+            })?;
 
-        let res = match provider {
-            Provider::Claude => {
-                claude::llm_request(api_key, &self.client, self.config.model.clone(), user_query, schema_info).await
-            },
-            Provider::OpenAI => {
-                openai::llm_request(api_key, &self.client, self.config.model.clone(), user_query, schema_info).await
-            }
-        };
+        let response_json: Value = response.json().await.map_err(|e| {
+            error!("Failed to parse response {}", e);
+            "Failed to generate LLM response".to_string()
+        })?;
 
-        let response_json = match res {
-            Ok(json) => json,
-            Err(_) => {
-                return Err("Failed to generate LLM response".to_string());
-            }
-        };
+        debug!("{} response data: {:?}", provider.name(), response_json);
+        Ok(response_json)
+    }
 
-        debug!("response data: {:?}", response_json);
+    /// Schema text for a prompt about `question`, pruned to the relevant tables for big schemas.
+    pub fn schema_context(&self, schema: &DatabaseSchema, question: &str, history: &[ChatTurn]) -> String {
+        render_schema(&prune_schema(schema, question, history, self.config.max_schema_tables))
+    }
 
-        match provider {
-            Provider::Claude => {
-                claude::parse_content(response_json)
-            },
-            Provider::OpenAI => {
-               openai::parse_content(response_json)
-            }
-        }
+    pub fn max_correction_attempts(&self) -> usize {
+        self.config.max_correction_attempts
+    }
 
+    /// Sends a failed query and the database error back to the model and asks for a fixed query.
+    pub async fn correct_sql(&self, history: &[ChatTurn], failed_query: &str, error: &str, schema_info: &str, dialect: &str) -> Result<Vec<ContentResponse>, String> {
+        let request = format!(
+            "The query below failed.\n\nQuery:\n{}\n\nDatabase error:\n{}\n\nReturn a corrected query in the same JSON format.",
+            failed_query,
+            error
+        );
+        self.generate_sql(history, &request, schema_info, dialect).await
     }
 }
 
@@ -86,5 +199,46 @@ pub enum ResponseType {
 pub struct ContentResponse {
     pub r#type: ResponseType,
     pub message: String,
+    #[serde(default, deserialize_with = "lenient_visualization")]
+    pub visualization: Option<VisualizationSpec>,
 }
 
+/// A malformed suggestion is dropped rather than failing the whole response.
+fn lenient_visualization<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<VisualizationSpec>, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::llm::{find_provider, providers, ContentResponse};
+
+    #[test]
+    fn visualization_is_optional() {
+        let parsed: Vec<ContentResponse> = serde_json::from_str(r#"[
+            {"type": "query", "message": "SELECT 1", "visualization": {"chart": "bar", "x": "region", "y": "revenue", "title": "Revenue"}},
+            {"type": "query", "message": "SELECT 2", "visualization": "a bar chart"},
+            {"type": "clarification", "message": "Which year?"}
+        ]"#).unwrap();
+        let visualization = parsed[0].visualization.as_ref().unwrap();
+        assert_eq!(visualization.chart, "bar");
+        assert_eq!(visualization.series, None);
+        assert_eq!(visualization.title.as_deref(), Some("Revenue"));
+        assert!(parsed[1].visualization.is_none());
+        assert!(parsed[2].visualization.is_none());
+    }
+
+    #[test]
+    fn provider_registry() {
+        let providers = providers();
+        for provider in &providers {
+            let found = find_provider(provider.id()).expect("Provider should be registered");
+            assert_eq!(found.name(), provider.name());
+            assert_eq!(providers.iter().filter(|p| p.id() == provider.id()).count(), 1);
+        }
+
+        assert!(find_provider("Claude").is_some());
+        assert!(find_provider("OpenAI").is_some());
+        assert!(find_provider("Unknown").is_none());
+    }
+}
@@ -1,3 +1,8 @@
 pub mod llm;
 pub mod claude;
-pub mod openai;
\ No newline at end of file
+pub mod openai;
+pub mod openai_compatible;
+pub mod history;
+pub mod schema_prompt;
+pub mod schema_pruning;
+pub mod result_prompt;
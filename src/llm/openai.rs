@@ -1,8 +1,11 @@
-use log::{debug, error};
-use reqwest::Client;
+use log::debug;
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use crate::llm::llm::ContentResponse;
+use crate::config::LLMConfig;
+use crate::llm::llm::{ChatTurn, LlmProvider};
+
+const CHAT_COMPLETIONS_URL: &str = "https://api.openai.com/v1/chat/completions";
 
 pub enum Model {
     Gpt4Turbo,
@@ -58,9 +61,41 @@ struct OpenaiRequest {
     pub max_tokens: u32,
     pub temperature: f32,
 }
-pub async fn llm_request(api_key: String, client: &Client, model: String, user_query: &str, schema_info: &str) -> Result<Value, String> {
+pub struct OpenAI;
+
+impl LlmProvider for OpenAI {
+    fn id(&self) -> &'static str {
+        "OpenAI"
+    }
+
+    fn name(&self) -> &'static str {
+        "OpenAI"
+    }
+
+    fn models(&self) -> Vec<&'static str> {
+        Model::variants_name()
+    }
+
+    fn authorize(&self, request: RequestBuilder, api_key: &str) -> RequestBuilder {
+        request.header("Authorization", format!("Bearer {}", api_key))
+    }
+
+    fn build_request(&self, client: &Client, config: &LLMConfig, turns: &[ChatTurn], schema_info: &str, dialect: &str) -> RequestBuilder {
+        self.build_text_request(client, config, &sql_prompt(schema_info, dialect), turns)
+    }
+
+    fn build_text_request(&self, client: &Client, config: &LLMConfig, system: &str, turns: &[ChatTurn]) -> RequestBuilder {
+        chat_completion_request(client, CHAT_COMPLETIONS_URL, &config.model, system, turns)
+    }
+
+    fn parse_text(&self, response_json: Value) -> Result<String, String> {
+        parse_chat_completion(response_json)
+    }
+}
 
-    let openai_prompt = format!(
+/// System prompt asking for SQL, shared with the OpenAI-compatible servers.
+pub fn sql_prompt(schema_info: &str, dialect: &str) -> String {
+    format!(
         r#"
             You are a helpful database assistant. Convert natural language queries to SQL.
             Do not include any explanations. If multiple database queries are required,
@@ -73,7 +108,8 @@ pub async fn llm_request(api_key: String, client: &Client, model: String, user_q
                 }},
                 {{
                     "type": "query",
-                    "message": "SELECT * FROM orders WHERE user_id = 1;"
+                    "message": "SELECT region, SUM(total) AS revenue FROM orders GROUP BY region;",
+                    "visualization": {{ "chart": "bar", "x": "region", "y": "revenue", "series": null, "title": "Revenue by region" }}
                 }}
             ]
             OR
@@ -84,62 +120,56 @@ pub async fn llm_request(api_key: String, client: &Client, model: String, user_q
                 }}
             ]
 
+            The database is {}. Write SQL for that dialect.
+
             Use the following database schema information:
             {}
 
             - Only write SQL that selects data (no INSERT, UPDATE, DELETE, etc.).
+            - Join tables on the columns listed under Relationships instead of guessing join columns.
             - Return multiple queries as separate objects in the JSON array.
+            - When a chart shows a query's answer better than a table, add a "visualization" object to it: "chart" is one of
+              line, bar, scatter, histogram or pie, "x", "y" and "series" are names of columns the query returns ("series" may be null),
+              "y" must be numeric. Leave "visualization" out otherwise.
             - Ensure the response is valid JSON, without additional explanations or text.
+            - Earlier messages are the conversation so far, answer the last user message.
             "#,
-                schema_info
-            );
+        dialect,
+        schema_info
+    )
+}
 
-    let messages = vec![
+/// Builds a chat completion request, shared with the OpenAI-compatible servers.
+pub fn chat_completion_request(client: &Client, url: &str, model: &str, system: &str, turns: &[ChatTurn]) -> RequestBuilder {
+    let mut messages = vec![
         Message {
             role: "system".to_string(),
-            content: openai_prompt,
-        },
-        Message {
-            role: "user".to_string(),
-            content: user_query.to_string(),
+            content: system.to_string(),
         },
     ];
+    messages.extend(turns.iter().map(|turn| Message {
+        role: turn.role.as_str().to_string(),
+        content: turn.content.clone(),
+    }));
 
     let request = OpenaiRequest {
-        model,
+        model: model.to_string(),
         messages,
         max_tokens: 1000,
         temperature: 0.0, // Use low temperature for deterministic results
     };
 
-    debug!("Sending Openai request: {:?}", request);
-    let response = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", api_key))
+    debug!("Sending Openai request to {}: {:?}", url, request);
+    client
+        .post(url)
         .header("content-type", "application/json")
         .json(&request)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    debug!("Openai response: {:?}", response);
-    let response_json: Value = response.json().await.map_err(|e| {
-        error!("Failed to parse response {}", e);
-        e.to_string()
-    })?;
-    debug!("OpenAI response data: {}", response_json);
-    Ok(response_json)
-
 }
 
-pub fn parse_content(response_json: Value) -> Result<Vec<ContentResponse>, String> {
-    if let Some(content) = response_json["choices"]
+pub fn parse_chat_completion(response_json: Value) -> Result<String, String> {
+    response_json["choices"]
         .get(0)
         .and_then(|choice| choice["message"]["content"].as_str())
-    {
-        let parsed: Vec<ContentResponse> = serde_json::from_str(content).map_err(|e| e.to_string())?;
-        Ok(parsed)
-    } else {
-        Err("message does not contain content".to_string())
-    }
-}
\ No newline at end of file
+        .map(str::to_string)
+        .ok_or_else(|| "message does not contain content".to_string())
+}
@@ -0,0 +1,140 @@
+use reqwest::{Client, RequestBuilder};
+use serde_json::Value;
+use crate::config::LLMConfig;
+use crate::llm::llm::{ChatTurn, LlmProvider};
+use crate::llm::openai::{chat_completion_request, parse_chat_completion, sql_prompt};
+
+/// Any server exposing the OpenAI chat completions API (Ollama, llama.cpp, vLLM, ...).
+pub struct OpenAICompatible;
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434/v1";
+
+impl LlmProvider for OpenAICompatible {
+    fn id(&self) -> &'static str {
+        "OpenAICompatible"
+    }
+
+    fn name(&self) -> &'static str {
+        "OpenAI-compatible (local)"
+    }
+
+    fn models(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    fn requires_api_key(&self) -> bool {
+        false
+    }
+
+    fn default_base_url(&self) -> Option<&'static str> {
+        Some(DEFAULT_BASE_URL)
+    }
+
+    fn authorize(&self, request: RequestBuilder, api_key: &str) -> RequestBuilder {
+        // Local servers usually run without authentication, only send a key when one is set
+        if api_key.is_empty() {
+            request
+        } else {
+            request.header("Authorization", format!("Bearer {}", api_key))
+        }
+    }
+
+    fn build_request(&self, client: &Client, config: &LLMConfig, turns: &[ChatTurn], schema_info: &str, dialect: &str) -> RequestBuilder {
+        self.build_text_request(client, config, &sql_prompt(schema_info, dialect), turns)
+    }
+
+    fn build_text_request(&self, client: &Client, config: &LLMConfig, system: &str, turns: &[ChatTurn]) -> RequestBuilder {
+        let base_url = if config.base_url.trim().is_empty() {
+            DEFAULT_BASE_URL
+        } else {
+            config.base_url.trim()
+        };
+        let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
+        chat_completion_request(client, &url, &config.model, system, turns)
+    }
+
+    fn parse_text(&self, response_json: Value) -> Result<String, String> {
+        parse_chat_completion(response_json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::LLMConfig;
+    use crate::llm::llm::{LLMClient, ResponseType};
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Accepts a single request, returns its path and JSON body and answers with `response`.
+    async fn mock_server(response: Value) -> (String, tokio::task::JoinHandle<(String, Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind mock server");
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.expect("Failed to accept connection");
+            let mut buffer = Vec::new();
+            let mut chunk = [0u8; 4096];
+            let (header_end, content_length) = loop {
+                let read = stream.read(&mut chunk).await.expect("Failed to read request");
+                buffer.extend_from_slice(&chunk[..read]);
+                if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                    let headers = String::from_utf8_lossy(&buffer[..pos]).to_lowercase();
+                    let content_length = headers
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .and_then(|value| value.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    break (pos + 4, content_length);
+                }
+            };
+            while buffer.len() < header_end + content_length {
+                let read = stream.read(&mut chunk).await.expect("Failed to read body");
+                buffer.extend_from_slice(&chunk[..read]);
+            }
+
+            let request_line = String::from_utf8_lossy(&buffer[..header_end]).lines().next().unwrap_or_default().to_string();
+            let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_string();
+            let body: Value = serde_json::from_slice(&buffer[header_end..header_end + content_length]).expect("Request body is not JSON");
+
+            let payload = response.to_string();
+            let reply = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                payload.len(),
+                payload
+            );
+            stream.write_all(reply.as_bytes()).await.expect("Failed to write response");
+            (path, body)
+        });
+
+        (base_url, handle)
+    }
+
+    #[tokio::test]
+    async fn generate_sql_against_local_server() {
+        let content = json!([{ "type": "query", "message": "SELECT * FROM users;" }]).to_string();
+        let (base_url, server) = mock_server(json!({
+            "choices": [{ "message": { "role": "assistant", "content": content } }]
+        })).await;
+
+        let client = LLMClient::new(LLMConfig {
+            provider: Some("OpenAICompatible".to_string()),
+            model: "llama3.1".to_string(),
+            base_url,
+            ..Default::default()
+        });
+        let response = client
+            .generate_sql(&[], "show all users", "Table: users\n  - id (int, NOT NULL, PRIMARY KEY)\n", "SQLite")
+            .await
+            .expect("Failed to generate SQL");
+
+        assert_eq!(response.len(), 1);
+        assert_eq!(response[0].r#type, ResponseType::Query);
+        assert_eq!(response[0].message, "SELECT * FROM users;");
+
+        let (path, body) = server.await.unwrap();
+        assert_eq!(path, "/v1/chat/completions");
+        assert_eq!(body["model"], "llama3.1");
+        assert_eq!(body["messages"][1]["content"], "show all users");
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+use crate::db_element::db::QueryResult;
+use crate::db_element::value::CellValue;
+
+/// Columns described to the LLM, the others are only counted.
+const MAX_COLUMNS: usize = 20;
+/// Rows sent as a sample, the statistics cover every row at hand.
+const MAX_SAMPLE_ROWS: usize = 20;
+/// Longer values are cut, a single text or JSON cell can be large.
+const MAX_VALUE_CHARS: usize = 100;
+
+/// Renders a query and its result as the plain text the LLM is asked to explain: the columns
+/// with statistics and a sample of the rows, bounded however big the result is.
+pub fn render_results(query: &str, data: &QueryResult) -> String {
+    let mut text = format!("Query:\n{}\n\n", query.trim());
+
+    text.push_str(&format!("The query returned {} rows and {} columns.", data.total_rows, data.columns.len()));
+    // A page of the result, not necessarily the first one
+    let first = data.current_page.saturating_sub(1) * data.limit + 1;
+    if data.rows.len() < data.total_rows {
+        text.push_str(&format!(
+            " The statistics and sample below only cover rows {} to {} of the result, not the whole of it.",
+            first,
+            first + data.rows.len().saturating_sub(1),
+        ));
+    }
+    if data.columns.len() > MAX_COLUMNS {
+        text.push_str(&format!(" Only the first {} columns are described.", MAX_COLUMNS));
+    }
+    text.push_str("\n\nColumns:\n");
+
+    let columns = data.columns.len().min(MAX_COLUMNS);
+    for (i, name) in data.columns.iter().take(columns).enumerate() {
+        render_column(name, data.rows.iter().map(|row| &row[i]), &mut text);
+    }
+
+    if !data.rows.is_empty() {
+        let sample = data.rows.len().min(MAX_SAMPLE_ROWS);
+        text.push_str(&format!("\nFirst {} rows{}:\n", sample, if first > 1 { " of these" } else { "" }));
+        text.push_str(&data.columns[..columns].join(" | "));
+        text.push('\n');
+        for row in data.rows.iter().take(sample) {
+            let values: Vec<String> = row.iter().take(columns).map(short_value).collect();
+            text.push_str(&values.join(" | "));
+            text.push('\n');
+        }
+    }
+
+    text
+}
+
+/// `- name: 48 values, 2 NULL, 12 distinct, min 1, max 90, mean 31.5`, the mean only for numbers.
+fn render_column<'a>(name: &str, values: impl Iterator<Item = &'a CellValue>, text: &mut String) {
+    let mut nulls = 0;
+    let mut present: Vec<&CellValue> = Vec::new();
+    for value in values {
+        if value.is_null() {
+            nulls += 1;
+        } else {
+            present.push(value);
+        }
+    }
+
+    text.push_str(&format!("  - {}: {} values, {} NULL", name, present.len(), nulls));
+    if present.is_empty() {
+        text.push('\n');
+        return;
+    }
+
+    let distinct: HashSet<String> = present.iter().map(|value| value.to_string()).collect();
+    text.push_str(&format!(", {} distinct", distinct.len()));
+
+    let min = present.iter().min_by(|a, b| a.sort_cmp(b)).expect("At least one value");
+    let max = present.iter().max_by(|a, b| a.sort_cmp(b)).expect("At least one value");
+    text.push_str(&format!(", min {}, max {}", short_value(min), short_value(max)));
+
+    let numbers: Option<Vec<f64>> = present.iter().map(|value| value.as_f64()).collect();
+    if let Some(numbers) = numbers {
+        let mean = numbers.iter().sum::<f64>() / numbers.len() as f64;
+        text.push_str(&format!(", mean {}", (mean * 1000.0).round() / 1000.0));
+    }
+    text.push('\n');
+}
+
+fn short_value(value: &CellValue) -> String {
+    let text = value.to_string().replace('\n', " ");
+    match text.char_indices().nth(MAX_VALUE_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_element::db::QueryResult;
+    use crate::db_element::value::CellValue;
+    use crate::llm::result_prompt::{render_results, MAX_COLUMNS, MAX_SAMPLE_ROWS};
+
+    #[test]
+    fn results_are_summarized_and_bounded() {
+        let rows: Vec<Vec<CellValue>> = (0..50)
+            .map(|i| vec![
+                CellValue::Text(if i % 2 == 0 { "north" } else { "south" }.to_string()),
+                if i == 0 { CellValue::Null } else { CellValue::Integer(i) },
+                CellValue::Text("x".repeat(500)),
+            ])
+            .collect();
+        let data = QueryResult {
+            columns: vec!["region".to_string(), "revenue".to_string(), "notes".to_string()],
+            rows,
+            total_rows: 120,
+            current_page: 1,
+            total_pages: 3,
+            limit: 50,
+        };
+
+        let text = render_results("SELECT region, revenue, notes FROM sales", &data);
+        assert!(text.contains("returned 120 rows and 3 columns"));
+        assert!(text.contains("only cover rows 1 to 50 of the result"));
+        assert!(text.contains("  - region: 50 values, 0 NULL, 2 distinct, min north, max south\n"));
+        assert!(text.contains("  - revenue: 49 values, 1 NULL, 49 distinct, min 1, max 49, mean 25\n"));
+        assert!(text.contains(&format!("First {} rows:", MAX_SAMPLE_ROWS)));
+        assert!(text.contains("north | NULL | "));
+        // The long text is cut in the sample and in the statistics
+        assert!(!text.contains(&"x".repeat(101)));
+
+        // A later page says which rows it holds
+        let later = QueryResult { current_page: 3, rows: data.rows[..20].to_vec(), ..data };
+        let text = render_results("SELECT region, revenue, notes FROM sales", &later);
+        assert!(text.contains("only cover rows 101 to 120 of the result"));
+        assert!(text.contains(&format!("First {} rows of these:", MAX_SAMPLE_ROWS)));
+
+        let wide = QueryResult {
+            columns: (0..30).map(|i| format!("c{}", i)).collect(),
+            rows: vec![(0..30).map(CellValue::Integer).collect()],
+            total_rows: 1,
+            current_page: 1,
+            total_pages: 1,
+            limit: 50,
+        };
+        let text = render_results("SELECT *", &wide);
+        assert!(text.contains(&format!("  - c{}:", MAX_COLUMNS - 1)));
+        assert!(!text.contains(&format!("  - c{}:", MAX_COLUMNS)));
+    }
+}
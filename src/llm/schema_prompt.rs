@@ -0,0 +1,122 @@
+use crate::db_element::schema::{DatabaseSchema, Table, TableKind};
+
+/// Renders the schema as the plain text context sent along with every prompt.
+pub fn render_schema(schema: &DatabaseSchema) -> String {
+    let mut text = String::new();
+
+    for table in schema.tables() {
+        render_table(schema, table, &mut text);
+    }
+
+    let relationships: Vec<String> = schema.tables()
+        .flat_map(|table| table.foreign_keys.iter().map(move |fk| (table, fk)))
+        .map(|(table, fk)| {
+            format!(
+                "  - {} -> {}\n",
+                key_columns(&schema.table_name(&table.schema, &table.name), &fk.columns),
+                key_columns(&schema.table_name(&fk.referenced_schema, &fk.referenced_table), &fk.referenced_columns),
+            )
+        })
+        .collect();
+    if !relationships.is_empty() {
+        text.push_str("Relationships:\n");
+        text.push_str(&relationships.concat());
+    }
+
+    text
+}
+
+fn render_table(schema: &DatabaseSchema, table: &Table, text: &mut String) {
+    let kind = match table.kind {
+        TableKind::Table => "Table",
+        TableKind::View => "View",
+        TableKind::MaterializedView => "Materialized view",
+    };
+    text.push_str(&format!("{}: {}", kind, schema.table_name(&table.schema, &table.name)));
+    if let Some(comment) = &table.comment {
+        text.push_str(&format!(" -- {}", comment));
+    }
+    text.push('\n');
+
+    for column in &table.columns {
+        let key = if table.primary_key.contains(&column.name) {
+            ", PRIMARY KEY"
+        } else if table.unique_keys.iter().any(|key| key.len() == 1 && key[0] == column.name) {
+            ", UNIQUE"
+        } else {
+            ""
+        };
+        text.push_str(&format!(
+            "  - {} ({}, {}{})",
+            column.name,
+            column.data_type,
+            if column.nullable { "NULL" } else { "NOT NULL" },
+            key
+        ));
+        if let Some(comment) = &column.comment {
+            text.push_str(&format!(" -- {}", comment));
+        }
+        text.push('\n');
+    }
+
+    for key in table.unique_keys.iter().filter(|key| key.len() > 1) {
+        text.push_str(&format!("  UNIQUE ({})\n", key.join(", ")));
+    }
+
+    text.push('\n');
+}
+
+/// `table.column` for single column keys, `table(a, b)` for composite ones.
+fn key_columns(table: &str, columns: &[String]) -> String {
+    match columns {
+        [column] => format!("{}.{}", table, column),
+        _ => format!("{}({})", table, columns.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_element::schema::{ColumnInfo, ForeignKey, SchemaInfo};
+
+    fn column(name: &str, data_type: &str, nullable: bool) -> ColumnInfo {
+        ColumnInfo { name: name.to_string(), data_type: data_type.to_string(), nullable, default: None, comment: None }
+    }
+
+    #[test]
+    fn test_render_schema() {
+        let mut users = Table::new("public", "users", TableKind::Table, Some("Registered users".to_string()));
+        users.columns = vec![column("id", "integer", false), column("email", "text", false)];
+        users.primary_key = vec!["id".to_string()];
+        users.unique_keys = vec![vec!["email".to_string()]];
+
+        let mut orders = Table::new("public", "orders", TableKind::Table, None);
+        orders.columns = vec![column("id", "integer", false), column("user_id", "integer", true)];
+        orders.primary_key = vec!["id".to_string()];
+        orders.foreign_keys = vec![ForeignKey {
+            name: "orders_user_id_fkey".to_string(),
+            columns: vec!["user_id".to_string()],
+            referenced_schema: "public".to_string(),
+            referenced_table: "users".to_string(),
+            referenced_columns: vec!["id".to_string()],
+        }];
+
+        let schema = DatabaseSchema {
+            schemas: vec![SchemaInfo { name: "public".to_string(), tables: vec![orders, users] }],
+            qualify_names: false,
+        };
+
+        assert_eq!(render_schema(&schema), "\
+Table: orders
+  - id (integer, NOT NULL, PRIMARY KEY)
+  - user_id (integer, NULL)
+
+Table: users -- Registered users
+  - id (integer, NOT NULL, PRIMARY KEY)
+  - email (text, NOT NULL, UNIQUE)
+
+Relationships:
+  - orders.user_id -> users.id
+");
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::{HashMap, HashSet};
+use log::debug;
+use crate::db_element::schema::{DatabaseSchema, Table};
+use crate::llm::llm::ChatTurn;
+
+/// Schema and name of a table.
+type TableKey<'a> = (&'a str, &'a str);
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "each", "for", "from", "get", "give",
+    "have", "how", "i", "in", "is", "it", "list", "many", "me", "much", "my", "of", "on", "or", "per",
+    "please", "show", "that", "the", "their", "them", "there", "this", "to", "was", "what", "when",
+    "where", "which", "who", "with", "all", "now", "id",
+];
+
+/// Keeps the tables most relevant to the question plus the tables they join to, so big schemas
+/// fit the context window. Schemas with at most `max_tables` tables are returned unchanged.
+pub fn prune_schema<'a>(schema: &'a DatabaseSchema, question: &str, history: &[ChatTurn], max_tables: usize) -> DatabaseSchema {
+    let total = schema.tables().count();
+    if max_tables == 0 || total <= max_tables {
+        return schema.clone();
+    }
+
+    let question_words = words(question);
+    // Earlier turns keep follow-ups like "now group that by month" on the same tables
+    let history_words: HashSet<String> = history.iter().flat_map(|turn| words(&turn.content)).collect();
+
+    let adjacency = adjacency(schema);
+    let neighbours = |key: &TableKey<'a>| partners(&adjacency, key);
+
+    let direct: HashMap<TableKey, f64> = schema.tables()
+        .map(|table| {
+            let score = score_table(table, &question_words) + 0.5 * score_table(table, &history_words);
+            ((table.schema.as_str(), table.name.as_str()), score)
+        })
+        .collect();
+
+    // Tables next to a strong match get part of its score
+    let scores: HashMap<TableKey, f64> = direct.iter()
+        .map(|(key, score)| {
+            let bonus: f64 = neighbours(key).iter().map(|partner| direct.get(partner).copied().unwrap_or(0.0)).sum();
+            (*key, score + 0.25 * bonus)
+        })
+        .collect();
+
+    // Without any match the most connected tables come first
+    let mut ranked: Vec<TableKey> = schema.tables().map(|table| (table.schema.as_str(), table.name.as_str())).collect();
+    ranked.sort_by(|a, b| {
+        scores[b].total_cmp(&scores[a]).then_with(|| neighbours(b).len().cmp(&neighbours(a).len()))
+    });
+
+    let mut included: HashSet<TableKey> = ranked.iter().take(max_tables).copied().collect();
+
+    // Join partners of the selected tables, bounded so a hub table can't pull in everything
+    let limit = max_tables * 2;
+    for key in ranked.iter().take(max_tables) {
+        for partner in neighbours(key) {
+            if included.len() >= limit {
+                break;
+            }
+            included.insert(*partner);
+        }
+    }
+
+    let mut pruned = schema.clone();
+    for schema_info in &mut pruned.schemas {
+        schema_info.tables.retain(|table| included.contains(&(table.schema.as_str(), table.name.as_str())));
+        for table in &mut schema_info.tables {
+            table.foreign_keys.retain(|fk| included.contains(&(fk.referenced_schema.as_str(), fk.referenced_table.as_str())));
+        }
+    }
+    pruned.schemas.retain(|schema_info| !schema_info.tables.is_empty());
+
+    let names: Vec<String> = pruned.tables().map(|table| pruned.table_name(&table.schema, &table.name)).collect();
+    debug!("Schema pruned to {} of {} tables: {}", names.len(), total, names.join(", "));
+    pruned
+}
+
+fn score_table(table: &Table, terms: &HashSet<String>) -> f64 {
+    if terms.is_empty() {
+        return 0.0;
+    }
+    let mut score = 0.0;
+    let table_words = identifier_words(&table.name);
+    if terms.contains(&normalize(&table.name)) {
+        score += 10.0;
+    }
+    score += 4.0 * table_words.iter().filter(|word| terms.contains(*word)).count() as f64;
+
+    // Columns count less and are capped, wide tables shouldn't win on width alone
+    let column_hits = table.columns.iter()
+        .filter(|column| {
+            terms.contains(&normalize(&column.name))
+                || identifier_words(&column.name).iter().any(|word| !table_words.contains(word) && terms.contains(word))
+        })
+        .count();
+    score += 1.5 * column_hits.min(4) as f64;
+
+    let comments = table.comment.iter()
+        .chain(table.columns.iter().filter_map(|column| column.comment.as_ref()));
+    let comment_hits = comments.flat_map(|comment| words(comment)).filter(|word| terms.contains(word)).count();
+    score += 0.5 * comment_hits.min(4) as f64;
+
+    score
+}
+
+/// Tables each table references or is referenced by.
+fn adjacency(schema: &DatabaseSchema) -> HashMap<TableKey<'_>, Vec<TableKey<'_>>> {
+    let mut adjacency: HashMap<TableKey, Vec<TableKey>> = HashMap::new();
+    for table in schema.tables() {
+        let from = (table.schema.as_str(), table.name.as_str());
+        for fk in &table.foreign_keys {
+            let to = (fk.referenced_schema.as_str(), fk.referenced_table.as_str());
+            if from == to {
+                continue;
+            }
+            adjacency.entry(from).or_default().push(to);
+            adjacency.entry(to).or_default().push(from);
+        }
+    }
+    for partners in adjacency.values_mut() {
+        partners.sort();
+        partners.dedup();
+    }
+    adjacency
+}
+
+fn partners<'a>(adjacency: &'a HashMap<TableKey<'a>, Vec<TableKey<'a>>>, key: &TableKey<'a>) -> &'a [TableKey<'a>] {
+    adjacency.get(key).map(Vec::as_slice).unwrap_or_default()
+}
+
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .flat_map(identifier_words)
+        .filter(|word| word.len() > 1 && !STOP_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// `orderItems` and `order_items` both become `order`, `item`.
+fn identifier_words(identifier: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in identifier.chars() {
+        if !c.is_alphanumeric() {
+            parts.push(std::mem::take(&mut current));
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower {
+            parts.push(std::mem::take(&mut current));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.push(c);
+    }
+    parts.push(current);
+    parts.iter().filter(|part| !part.is_empty()).map(|part| normalize(part)).collect()
+}
+
+/// Lower case singular, good enough to match "customers" with `customer`.
+fn normalize(word: &str) -> String {
+    let word = word.to_lowercase();
+    if let Some(stem) = word.strip_suffix("ies").filter(|stem| stem.len() > 2) {
+        format!("{}y", stem)
+    } else if let Some(stem) = word.strip_suffix("ses").filter(|stem| stem.len() > 2) {
+        format!("{}s", stem)
+    } else if let Some(stem) = word.strip_suffix('s').filter(|stem| stem.len() > 2 && !stem.ends_with('s')) {
+        stem.to_string()
+    } else {
+        word
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_element::schema::{ColumnInfo, ForeignKey, SchemaInfo, TableKind};
+
+    fn table(name: &str, columns: &[&str], references: &[&str]) -> Table {
+        let mut table = Table::new("public", name, TableKind::Table, None);
+        table.columns = columns.iter()
+            .map(|column| ColumnInfo { name: column.to_string(), data_type: "text".to_string(), nullable: true, default: None, comment: None })
+            .collect();
+        table.foreign_keys = references.iter()
+            .map(|referenced| ForeignKey {
+                name: format!("{}_{}_fkey", name, referenced),
+                columns: vec![format!("{}_id", referenced)],
+                referenced_schema: "public".to_string(),
+                referenced_table: referenced.to_string(),
+                referenced_columns: vec!["id".to_string()],
+            })
+            .collect();
+        table
+    }
+
+    #[test]
+    fn test_prune_keeps_matches_and_join_partners() {
+        let schema = DatabaseSchema {
+            schemas: vec![SchemaInfo {
+                name: "public".to_string(),
+                tables: vec![
+                    table("customers", &["id", "name", "region"], &[]),
+                    table("orders", &["id", "customer_id", "total"], &["customers"]),
+                    table("order_items", &["id", "order_id", "product_id"], &["orders", "products"]),
+                    table("products", &["id", "title"], &[]),
+                    table("audit_log", &["id", "message"], &[]),
+                    table("feature_flags", &["id", "enabled"], &[]),
+                ],
+            }],
+            qualify_names: false,
+        };
+
+        let pruned = prune_schema(&schema, "Total revenue of orders per customer region", &[], 2);
+        let names: HashSet<&str> = pruned.tables().map(|table| table.name.as_str()).collect();
+
+        assert!(names.contains("orders"));
+        assert!(names.contains("customers"));
+        assert!(names.contains("order_items"), "join partner of orders");
+        assert!(!names.contains("audit_log"));
+        assert!(!names.contains("feature_flags"));
+        // Keys to tables that were left out are dropped with them
+        let order_items = pruned.tables().find(|table| table.name == "order_items").unwrap();
+        assert!(order_items.foreign_keys.iter().all(|fk| names.contains(fk.referenced_table.as_str())));
+
+        assert_eq!(prune_schema(&schema, "anything", &[], 10), schema);
+    }
+}
@@ -37,15 +37,30 @@ impl SecureStorage {
         Ok(())
     }
 
-    // Store API key securely
-    pub fn store_api_key(api_key: &str) -> Result<(), SecurityError> {
-        let entry = Entry::new(Service::LlmAPI.as_str(), "api_key")?;
+    // Store API key securely, one per LLM provider
+    pub fn store_api_key(provider: &str, api_key: &str) -> Result<(), SecurityError> {
+        let entry = Entry::new(Service::LlmAPI.as_str(), &format!("api_key:{}", provider))?;
         entry.set_password(api_key)?;
         Ok(())
     }
 
     // Retrieve API key securely
-    pub fn get_api_key() -> Result<String, SecurityError> {
+    pub fn get_api_key(provider: &str) -> Result<String, SecurityError> {
+        let entry = Entry::new(Service::LlmAPI.as_str(), &format!("api_key:{}", provider))?;
+        let api_key = entry.get_password()?;
+        Ok(api_key)
+    }
+
+    pub fn remove_api_key(provider: &str) -> Result<(), SecurityError> {
+        let entry = Entry::new(Service::LlmAPI.as_str(), &format!("api_key:{}", provider))?;
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // Key saved before they were kept per provider
+    pub fn get_legacy_api_key() -> Result<String, SecurityError> {
         let entry = Entry::new(Service::LlmAPI.as_str(), "api_key")?;
         let api_key = entry.get_password()?;
         Ok(api_key)
@@ -0,0 +1,251 @@
+use std::path::{Path, PathBuf};
+use crate::app::{AppState, RowsFetch};
+use crate::db_element::db::{QueryResult, SortOrder};
+use crate::utils::chart::{suggest, to_svg, Chart, ChartKind, ChartSpec, Mark, FONT_SIZE};
+use egui::{Color32, ColorImage, FontId, Painter, Pos2, Rect, RichText, Sense, Shape, Stroke, Ui, UserData, Vec2, ViewportCommand};
+use tokio::sync::oneshot::error::TryRecvError;
+use uuid::Uuid;
+
+/// Rows loaded for the chart of a result with more than one page.
+const CHART_ROWS: usize = 5000;
+
+/// The chart of a result window, kept in its `TableView`.
+pub struct ChartView {
+    pub spec: ChartSpec,
+    /// Up to `CHART_ROWS` rows when the window only holds a page of them.
+    rows: Option<QueryResult>,
+    /// Order of the table the rows were fetched in.
+    rows_sort: Option<SortOrder>,
+    loading: Option<RowsFetch>,
+    error: Option<String>,
+    /// Outcome of the last export.
+    saved: Option<Result<String, String>>,
+    /// Where the chart was painted, what the screenshot for a PNG is cropped to.
+    rect: Option<Rect>,
+    png: Option<PathBuf>,
+}
+
+impl ChartView {
+    pub fn new(spec: ChartSpec) -> Self {
+        Self { spec, rows: None, rows_sort: None, loading: None, error: None, saved: None, rect: None, png: None }
+    }
+}
+
+/// Switches a result window between table and chart. The first time the chart type and
+/// columns are picked from the column types.
+pub fn toggle_chart(app_state: &mut AppState, index: usize) {
+    let result = &mut app_state.query_result[index];
+    result.view.show_chart = !result.view.show_chart;
+    if result.view.chart.is_none() {
+        let spec = suggest(&result.data).unwrap_or(ChartSpec { kind: ChartKind::Bar, x: None, y: None, series: None, title: None });
+        result.view.chart = Some(ChartView::new(spec));
+    }
+}
+
+/// Stops loading the rows of a chart that is going away with its window.
+pub fn stop_chart(app_state: &AppState, chart: Option<&mut ChartView>) {
+    if let Some(fetch) = chart.and_then(|chart| chart.loading.take()) {
+        app_state.stop_fetch(fetch);
+    }
+}
+
+pub fn render_chart(ui: &mut Ui, app_state: &mut AppState, index: usize) {
+    load_rows(app_state, index);
+    let result = &mut app_state.query_result[index];
+    let Some(chart) = result.view.chart.as_mut() else {
+        return;
+    };
+    save_screenshot(ui, &result.id, chart);
+
+    let data = chart.rows.as_ref().unwrap_or(&result.data);
+    let mut save_svg = false;
+    let mut save_png = false;
+    ui.horizontal(|ui| {
+        let spec = &mut chart.spec;
+        egui::ComboBox::from_id_salt(("chart_kind", result.id))
+            .selected_text(spec.kind.label())
+            .show_ui(ui, |ui| {
+                for kind in ChartKind::ALL {
+                    ui.selectable_value(&mut spec.kind, kind, kind.label());
+                }
+            });
+        let id = result.id;
+        if spec.kind != ChartKind::Histogram {
+            column_choice(ui, (id, "x"), "X", &mut spec.x, &data.columns, false);
+        }
+        column_choice(ui, (id, "y"), if spec.kind == ChartKind::Histogram { "Values" } else { "Y" }, &mut spec.y, &data.columns, false);
+        if !matches!(spec.kind, ChartKind::Histogram | ChartKind::Pie) {
+            column_choice(ui, (id, "series"), "Series", &mut spec.series, &data.columns, true);
+        }
+        ui.separator();
+        save_svg = ui.button("💾 SVG").clicked();
+        save_png = ui.button("💾 PNG").clicked();
+        match &chart.saved {
+            Some(Ok(path)) => {
+                ui.colored_label(Color32::from_rgb(80, 200, 120), "✔ Saved").on_hover_text(path);
+            }
+            Some(Err(err)) => {
+                ui.colored_label(Color32::from_rgb(230, 80, 80), "✖ Not saved").on_hover_text(err);
+            }
+            None => {}
+        }
+    });
+
+    if let Some(error) = &chart.error {
+        ui.colored_label(Color32::from_rgb(230, 80, 80), format!("Failed to load the rows: {}", error));
+        return;
+    }
+    if chart.loading.is_some() {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("Loading rows...");
+        });
+        return;
+    }
+    if result.data.total_rows > data.rows.len() {
+        ui.label(RichText::new(format!("Showing the first {} of {} rows", data.rows.len(), result.data.total_rows)).weak());
+    }
+
+    let built = match Chart::build(data, &chart.spec) {
+        Ok(built) => built,
+        Err(err) => {
+            ui.label(RichText::new(err).weak());
+            return;
+        }
+    };
+    let size = Vec2::new(ui.available_width(), ui.available_height().max(300.0));
+    let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+    let marks = built.layout(rect.size());
+    paint(&ui.painter_at(rect), rect.min.to_vec2(), &marks);
+    // Only the visible part of the chart is in a screenshot
+    chart.rect = Some(rect.intersect(ui.clip_rect()));
+
+    if save_svg {
+        if let Some(path) = save_dialog("SVG image", "svg") {
+            chart.saved = Some(std::fs::write(&path, to_svg(&marks, rect.size()))
+                .map(|()| path.display().to_string())
+                .map_err(|e| e.to_string()));
+        }
+    }
+    if save_png {
+        if let Some(path) = save_dialog("PNG image", "png") {
+            // The chart is only in pixels once the frame is rendered, it comes back as an event
+            chart.png = Some(path);
+            ui.ctx().send_viewport_cmd(ViewportCommand::Screenshot(UserData::new(result.id)));
+        }
+    }
+}
+
+fn column_choice(ui: &mut Ui, id_salt: impl std::hash::Hash, label: &str, column: &mut Option<usize>, columns: &[String], optional: bool) {
+    ui.label(label);
+    let selected = column.and_then(|c| columns.get(c)).map(String::as_str).unwrap_or("—");
+    egui::ComboBox::from_id_salt(id_salt).selected_text(selected).show_ui(ui, |ui| {
+        if optional {
+            ui.selectable_value(column, None, "—");
+        }
+        for (i, name) in columns.iter().enumerate() {
+            ui.selectable_value(column, Some(i), name);
+        }
+    });
+}
+
+fn save_dialog(name: &str, extension: &str) -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter(name, &[extension])
+        .set_file_name(format!("chart.{}", extension))
+        .save_file()
+}
+
+/// Fetches the rows of a chart over a result with more than one page, in the order of the table.
+fn load_rows(app_state: &mut AppState, index: usize) {
+    let result = &mut app_state.query_result[index];
+    let sort = result.view.sort.clone();
+    let Some(chart) = result.view.chart.as_mut() else {
+        return;
+    };
+    let mut stale = None;
+    if chart.rows_sort != sort {
+        chart.rows = None;
+        chart.error = None;
+        chart.rows_sort = sort.clone();
+        stale = chart.loading.take();
+    }
+    if let Some(fetch) = chart.loading.as_mut() {
+        match fetch.rx.try_recv() {
+            Ok(Ok(rows)) => chart.rows = Some(rows),
+            Ok(Err(err)) => chart.error = Some(err),
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Closed) => chart.error = Some("Loading the rows stopped".to_string()),
+        }
+        if chart.rows.is_some() || chart.error.is_some() {
+            chart.loading = None;
+            app_state.query_history.invalidate();
+        }
+    }
+    // One page isn't the whole picture, the chart gets its own, larger, fetch
+    let fetch = result.data.total_pages > 1 && chart.rows.is_none() && chart.loading.is_none() && chart.error.is_none();
+    let (connection_id, query) = (result.connection_id, result.query.clone());
+
+    if let Some(stale) = stale {
+        app_state.stop_fetch(stale);
+    }
+    if fetch {
+        let fetch = app_state.fetch_rows(&connection_id, &query, CHART_ROWS, sort);
+        if let Some(chart) = app_state.query_result[index].view.chart.as_mut() {
+            chart.loading = Some(fetch);
+        }
+    }
+}
+
+/// Writes the part of a requested screenshot showing the chart.
+fn save_screenshot(ui: &Ui, result_id: &Uuid, chart: &mut ChartView) {
+    let (Some(path), Some(rect)) = (chart.png.as_ref(), chart.rect) else {
+        return;
+    };
+    let screenshot = ui.ctx().input(|i| i.raw.events.iter().find_map(|event| match event {
+        egui::Event::Screenshot { user_data, image, .. }
+            if user_data.data.as_ref().and_then(|data| data.downcast_ref::<Uuid>()) == Some(result_id) => Some(image.clone()),
+        _ => None,
+    }));
+    let Some(screenshot) = screenshot else {
+        return;
+    };
+    let pixels_per_point = ui.ctx().pixels_per_point();
+    let image_rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(screenshot.size[0] as f32, screenshot.size[1] as f32) / pixels_per_point);
+    // `region` doesn't check its bounds, the chart may reach past the window or the screen
+    let rect = rect.intersect(ui.ctx().screen_rect()).intersect(image_rect);
+    chart.saved = Some(if rect.width() < 1.0 || rect.height() < 1.0 {
+        Err("The chart isn't visible on screen".to_string())
+    } else {
+        let image = screenshot.region(&rect, Some(pixels_per_point));
+        write_png(path, &image).map(|()| path.display().to_string())
+    });
+    chart.png = None;
+}
+
+fn write_png(path: &Path, image: &ColorImage) -> Result<(), String> {
+    image::save_buffer(path, image.as_raw(), image.size[0] as u32, image.size[1] as u32, image::ColorType::Rgba8)
+        .map_err(|e| e.to_string())
+}
+
+fn paint(painter: &Painter, offset: Vec2, marks: &[Mark]) {
+    for mark in marks {
+        match mark {
+            Mark::Line { points, color, width } => {
+                painter.add(Shape::line(points.iter().map(|p| *p + offset).collect(), Stroke::new(*width, *color)));
+            }
+            Mark::Rect { rect, color } => {
+                painter.rect_filled(rect.translate(offset), 0.0, *color);
+            }
+            Mark::Circle { center, radius, color } => {
+                painter.circle_filled(*center + offset, *radius, *color);
+            }
+            Mark::Polygon { points, color } => {
+                painter.add(Shape::convex_polygon(points.iter().map(|p| *p + offset).collect(), *color, Stroke::new(0.5, *color)));
+            }
+            Mark::Text { pos, text, align, color } => {
+                painter.text(*pos + offset, *align, text, FontId::proportional(FONT_SIZE), *color);
+            }
+        }
+    }
+}
@@ -1,24 +1,76 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use crate::app::{AppMode, AppState};
-use crate::db_element::chat::{Message, Sender};
+use crate::db_element::chat::{Message, MessageMeta, MessageRevision, Sender};
 use crate::db_element::db::DatabaseManager;
-use crate::llm::llm::{LLMClient, ResponseType};
-use egui::{Align, Color32, Context, Frame, ScrollArea, TextEdit};
-use log::debug;
+use crate::db_element::schema::DatabaseSchema;
+use crate::db_element::chat_storage::ChatStorage;
+use crate::db_element::schema_cache::SchemaCache;
+use crate::llm::llm::{ChatTurn, ContentResponse, LLMClient, ResponseType};
+use crate::ui::query_result::QueryError;
+use crate::ui::sql_editor::{sql_editor, sql_label};
+use chrono::Utc;
+use egui::{Align, Color32, Context, Frame, RichText, ScrollArea, TextEdit};
+use log::{debug, error};
+use tokio::sync::mpsc::Receiver;
 use uuid::Uuid;
 
+/// Messages produced by a background LLM call, appended to the conversation once it finishes.
+pub struct ChatReply {
+    pub messages: Vec<(Message, MessageMeta)>,
+    /// Query message to run as soon as it is added.
+    pub run: Option<Uuid>,
+}
+
 pub struct Conversation {
     pub id: Option<Uuid>,
     pub messages: Vec<Message>,
+    pub meta: HashMap<Uuid, MessageMeta>,
     is_loading: bool,
     pub loading_query: RefCell<Vec<Uuid>>,
     message_input: String,
-    rx: Option<tokio::sync::mpsc::Receiver<Result<(Message,Vec<Message>), String>>>
+    rx: Option<Receiver<Result<ChatReply, String>>>,
+    /// LLM calls started outside the input box: query corrections and result explanations.
+    background: Vec<Receiver<Result<ChatReply, String>>>,
+    schema_refresh: Option<Receiver<Result<DatabaseSchema, String>>>,
+    /// Every version of the edited SQL messages, starting with the generated one.
+    revisions: HashMap<Uuid, Vec<String>>,
+    /// Drafts of the SQL messages in edit mode.
+    editing: HashMap<Uuid, String>,
+}
+
+enum SqlAction {
+    Run(Uuid),
+    Edit(Uuid),
+    Cancel(Uuid),
+    SaveRevision { message: Uuid, run: bool },
+    SaveQuery(Uuid),
 }
 
 impl Conversation {
     pub fn new(uuid: Option<Uuid>) -> Self {
-        Self { id: uuid, messages: Vec::new(), is_loading: false, loading_query: RefCell::new(vec![]), message_input: "".to_string(), rx: None }
+        Self { id: uuid, messages: Vec::new(), meta: HashMap::new(), is_loading: false, loading_query: RefCell::new(vec![]), message_input: "".to_string(), rx: None, background: Vec::new(), schema_refresh: None, revisions: HashMap::new(), editing: HashMap::new() }
+    }
+
+    /// Shows the latest revision of edited messages in place of the generated text.
+    pub fn apply_revisions(&mut self, revisions: HashMap<Uuid, Vec<MessageRevision>>) {
+        for message in &mut self.messages {
+            let Some(message_revisions) = revisions.get(&message.uuid).filter(|revisions| !revisions.is_empty()) else {
+                continue;
+            };
+            let mut versions = vec![message.content.clone()];
+            versions.extend(message_revisions.iter().map(|revision| revision.content.clone()));
+            message.content = versions.last().cloned().unwrap_or_default();
+            self.revisions.insert(message.uuid, versions);
+        }
+    }
+
+    /// Appends text to the message being typed, separated by a space.
+    pub fn insert_text(&mut self, text: &str) {
+        if !self.message_input.is_empty() && !self.message_input.ends_with(char::is_whitespace) {
+            self.message_input.push(' ');
+        }
+        self.message_input.push_str(text);
     }
 }
 pub fn render_chat(ctx: &Context, app_state: &mut AppState) {
@@ -31,10 +83,14 @@ pub fn render_chat(ctx: &Context, app_state: &mut AppState) {
                 return;
             }
         };
+        render_top_bar(ui, app_state, &uuid);
+
         // Chat area
         let available_height = ui.available_height();
         let chat_height = available_height * 0.85;
 
+        let mut action = None;
+        let mut discard = None;
         ScrollArea::vertical()
             .auto_shrink([false; 2])
             .stick_to_bottom(true)
@@ -43,6 +99,8 @@ pub fn render_chat(ctx: &Context, app_state: &mut AppState) {
                 for msg in &app_state.conversation.messages {
 
                     let (align, bubble_color, valign) = match msg.sender {
+                        // Statements from the SQL console, light enough for the highlighted SQL
+                        Sender::User if msg.is_sql => (egui::Layout::right_to_left(Align::RIGHT), Color32::from_rgb(200, 225, 255), Align::RIGHT),
                         Sender::User => (egui::Layout::right_to_left(Align::RIGHT), Color32::from_rgb(0, 150, 255), Align::RIGHT),
                         Sender::System => (egui::Layout::left_to_right(Align::RIGHT), Color32::from_rgb(230, 230, 230), Align::LEFT),
                     };
@@ -61,21 +119,65 @@ pub fn render_chat(ctx: &Context, app_state: &mut AppState) {
                                     Color32::BLACK
                                 };
 
-                                if msg.is_sql {
+                                let is_note = app_state.conversation.meta.get(&msg.uuid).is_some_and(|meta| meta.note);
+                                if is_note {
+                                    ui.horizontal_wrapped(|ui| {
+                                        ui.label(RichText::new(&msg.content).italics().color(Color32::DARK_GRAY));
+                                    });
+                                } else if msg.is_sql {
                                     ui.with_layout(egui::Layout::top_down(valign), |ui| {
+                                        if let Some(draft) = app_state.conversation.editing.get_mut(&msg.uuid) {
+                                            sql_editor(ui, draft);
+                                            ui.horizontal(|ui| {
+                                                if ui.button("▶ Run").clicked() {
+                                                    action = Some(SqlAction::SaveRevision { message: msg.uuid, run: true });
+                                                }
+                                                if ui.button("💾 Save").clicked() {
+                                                    action = Some(SqlAction::SaveRevision { message: msg.uuid, run: false });
+                                                }
+                                                if ui.button("✖ Discard").clicked() {
+                                                    discard = Some(msg.uuid);
+                                                }
+                                            });
+                                            return;
+                                        }
+
                                         ui.horizontal_wrapped(|ui| {
-                                            ui.colored_label(text_color, &msg.content);
+                                            sql_label(ui, &msg.content);
                                         });
+                                        if let Some(versions) = app_state.conversation.revisions.get(&msg.uuid) {
+                                            ui.label(RichText::new(format!("✎ Edited, revision {}", versions.len() - 1)).small().italics().color(Color32::DARK_GRAY))
+                                                .on_hover_text(format!("Generated query:\n{}", versions[0]));
+                                        }
                                         if app_state.conversation.loading_query.borrow().contains(&msg.uuid) {
-                                            ui.add_enabled(false, egui::Button::new("⏳ Running..."));
+                                            ui.horizontal(|ui| {
+                                                ui.add_enabled(false, egui::Button::new("⏳ Running..."));
+                                                if ui.button("✖ Cancel").clicked() {
+                                                    action = Some(SqlAction::Cancel(msg.uuid));
+                                                }
+                                            });
                                         } else {
-                                            if ui.button("▶ Run Query").clicked() {
-                                                app_state.conversation.loading_query.borrow_mut().push(msg.uuid);
-                                                app_state.run_query(&uuid, &msg.content, &msg.uuid, 1);
-                                            }
+                                            ui.horizontal(|ui| {
+                                                if ui.button("▶ Run Query").clicked() {
+                                                    action = Some(SqlAction::Run(msg.uuid));
+                                                }
+                                                if ui.button("✎ Edit").clicked() {
+                                                    action = Some(SqlAction::Edit(msg.uuid));
+                                                }
+                                                if ui.button("⭐ Save").on_hover_text("Add to the saved queries").clicked() {
+                                                    action = Some(SqlAction::SaveQuery(msg.uuid));
+                                                }
+                                            });
                                         }
                                     });
                                 } else {
+                                    if let Some(query_id) = app_state.conversation.meta.get(&msg.uuid).and_then(|meta| meta.explains) {
+                                        let query = app_state.conversation.messages.iter().find(|m| m.uuid == query_id);
+                                        let label = ui.label(RichText::new("💡 Summary of the query results").small().color(Color32::DARK_GRAY));
+                                        if let Some(query) = query {
+                                            label.on_hover_text(&query.content);
+                                        }
+                                    }
                                     ui.horizontal_wrapped(|ui| {
                                         ui.colored_label(text_color, &msg.content);
                                     });
@@ -87,6 +189,38 @@ pub fn render_chat(ctx: &Context, app_state: &mut AppState) {
                 }
             });
 
+        if let Some(message_uuid) = discard {
+            app_state.conversation.editing.remove(&message_uuid);
+        }
+        match action {
+            Some(SqlAction::Run(message_uuid)) => {
+                if let Some(message) = app_state.conversation.messages.iter().find(|m| m.uuid == message_uuid) {
+                    app_state.conversation.loading_query.borrow_mut().push(message_uuid);
+                    app_state.run_query(&uuid, &message.content, &message_uuid, 1);
+                }
+            }
+            Some(SqlAction::Edit(message_uuid)) => {
+                if let Some(message) = app_state.conversation.messages.iter().find(|m| m.uuid == message_uuid) {
+                    app_state.conversation.editing.insert(message_uuid, message.content.clone());
+                }
+            }
+            Some(SqlAction::Cancel(message_uuid)) => app_state.cancel_query(&message_uuid),
+            Some(SqlAction::SaveRevision { message, run }) => save_revision(app_state, &uuid, &message, run),
+            Some(SqlAction::SaveQuery(message_uuid)) => {
+                let messages = &app_state.conversation.messages;
+                if let Some(index) = messages.iter().position(|m| m.uuid == message_uuid) {
+                    // Named after the question it answers, when there is one
+                    let name = messages[..index].iter().rev()
+                        .find(|m| matches!(m.sender, Sender::User) && !m.is_sql)
+                        .map(|m| m.content.chars().take(60).collect())
+                        .unwrap_or_default();
+                    let sql = messages[index].content.clone();
+                    app_state.saved_queries.open_editor(uuid, name, sql);
+                }
+            }
+            None => {}
+        }
+
         // Input area
         ui.separator();
         ui.add_space(4.0);
@@ -122,11 +256,13 @@ pub fn render_chat(ctx: &Context, app_state: &mut AppState) {
                     app_state.conversation.is_loading = true;
                     app_state.conversation.rx = Some(rx);
                     let db_manager = app_state.db_manager.clone();
+                    let schema_cache = app_state.schema_cache.clone();
                     let message = app_state.conversation.message_input.clone();
                     app_state.conversation.message_input.clear();
                     let llm_client = llm_client.clone();
+                    let history = llm_client.conversation_history(&app_state.conversation.messages, &app_state.conversation.meta);
                     app_state.runtime.spawn(async move {
-                        let res = send_message(&llm_client, &db_manager, &uuid, message).await;
+                        let res = send_message(&llm_client, &db_manager, &schema_cache, &uuid, &history, message).await;
                        tx.send(res).await.ok();
                     });
 
@@ -134,31 +270,278 @@ pub fn render_chat(ctx: &Context, app_state: &mut AppState) {
             }
         });
 
-        if let Some(ref mut rx) = app_state.conversation.rx {
-            if let Ok(recv) = rx.try_recv() {
-                if let Ok(res) = recv {
-                    let (user_message, system_messages) = res;
-                    app_state.chat_storage.add_message(&uuid, &user_message).expect("Failed to add message");
-                    app_state.conversation.messages.push(user_message);
-                    system_messages.into_iter().for_each(|system_message| {
-                        app_state.chat_storage.add_message(&uuid, &system_message).expect("Failed to add message");
-                        app_state.conversation.messages.push(system_message);
-                    });
-                    app_state.conversation.is_loading = false;
-                }
+        let reply = app_state.conversation.rx.as_mut().and_then(|rx| rx.try_recv().ok());
+        if let Some(recv) = reply {
+            app_state.conversation.is_loading = false;
+            app_state.conversation.rx = None;
+            apply_reply(app_state, &uuid, recv);
+        }
+
+        let mut replies = Vec::new();
+        app_state.conversation.background.retain_mut(|rx| match rx.try_recv() {
+            Ok(recv) => {
+                replies.push(recv);
+                false
             }
+            Err(tokio::sync::mpsc::error::TryRecvError::Empty) => true,
+            Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => false,
+        });
+        for recv in replies {
+            apply_reply(app_state, &uuid, recv);
         }
+    });
+}
+
+/// Switches between the chat and the SQL console, and shows the state of the cached schema.
+pub fn render_top_bar(ui: &mut egui::Ui, app_state: &mut AppState, uuid: &Uuid) {
+    if let Some(res) = app_state.conversation.schema_refresh.as_mut().and_then(|rx| rx.try_recv().ok()) {
+        app_state.conversation.schema_refresh = None;
+        match res {
+            Ok(schema) => app_state.schema_browser.set_schema(uuid, schema),
+            Err(err) => {
+                error!("Failed to refresh schema: {}", err);
+                apply_reply(app_state, uuid, Ok(ChatReply {
+                    messages: vec![note(format!("⚠ Failed to refresh the schema: {}", err))],
+                    run: None,
+                }));
+            }
+        }
+    }
 
+    ui.horizontal(|ui| {
+        if ui.selectable_label(matches!(app_state.mode, AppMode::Chat), "💬 Chat").clicked() {
+            app_state.mode = AppMode::Chat;
+        }
+        if ui.selectable_label(matches!(app_state.mode, AppMode::Console), "⌨ SQL console").clicked() {
+            app_state.mode = AppMode::Console;
+        }
+        if ui.selectable_label(matches!(app_state.mode, AppMode::History), "🕘 History").clicked() {
+            app_state.mode = AppMode::History;
+        }
+        ui.separator();
+        if app_state.schema_cache.is_stale(uuid) {
+            ui.colored_label(Color32::from_rgb(230, 160, 0), "⚠ The database schema changed since it was cached");
+        }
+        ui.with_layout(egui::Layout::right_to_left(Align::Center), |ui| {
+            if app_state.conversation.schema_refresh.is_some() {
+                ui.add_enabled(false, egui::Button::new("⏳ Refreshing schema..."));
+            } else if ui.button("↻ Refresh schema").clicked() {
+                let (tx, rx) = tokio::sync::mpsc::channel(1);
+                app_state.conversation.schema_refresh = Some(rx);
+                let schema_cache = app_state.schema_cache.clone();
+                let uuid = *uuid;
+                app_state.runtime.spawn(async move {
+                    let res = schema_cache.refresh(&uuid).await;
+                    tx.send(res).await.ok();
+                });
+            }
+        });
+    });
+    ui.separator();
+}
 
+/// Stores the draft of an edited query as a new revision of the message, optionally running it.
+fn save_revision(app_state: &mut AppState, uuid: &Uuid, message_uuid: &Uuid, run: bool) {
+    let Some(draft) = app_state.conversation.editing.remove(message_uuid) else {
+        return;
+    };
+    let Some(message) = app_state.conversation.messages.iter_mut().find(|m| m.uuid == *message_uuid) else {
+        return;
+    };
 
+    let content = draft.trim().to_string();
+    if !content.is_empty() && content != message.content {
+        let revision = MessageRevision { content: content.clone(), created_at: Utc::now() };
+        if let Err(err) = app_state.chat_storage.add_revision(uuid, message_uuid, &revision) {
+            error!("Failed to store revision of message {}: {}", message_uuid, err);
+        }
+        app_state.conversation.revisions
+            .entry(*message_uuid)
+            .or_insert_with(|| vec![message.content.clone()])
+            .push(content.clone());
+        message.content = content;
+    }
+
+    if run {
+        let content = message.content.clone();
+        app_state.conversation.loading_query.borrow_mut().push(*message_uuid);
+        app_state.run_query(uuid, &content, message_uuid, 1);
+    }
+}
+
+fn apply_reply(app_state: &mut AppState, uuid: &Uuid, reply: Result<ChatReply, String>) {
+    let reply = match reply {
+        Ok(reply) => reply,
+        Err(err) => {
+            error!("LLM request failed: {}", err);
+            ChatReply {
+                messages: vec![note(format!("⚠ {}", err))],
+                run: None,
+            }
+        }
+    };
+
+    for (message, meta) in reply.messages {
+        store_message(&app_state.chat_storage, uuid, &message, &meta).expect("Failed to add message");
+        if meta.note || meta.correction_attempt.is_some() || meta.visualization.is_some() || meta.explains.is_some() {
+            app_state.conversation.meta.insert(message.uuid, meta);
+        }
+        app_state.conversation.messages.push(message);
+    }
+
+    if let Some(run) = reply.run {
+        if let Some(message) = app_state.conversation.messages.iter().find(|m| m.uuid == run) {
+            app_state.conversation.loading_query.borrow_mut().push(run);
+            app_state.run_query(uuid, &message.content, &run, 1);
+        }
+    }
+}
+
+/// Saves a message of a conversation, open or not, with its meta when it has any.
+fn store_message(chat_storage: &ChatStorage, uuid: &Uuid, message: &Message, meta: &MessageMeta) -> Result<(), String> {
+    chat_storage.add_message(uuid, message)?;
+    if meta.note || meta.correction_attempt.is_some() || meta.visualization.is_some() || meta.explains.is_some() {
+        if let Err(err) = chat_storage.set_message_meta(uuid, &message.uuid, meta) {
+            error!("Failed to store message meta: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// A status message shown in the conversation but never sent to the LLM.
+fn note(content: String) -> (Message, MessageMeta) {
+    (Message::new(Sender::System, content, false), MessageMeta { note: true, ..Default::default() })
+}
+
+/// What happens after a query failed.
+enum Correction {
+    None,
+    Attempt { attempt: usize, max_attempts: usize },
+    Exhausted { max_attempts: usize },
+}
+
+fn failure_note(failed: &QueryError, correction: &Correction) -> String {
+    match correction {
+        Correction::None => format!("⚠ Query failed: {}", failed.message),
+        Correction::Attempt { attempt, max_attempts } => format!(
+            "⚠ Query failed: {}\nAsking the LLM for a correction (attempt {}/{})...", failed.message, attempt, max_attempts
+        ),
+        Correction::Exhausted { max_attempts } => format!(
+            "⚠ Query still fails after {} correction attempts: {}", max_attempts, failed.message
+        ),
+    }
+}
+
+/// Reports a failed query in the conversation of its connection, then feeds it back to the LLM when
+/// it was generated in the open conversation and attempts are left. A `rerun` for another page or
+/// order of a query that already worked is only reported.
+pub fn report_query_error(app_state: &mut AppState, failed: &QueryError, rerun: bool) {
+    let open = app_state.conversation.id == Some(failed.connection_id);
+    let llm_client = app_state.llm_client.clone().filter(|client| open && !rerun && client.max_correction_attempts() > 0);
+    // Only generated queries are corrected, statements from the SQL console are left to the user
+    let generated = app_state.conversation.messages.iter().any(|m| m.uuid == failed.id && matches!(m.sender, Sender::System));
+    let correction = match &llm_client {
+        Some(llm_client) if generated => {
+            let max_attempts = llm_client.max_correction_attempts();
+            let attempt = app_state.conversation.meta
+                .get(&failed.id)
+                .and_then(|meta| meta.correction_attempt)
+                .unwrap_or(0) + 1;
+            if attempt > max_attempts {
+                Correction::Exhausted { max_attempts }
+            } else {
+                Correction::Attempt { attempt, max_attempts }
+            }
+        }
+        _ => Correction::None,
+    };
+
+    let note = note(failure_note(failed, &correction));
+    if open {
+        apply_reply(app_state, &failed.connection_id, Ok(ChatReply { messages: vec![note], run: None }));
+    } else if let Err(err) = store_message(&app_state.chat_storage, &failed.connection_id, &note.0, &note.1) {
+        error!("Failed to store query error: {}", err);
+    }
+
+    let (Some(llm_client), Correction::Attempt { attempt, .. }) = (llm_client, correction) else {
+        return;
+    };
+    let uuid = failed.connection_id;
+    let history = llm_client.conversation_history(&app_state.conversation.messages, &app_state.conversation.meta);
+    let db_manager = app_state.db_manager.clone();
+    let schema_cache = app_state.schema_cache.clone();
+    let failed_query = failed.query.clone();
+    let error = failed.message.clone();
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    app_state.conversation.background.push(rx);
+    app_state.runtime.spawn(async move {
+        let res = async {
+            let schema = schema_cache.get(&uuid).await?;
+            let schema = llm_client.schema_context(&schema, &format!("{}\n{}", failed_query, error), &history);
+            let dialect = db_manager.dialect(&uuid).await?;
+            let response = llm_client.correct_sql(&history, &failed_query, &error, &schema, dialect).await?;
+            let messages: Vec<_> = to_messages(response)
+                .into_iter()
+                .map(|(message, meta)| (message, MessageMeta { correction_attempt: Some(attempt), ..meta }))
+                .collect();
+            let run = messages.iter().find(|(message, _)| message.is_sql).map(|(message, _)| message.uuid);
+            Ok(ChatReply { messages, run })
+        }.await;
+        tx.send(res).await.ok();
+    });
+}
+
+/// Asks the LLM to summarize a result window in plain language. The summary is posted to the
+/// conversation of the result's connection, linked to the query it explains.
+pub fn request_explanation(app_state: &mut AppState, index: usize) {
+    let Some(uuid) = app_state.conversation.id else {
+        return;
+    };
+    let Some(llm_client) = app_state.llm_client.clone() else {
+        return;
+    };
+    let result = &app_state.query_result[index];
+    if result.connection_id != uuid {
+        return;
+    }
+
+    apply_reply(app_state, &uuid, Ok(ChatReply {
+        messages: vec![note("Asking the LLM to explain the results...".to_string())],
+        run: None,
+    }));
+
+    let result = &app_state.query_result[index];
+    let query_id = result.id;
+    let query = result.query.clone();
+    let data = result.data.clone();
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    app_state.conversation.background.push(rx);
+    app_state.runtime.spawn(async move {
+        let res = llm_client.explain_results(&query, &data).await.map(|summary| ChatReply {
+            messages: vec![(
+                Message::new(Sender::System, summary, false),
+                MessageMeta { explains: Some(query_id), ..Default::default() },
+            )],
+            run: None,
+        });
+        tx.send(res).await.ok();
     });
 }
 
+fn to_messages(response: Vec<ContentResponse>) -> Vec<(Message, MessageMeta)> {
+    response.into_iter().map(|res| {
+        let is_sql = res.r#type == ResponseType::Query;
+        let meta = MessageMeta { visualization: res.visualization.filter(|_| is_sql), ..Default::default() };
+        (Message::new(Sender::System, res.message, is_sql), meta)
+    }).collect()
+}
 
-pub async fn send_message(llm_client:  &LLMClient, db_manager: &DatabaseManager, element_uuid: &Uuid, msg: String) -> Result<(Message, Vec<Message>), String> {
+pub async fn send_message(llm_client:  &LLMClient, db_manager: &DatabaseManager, schema_cache: &SchemaCache, element_uuid: &Uuid, history: &[ChatTurn], msg: String) -> Result<ChatReply, String> {
     let message = Message::new(Sender::User, msg, false);
-    let schema = db_manager.get_schema_info(element_uuid).await?;
-    let response = match llm_client.generate_sql(&message.content, &schema).await {
+    let schema = schema_cache.get(element_uuid).await?;
+    let schema = llm_client.schema_context(&schema, &message.content, history);
+    let dialect = db_manager.dialect(element_uuid).await?;
+    let response = match llm_client.generate_sql(history, &message.content, &schema, dialect).await {
         Ok(res) => {
             debug!("System response: {:?}", res);
             res
@@ -169,9 +552,40 @@ pub async fn send_message(llm_client:  &LLMClient, db_manager: &DatabaseManager,
         }
     };
 
-    // let system_response = Message::new(Sender::System, response.message, response.r#type == ResponseType::Query);
-    let system_responses = response.iter().map(|res| {
-        Message::new(Sender::System, res.message.to_string(), res.r#type == ResponseType::Query)
-    }).collect();
-    Ok((message, system_responses))
-}
\ No newline at end of file
+    let mut messages = vec![(message, MessageMeta::default())];
+    messages.extend(to_messages(response));
+    Ok(ChatReply { messages, run: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::DbType;
+    use crate::db_element::chat_storage::ChatStorage;
+    use crate::ui::chat::{failure_note, note, store_message, Correction};
+    use crate::ui::query_result::QueryError;
+    use crate::utils::sql_guard::ensure_read_only;
+    use tempfile::tempdir;
+    use uuid::Uuid;
+
+    #[test]
+    fn blocked_statement_is_shown_in_the_conversation() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let chat_storage = ChatStorage::new(temp_dir.path().join("test_db")).expect("Failed to initialize ChatStorage");
+        let connection_id = Uuid::new_v4();
+        let query = "DELETE FROM users";
+        let failed = QueryError {
+            id: Uuid::new_v4(),
+            connection_id,
+            query: query.to_string(),
+            message: ensure_read_only(query, &DbType::SQLite).expect_err("Query should be rejected"),
+        };
+
+        let (message, meta) = note(failure_note(&failed, &Correction::None));
+        store_message(&chat_storage, &connection_id, &message, &meta).unwrap();
+
+        let messages = chat_storage.get_conversation(&connection_id).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].content.contains("Query rejected, only read-only queries are allowed but it contains a DELETE statement"));
+        assert!(chat_storage.get_message_meta(&connection_id).unwrap()[&messages[0].uuid].note);
+    }
+}
@@ -14,6 +14,10 @@ pub struct Connection {
     pub username: String,
     pub database: String,
     pub password: String,
+    pub read_only: bool,
+    pub statement_timeout: u64,
+    /// Comma separated, see `DbConnection::schemas`.
+    pub schemas: String,
     success_message: Option<String>,
     error_message: Option<String>,
     loading_message: Option<String>,
@@ -33,6 +37,9 @@ impl Clone for Connection {
             username: self.username.clone(),
             database: self.database.clone(),
             password: self.password.clone(),
+            read_only: self.read_only,
+            statement_timeout: self.statement_timeout,
+            schemas: self.schemas.clone(),
             success_message: None,
             error_message: None,
             loading_message: None,
@@ -54,6 +61,9 @@ impl Connection {
             username: "root".to_string(),
             database: "".to_string(),
             password: "".to_string(),
+            read_only: true,
+            statement_timeout: 0,
+            schemas: "".to_string(),
             success_message: None,
             error_message: None,
             loading_message: None,
@@ -61,6 +71,14 @@ impl Connection {
             rx: None,
         }
     }
+
+    pub fn schema_list(&self) -> Vec<String> {
+        self.schemas
+            .split(',')
+            .map(|schema| schema.trim().to_string())
+            .filter(|schema| !schema.is_empty())
+            .collect()
+    }
 }
 pub fn connection_ui(ctx: &Context, app_state: &mut AppState) {
     info!("Rending connection");
@@ -84,35 +102,74 @@ pub fn connection_ui(ctx: &Context, app_state: &mut AppState) {
                 DbType::PostgreSQL,
                 "PostgreSQL",
             );
+            ui.radio_value(&mut app_state.connection.db_type, DbType::SQLite, "SQLite");
+            ui.radio_value(&mut app_state.connection.db_type, DbType::SQLServer, "SQL Server");
         });
 
-        ui.horizontal(|ui| {
-            ui.label("Host:");
-            ui.text_edit_singleline(&mut app_state.connection.host);
-        });
+        if app_state.connection.db_type.is_file_based() {
+            ui.horizontal(|ui| {
+                ui.label("Database File:");
+                ui.text_edit_singleline(&mut app_state.connection.database);
+                if ui.button("Browse...").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("SQLite database", &["db", "sqlite", "sqlite3", "db3"])
+                        .add_filter("All files", &["*"])
+                        .pick_file()
+                    {
+                        app_state.connection.database = path.display().to_string();
+                    }
+                }
+            });
+        } else {
+            ui.horizontal(|ui| {
+                ui.label("Host:");
+                ui.text_edit_singleline(&mut app_state.connection.host);
+            });
 
-        ui.horizontal(|ui| {
-            ui.label("Port:");
-            let mut port_str = app_state.connection.port.to_string();
-            ui.text_edit_singleline(&mut port_str);
-            if let Ok(port) = port_str.parse::<u16>() {
-                app_state.connection.port = port;
-            }
-        });
+            ui.horizontal(|ui| {
+                ui.label("Port:");
+                let mut port_str = app_state.connection.port.to_string();
+                ui.text_edit_singleline(&mut port_str);
+                if let Ok(port) = port_str.parse::<u16>() {
+                    app_state.connection.port = port;
+                }
+            });
 
-        ui.horizontal(|ui| {
-            ui.label("Database Name:");
-            ui.text_edit_singleline(&mut app_state.connection.database);
-        });
+            ui.horizontal(|ui| {
+                ui.label("Database Name:");
+                ui.text_edit_singleline(&mut app_state.connection.database);
+            });
 
-        ui.horizontal(|ui| {
-            ui.label("Username:");
-            ui.text_edit_singleline(&mut app_state.connection.username);
-        });
+            ui.horizontal(|ui| {
+                ui.label("Username:");
+                ui.text_edit_singleline(&mut app_state.connection.username);
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Password:");
+                ui.add(TextEdit::singleline(&mut app_state.connection.password).password(true));
+            });
+
+            if app_state.connection.db_type == DbType::PostgreSQL {
+                ui.horizontal(|ui| {
+                    ui.label("Schemas:");
+                    ui.add(TextEdit::singleline(&mut app_state.connection.schemas).hint_text("public, analytics"))
+                        .on_hover_text("Comma separated, used as the search_path. Leave empty for the server default.");
+                });
+            }
+        }
+
+        ui.checkbox(&mut app_state.connection.read_only, "Read-only session")
+            .on_hover_text(if app_state.connection.db_type == DbType::SQLServer {
+                "Connects with ApplicationIntent=ReadOnly. SQL Server only enforces it on read-only replicas."
+            } else {
+                "Every connection is opened read-only, so the database refuses writes."
+            });
 
         ui.horizontal(|ui| {
-            ui.label("Password:");
-            ui.add(TextEdit::singleline(&mut app_state.connection.password).password(true));
+            ui.label("Statement timeout:");
+            ui.add(egui::DragValue::new(&mut app_state.connection.statement_timeout).range(0..=86400).suffix(" s"))
+                .on_hover_text("Longest a single query may run, 0 for no limit.");
         });
 
         ui.add_space(20.0);
@@ -138,6 +195,9 @@ pub fn connection_ui(ctx: &Context, app_state: &mut AppState) {
                     port: app_state.connection.port,
                     username: app_state.connection.username.clone(),
                     database: app_state.connection.database.clone(),
+                    read_only: app_state.connection.read_only,
+                    statement_timeout: app_state.connection.statement_timeout,
+                    schemas: app_state.connection.schema_list(),
                 };
                 let db_manager = app_state.db_manager.clone();
 
@@ -171,7 +231,11 @@ pub fn connection_ui(ctx: &Context, app_state: &mut AppState) {
                         Some("Connection name cannot be empty".to_string());
                 } else if app_state.connection.database.trim().is_empty() {
                     app_state.connection.error_message =
-                        Some("Database name cannot be empty".to_string());
+                        Some(if app_state.connection.db_type.is_file_based() {
+                            "Database file cannot be empty".to_string()
+                        } else {
+                            "Database name cannot be empty".to_string()
+                        });
                 } else {
                     if let Err(err) = app_state.save_db() {
                         app_state.connection.error_message =
@@ -0,0 +1,96 @@
+use crate::app::{AppMode, AppState};
+use crate::db_element::chat::{Message, Sender};
+use crate::ui::chat::render_top_bar;
+use crate::ui::sql_editor::sql_editor;
+use egui::{Color32, Context, Key, Modifiers, RichText};
+use log::error;
+use uuid::Uuid;
+
+/// SQL typed by hand for the open connection, run without the LLM round trip.
+pub struct Console {
+    pub sql: String,
+    /// Message recorded for the last statement run from the console.
+    last_run: Option<Uuid>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self { sql: String::new(), last_run: None }
+    }
+
+    /// Appends text to the statement, separated by a space.
+    pub fn insert_text(&mut self, text: &str) {
+        if !self.sql.is_empty() && !self.sql.ends_with(char::is_whitespace) {
+            self.sql.push(' ');
+        }
+        self.sql.push_str(text);
+    }
+}
+
+pub fn render_console(ctx: &Context, app_state: &mut AppState) {
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let Some(uuid) = app_state.conversation.id else {
+            app_state.mode = AppMode::Home;
+            return;
+        };
+        render_top_bar(ui, app_state, &uuid);
+
+        ui.label(RichText::new("Statements run directly against the database, with the same read-only checks as generated queries. \
+            They're added to the chat history so later questions can refer to them.").weak());
+        ui.add_space(4.0);
+
+        // Consumed before the editor sees it, otherwise it would also insert a new line
+        let shortcut = ui.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::Enter));
+        sql_editor(ui, &mut app_state.console.sql);
+        ui.add_space(4.0);
+
+        let mut run = false;
+        let mut cancel = None;
+        ui.horizontal(|ui| {
+            let running = app_state.console.last_run
+                .filter(|id| app_state.conversation.loading_query.borrow().contains(id));
+            if let Some(id) = running {
+                ui.add_enabled(false, egui::Button::new("⏳ Running..."));
+                if ui.button("✖ Cancel").clicked() {
+                    cancel = Some(id);
+                }
+                return;
+            }
+
+            run = (ui.button("▶ Run").on_hover_text("Ctrl+Enter").clicked() || shortcut)
+                && !app_state.console.sql.trim().is_empty();
+
+            let execution = app_state.console.last_run
+                .and_then(|id| app_state.conversation.meta.get(&id))
+                .and_then(|meta| meta.execution.as_ref());
+            match execution {
+                Some(execution) if execution.success => {
+                    ui.colored_label(Color32::from_rgb(80, 200, 120), format!("✔ {} rows", execution.row_count));
+                }
+                Some(execution) => {
+                    ui.colored_label(Color32::from_rgb(230, 80, 80), format!("✖ {}", execution.error.as_deref().unwrap_or("Query failed")));
+                }
+                None => {}
+            }
+        });
+
+        if let Some(id) = cancel {
+            app_state.cancel_query(&id);
+        }
+        if run {
+            run_statement(app_state, &uuid);
+        }
+    });
+}
+
+/// Records the statement in the conversation and runs it like a generated query.
+fn run_statement(app_state: &mut AppState, uuid: &Uuid) {
+    let message = Message::new(Sender::User, app_state.console.sql.clone(), true);
+    if let Err(err) = app_state.chat_storage.add_message(uuid, &message) {
+        error!("Failed to store console statement: {}", err);
+    }
+    app_state.console.last_run = Some(message.uuid);
+    app_state.conversation.loading_query.borrow_mut().push(message.uuid);
+    app_state.run_query(uuid, &message.content, &message.uuid, 1);
+    app_state.conversation.messages.push(message);
+}
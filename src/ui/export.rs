@@ -0,0 +1,174 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use crate::app::AppState;
+use crate::db_element::db::CancelToken;
+use crate::utils::export::{ExportFormat, FileExporter};
+use egui::{Color32, ProgressBar, Ui};
+use log::error;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// A result window's query streamed to a file in the background.
+pub struct ExportJob {
+    result_id: Uuid,
+    connection_id: Uuid,
+    path: PathBuf,
+    /// Row count of the result, what the progress is measured against.
+    total_rows: usize,
+    progress: Arc<AtomicUsize>,
+    handle: Option<JoinHandle<()>>,
+    cancel: CancelToken,
+    rx: oneshot::Receiver<Result<(), String>>,
+    outcome: Option<Result<usize, String>>,
+}
+
+/// Asks where to save and starts re-running the query of the result window without pagination.
+pub fn start_export(app_state: &mut AppState, index: usize, format: ExportFormat) {
+    let result = &app_state.query_result[index];
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter(format.label(), &[format.extension()])
+        .set_file_name(format!("export.{}", format.extension()))
+        .save_file()
+    else {
+        return;
+    };
+
+    let (tx, rx) = oneshot::channel();
+    let progress = Arc::new(AtomicUsize::new(0));
+    let cancel = CancelToken::default();
+    let db_manager = app_state.db_manager.clone();
+    let connection_id = result.connection_id;
+    let query = result.query.clone();
+    let task_path = path.clone();
+    let task_progress = progress.clone();
+    let task_cancel = cancel.clone();
+    let export = async move {
+        let mut exporter = FileExporter::new(format, task_path, task_progress.clone());
+        db_manager.stream_query(&connection_id, &query, &task_cancel, &mut exporter).await?;
+        exporter.finish()?;
+        Ok(task_progress.load(Ordering::Relaxed))
+    };
+    // Logged as page 0, every row at once
+    let execution = app_state.supervise(connection_id, result.query.clone(), 0, cancel.clone(), export, |rows| *rows);
+    let task_path = path.clone();
+    let handle = app_state.runtime.spawn(async move {
+        // Cancelled, `cancel_export` cleans up
+        let Some(res) = execution.await else {
+            return;
+        };
+        let res = res.map(|_| ());
+        if let Err(err) = &res {
+            error!("Failed to export query: {}", err);
+            std::fs::remove_file(&task_path).ok();
+        }
+        tx.send(res).ok();
+    });
+
+    let job = ExportJob {
+        result_id: result.id,
+        connection_id,
+        path,
+        total_rows: result.data.total_rows,
+        progress,
+        handle: Some(handle),
+        cancel,
+        rx,
+        outcome: None,
+    };
+    // One export per window, a new one replaces a finished one
+    app_state.exports.retain(|job| job.result_id != result.id);
+    app_state.exports.push(job);
+}
+
+/// Picks up finished exports. They outlive their window, the ones whose window is gone are dropped once done.
+pub fn poll_exports(app_state: &mut AppState) {
+    for job in &mut app_state.exports {
+        if job.outcome.is_some() {
+            continue;
+        }
+        if let Ok(res) = job.rx.try_recv() {
+            job.outcome = Some(res.map(|()| job.progress.load(Ordering::Relaxed)));
+            job.handle = None;
+        }
+    }
+    let open: Vec<Uuid> = app_state.query_result.iter().map(|result| result.id).collect();
+    app_state.exports.retain(|job| job.outcome.is_none() || open.contains(&job.result_id));
+}
+
+/// Export menu of a result window, or the progress of its running export.
+pub fn render_export(ui: &mut Ui, app_state: &mut AppState, index: usize) {
+    let result_id = app_state.query_result[index].id;
+    let mut start = None;
+    let mut cancel = false;
+    let mut dismiss = false;
+
+    match app_state.exports.iter().find(|job| job.result_id == result_id) {
+        Some(job) if job.outcome.is_none() => {
+            let rows = job.progress.load(Ordering::Relaxed);
+            let fraction = if job.total_rows > 0 { rows as f32 / job.total_rows as f32 } else { 0.0 };
+            ui.add(ProgressBar::new(fraction.min(1.0))
+                .desired_width(200.0)
+                .text(format!("Exporting {} / {} rows", rows, job.total_rows)));
+            cancel = ui.button("✖ Cancel").clicked();
+        }
+        Some(job) => {
+            match &job.outcome {
+                Some(Ok(rows)) => {
+                    ui.colored_label(Color32::from_rgb(80, 200, 120), format!("✔ Exported {} rows", rows))
+                        .on_hover_text(job.path.display().to_string());
+                }
+                Some(Err(err)) => {
+                    ui.colored_label(Color32::from_rgb(230, 80, 80), "✖ Export failed").on_hover_text(err);
+                }
+                None => {}
+            }
+            dismiss = ui.small_button("✖").on_hover_text("Dismiss").clicked();
+        }
+        None => {
+            ui.menu_button("⬇ Export", |ui| {
+                for format in ExportFormat::ALL {
+                    if ui.button(format.label()).clicked() {
+                        start = Some(format);
+                        ui.close_menu();
+                    }
+                }
+            });
+        }
+    }
+
+    if let Some(format) = start {
+        start_export(app_state, index, format);
+    }
+    if cancel {
+        cancel_export(app_state, &result_id);
+    }
+    if dismiss {
+        app_state.exports.retain(|job| job.result_id != result_id);
+    }
+}
+
+/// Stops the export on the server and removes the partial file.
+fn cancel_export(app_state: &mut AppState, result_id: &Uuid) {
+    let Some(position) = app_state.exports.iter().position(|job| job.result_id == *result_id) else {
+        return;
+    };
+    let mut job = app_state.exports.remove(position);
+    let Some(handle) = job.handle.take() else {
+        return;
+    };
+    let db_manager = app_state.db_manager.clone();
+    app_state.runtime.spawn(async move {
+        // Stopped on the server while the task still holds its session, a finished one has nothing to stop
+        if !handle.is_finished() {
+            if let Err(err) = db_manager.cancel_query(&job.connection_id, &job.cancel).await {
+                error!("Failed to cancel export: {}", err);
+            }
+        }
+        handle.abort();
+        // Wait for the task to let go of the file before removing it
+        handle.await.ok();
+        std::fs::remove_file(&job.path).ok();
+    });
+}
@@ -0,0 +1,135 @@
+use crate::app::{AppMode, AppState};
+use crate::db_element::query_log::QueryLogEntry;
+use crate::ui::chat::render_top_bar;
+use chrono::Local;
+use egui::{Color32, Context, RichText, TextEdit};
+use egui_extras::{Column, TableBuilder};
+use log::error;
+use uuid::Uuid;
+
+/// Executed queries of the open connection, loaded from the chat store when shown.
+pub struct QueryHistory {
+    entries: Option<Vec<QueryLogEntry>>,
+    search: String,
+}
+
+impl QueryHistory {
+    pub fn new() -> Self {
+        Self { entries: None, search: String::new() }
+    }
+
+    /// Reloads the entries next time the panel is shown, after a query finished.
+    pub fn invalidate(&mut self) {
+        self.entries = None;
+    }
+}
+
+enum HistoryAction {
+    Rerun(String),
+    Copy(String),
+}
+
+pub fn render_history(ctx: &Context, app_state: &mut AppState) {
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let Some(uuid) = app_state.conversation.id else {
+            app_state.mode = AppMode::Home;
+            return;
+        };
+        render_top_bar(ui, app_state, &uuid);
+
+        if app_state.query_history.entries.is_none() {
+            let entries = app_state.chat_storage.get_query_log(&uuid).unwrap_or_else(|err| {
+                error!("Failed to load query history: {}", err);
+                Vec::new()
+            });
+            app_state.query_history.entries = Some(entries);
+        }
+
+        ui.add(TextEdit::singleline(&mut app_state.query_history.search)
+            .hint_text("Search queries and errors")
+            .desired_width(ui.available_width()));
+        ui.add_space(4.0);
+
+        let search = app_state.query_history.search.trim().to_lowercase();
+        let entries: Vec<&QueryLogEntry> = app_state.query_history.entries.iter()
+            .flatten()
+            .filter(|entry| search.is_empty()
+                || entry.sql.to_lowercase().contains(&search)
+                || entry.error.as_ref().is_some_and(|error| error.to_lowercase().contains(&search)))
+            .collect();
+
+        if entries.is_empty() {
+            ui.label(RichText::new("No queries yet").weak());
+            return;
+        }
+
+        let mut action = None;
+        TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::remainder().clip(true))
+            .column(Column::auto())
+            .header(20.0, |mut header| {
+                for title in ["Started", "Duration", "Result", "Page", "Query", ""] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(22.0, entries.len(), |mut row| {
+                    let entry = entries[row.index()];
+                    row.col(|ui| {
+                        ui.label(entry.started_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(format_duration(entry.duration_ms));
+                    });
+                    row.col(|ui| {
+                        match (&entry.error, entry.row_count) {
+                            (Some(error), _) => {
+                                ui.colored_label(Color32::from_rgb(230, 80, 80), "✖ failed").on_hover_text(error);
+                            }
+                            (None, Some(rows)) => {
+                                ui.label(rows.to_string());
+                            }
+                            (None, None) => {}
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.label(if entry.page == 0 { "export".to_string() } else { entry.page.to_string() });
+                    });
+                    row.col(|ui| {
+                        ui.label(entry.sql.replace('\n', " ")).on_hover_text(&entry.sql);
+                    });
+                    row.col(|ui| {
+                        if ui.small_button("▶ Rerun").clicked() {
+                            action = Some(HistoryAction::Rerun(entry.sql.clone()));
+                        }
+                        if ui.small_button("📋 Copy").clicked() {
+                            action = Some(HistoryAction::Copy(entry.sql.clone()));
+                        }
+                    });
+                });
+            });
+
+        match action {
+            // A new result window, the entry of the rerun shows up once it finishes
+            Some(HistoryAction::Rerun(sql)) => app_state.run_query(&uuid, &sql, &Uuid::new_v4(), 1),
+            Some(HistoryAction::Copy(sql)) => ui.ctx().copy_text(sql),
+            None => {}
+        }
+    });
+}
+
+fn format_duration(duration_ms: u64) -> String {
+    if duration_ms < 1000 {
+        format!("{} ms", duration_ms)
+    } else {
+        format!("{:.2} s", duration_ms as f64 / 1000.0)
+    }
+}
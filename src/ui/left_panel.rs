@@ -1,9 +1,15 @@
 use crate::app::{AppMode, AppState};
+use crate::config::DbConnection;
 use crate::security::SecureStorage;
 use crate::ui::chat::Conversation;
 use crate::ui::connection::Connection;
+use crate::ui::console::Console;
+use crate::ui::history::QueryHistory;
+use crate::ui::saved_queries::apply_saved_action;
+use crate::ui::schema_browser::{BrowserAction, SchemaBrowser};
 use egui::{Align, Context, Layout};
 use log::info;
+use uuid::Uuid;
 
 pub fn left_panel_ui(ctx: &Context, app_state: &mut AppState) {
     info!("Rendering left panel");
@@ -11,7 +17,11 @@ pub fn left_panel_ui(ctx: &Context, app_state: &mut AppState) {
         .resizable(true)
         .show(ctx, |ui| {
             side_menu(ui, app_state);
-            connection_list(ui, app_state);
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    connection_list(ui, app_state);
+                });
         });
 }
 
@@ -34,28 +44,16 @@ pub fn side_menu(ui: &mut egui::Ui, app_state: &mut AppState) {
 
 pub fn connection_list(ui: &mut egui::Ui, app_state: &mut AppState) {
     info!("Rendering connections left panel");
+    let mut action = None;
+    let mut saved_action = None;
+    let mut open = None;
     for con in &app_state.config.connections {
         ui.horizontal(|ui| {
             if ui
                 .add(egui::Button::new(con.name.clone()).frame(false))
                 .clicked()
             {
-                app_state.conversation = Conversation::new(Some(con.uuid.clone()));
-                app_state.conversation.messages = app_state
-                    .chat_storage
-                    .get_conversation(&con.uuid)
-                    .unwrap_or_else(|_| vec![]);
-                let db_config = con.clone();
-                let pass = if let Ok(pwd) = SecureStorage::get_db_password(&con.uuid.to_string()) {
-                    pwd
-                } else {
-                    return;
-                };
-                let db_manager = app_state.db_manager.clone();
-                app_state
-                    .runtime
-                    .spawn(async move { db_manager.connect(&db_config, Some(pass), false).await });
-                app_state.mode = AppMode::Chat;
+                open = Some(con.clone());
             }
 
             ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
@@ -69,6 +67,9 @@ pub fn connection_list(ui: &mut egui::Ui, app_state: &mut AppState) {
                     existing_connection.port = con.port.clone();
                     existing_connection.db_type = con.db_type.clone();
                     existing_connection.username = con.username.clone();
+                    existing_connection.read_only = con.read_only;
+                    existing_connection.statement_timeout = con.statement_timeout;
+                    existing_connection.schemas = con.schemas.join(", ");
                     if let Ok(pwd) = SecureStorage::get_db_password(&con.uuid.to_string()) {
                         existing_connection.password = pwd;
                     }
@@ -78,6 +79,88 @@ pub fn connection_list(ui: &mut egui::Ui, app_state: &mut AppState) {
             });
         });
 
+        if app_state.schema_browser.connection_id == Some(con.uuid) {
+            if let Some(clicked) = app_state.schema_browser.render(ui, &con.db_type) {
+                action = Some((con.uuid, clicked));
+            }
+        }
+        if let Some(clicked) = app_state.saved_queries.render_list(ui, &app_state.chat_storage, &con.uuid) {
+            saved_action = Some(clicked);
+        }
+
         ui.separator();
     }
+
+    if let Some(con) = open {
+        if open_connection(app_state, &con) {
+            app_state.mode = AppMode::Chat;
+        }
+    }
+    if let Some((connection_id, action)) = action {
+        apply_action(app_state, connection_id, action);
+    }
+    if let Some(action) = saved_action {
+        apply_saved_action(app_state, action);
+    }
+}
+
+/// Makes the connection the active one: loads its conversation and connects in the background.
+/// Returns false when the password of the connection can't be read.
+pub fn open_connection(app_state: &mut AppState, con: &DbConnection) -> bool {
+    app_state.conversation = Conversation::new(Some(con.uuid));
+    app_state.console = Console::new();
+    app_state.query_history = QueryHistory::new();
+    app_state.saved_queries.cancel_pending();
+    app_state.conversation.messages = app_state
+        .chat_storage
+        .get_conversation(&con.uuid)
+        .unwrap_or_else(|_| vec![]);
+    app_state.conversation.meta = app_state
+        .chat_storage
+        .get_message_meta(&con.uuid)
+        .unwrap_or_default();
+    let revisions = app_state.chat_storage.get_revisions(&con.uuid).unwrap_or_default();
+    app_state.conversation.apply_revisions(revisions);
+    let db_config = con.clone();
+    let pass = if con.db_type.is_file_based() {
+        String::new()
+    } else if let Ok(pwd) = SecureStorage::get_db_password(&con.uuid.to_string()) {
+        pwd
+    } else {
+        return false;
+    };
+    let db_manager = app_state.db_manager.clone();
+    let schema_cache = app_state.schema_cache.clone();
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    app_state.schema_browser = SchemaBrowser::load(con.uuid, rx);
+    app_state.runtime.spawn(async move {
+        // A cached schema is checked against the database and flagged when it no longer matches
+        let res = async {
+            db_manager.connect(&db_config, Some(pass), false).await?;
+            schema_cache.get(&db_config.uuid).await
+        }.await;
+        tx.send(res).await.ok();
+    });
+    true
+}
+
+fn apply_action(app_state: &mut AppState, connection_id: Uuid, action: BrowserAction) {
+    match action {
+        BrowserAction::Insert(name) => {
+            if app_state.conversation.id != Some(connection_id) {
+                return;
+            }
+            if let AppMode::Console = app_state.mode {
+                app_state.console.insert_text(&name);
+            } else {
+                app_state.conversation.insert_text(&name);
+                app_state.mode = AppMode::Chat;
+            }
+        }
+        BrowserAction::Preview(query) => {
+            let id = Uuid::new_v4();
+            app_state.schema_browser.add_preview(id);
+            app_state.run_query(&connection_id, &query, &id, 1);
+        }
+    }
 }
@@ -3,5 +3,12 @@ mod left_panel;
 pub mod connection;
 pub mod setting;
 pub mod home;
+pub mod chart;
 pub mod chat;
-pub mod query_result;
\ No newline at end of file
+pub mod console;
+pub mod export;
+pub mod history;
+pub mod query_result;
+pub mod saved_queries;
+pub mod schema_browser;
+pub mod sql_editor;
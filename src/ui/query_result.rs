@@ -1,8 +1,15 @@
 use crate::app::AppState;
-use crate::db_element::db::QueryResult;
+use crate::db_element::chat::QueryExecution;
+use std::collections::HashSet;
+use crate::db_element::db::{QueryResult, SortOrder};
+use crate::utils::chart::ChartSpec;
+use crate::ui::chart::{render_chart, stop_chart, toggle_chart, ChartView};
+use crate::ui::chat::{report_query_error, request_explanation};
+use crate::ui::export::{poll_exports, render_export};
 use eframe::emath::Align;
-use egui::{Color32, Context, Frame, RichText, TextEdit, Ui, Window};
+use egui::{Color32, Context, Frame, Label, RichText, Sense, TextEdit, Ui, Window};
 use egui_extras::{Column, Size, StripBuilder, TableBuilder};
+use log::warn;
 use uuid::Uuid;
 
 pub struct ResultTable {
@@ -10,9 +17,126 @@ pub struct ResultTable {
     pub connection_id: Uuid,
     pub query: String,
     pub data: QueryResult,
+    pub view: TableView,
     pub is_open: bool,
     pub edited_page: usize,
 }
+
+/// How a `ResultTable` is shown. Kept while paging, dropped when the window gets another query.
+#[derive(Default)]
+pub struct TableView {
+    pub sort: Option<SortOrder>,
+    /// Text per column a cell has to contain, ignoring case.
+    filters: Vec<String>,
+    hidden: HashSet<usize>,
+    /// Shown first and left out of the horizontal scroll.
+    pinned: Vec<usize>,
+    /// Display order, empty until a column is moved.
+    order: Vec<usize>,
+    pub chart: Option<ChartView>,
+    pub show_chart: bool,
+}
+
+enum ColumnAction {
+    Sort(usize),
+    Hide(usize),
+    Show(usize),
+    TogglePin(usize),
+    Move(usize, isize),
+}
+
+impl TableView {
+    fn order(&self, count: usize) -> Vec<usize> {
+        if self.order.len() == count {
+            self.order.clone()
+        } else {
+            (0..count).collect()
+        }
+    }
+
+    /// Visible columns, the pinned ones and the others in display order.
+    fn columns(&self, count: usize) -> (Vec<usize>, Vec<usize>) {
+        let pinned = self.pinned.iter()
+            .filter(|column| **column < count && !self.hidden.contains(column))
+            .copied()
+            .collect();
+        let others = self.order(count).into_iter()
+            .filter(|column| !self.hidden.contains(column) && !self.pinned.contains(column))
+            .collect();
+        (pinned, others)
+    }
+
+    /// Indices of the rows passing the filters, sorted here when `sort_rows` and not by the server.
+    fn rows(&self, data: &QueryResult, sort_rows: bool) -> Vec<usize> {
+        let filters: Vec<(usize, String)> = self.filters.iter().enumerate()
+            .filter(|(_, filter)| !filter.trim().is_empty())
+            .map(|(column, filter)| (column, filter.trim().to_lowercase()))
+            .collect();
+        let mut rows: Vec<usize> = (0..data.rows.len())
+            .filter(|row| filters.iter().all(|(column, filter)| {
+                data.rows[*row].get(*column).is_some_and(|cell| cell.to_string().to_lowercase().contains(filter))
+            }))
+            .collect();
+        if let Some(sort) = self.sort.as_ref().filter(|_| sort_rows) {
+            rows.sort_by(|a, b| {
+                let (a, b) = (&data.rows[*a][sort.column], &data.rows[*b][sort.column]);
+                // NULLs stay last either way
+                if sort.descending && !a.is_null() && !b.is_null() {
+                    b.sort_cmp(a)
+                } else {
+                    a.sort_cmp(b)
+                }
+            });
+        }
+        rows
+    }
+
+    fn apply(&mut self, action: ColumnAction, count: usize) {
+        match action {
+            ColumnAction::Sort(_) => {}
+            ColumnAction::Hide(column) => {
+                self.hidden.insert(column);
+            }
+            ColumnAction::Show(column) => {
+                self.hidden.remove(&column);
+            }
+            ColumnAction::TogglePin(column) => {
+                if let Some(position) = self.pinned.iter().position(|pinned| *pinned == column) {
+                    self.pinned.remove(position);
+                } else {
+                    self.pinned.push(column);
+                }
+            }
+            ColumnAction::Move(column, step) => {
+                self.order = self.order(count);
+                // A pinned column moves among the pinned ones
+                let is_pinned = self.pinned.contains(&column);
+                let shown = |c: &usize| !self.hidden.contains(c) && (is_pinned || !self.pinned.contains(c));
+                let order = if is_pinned { &self.pinned } else { &self.order };
+                let Some(position) = order.iter().position(|c| *c == column) else {
+                    return;
+                };
+                // Swap with the nearest shown column that way, hidden ones stay where they are
+                let mut target = position as isize + step.signum();
+                while target >= 0 && (target as usize) < order.len() && !shown(&order[target as usize]) {
+                    target += step.signum();
+                }
+                if target >= 0 && (target as usize) < order.len() {
+                    let order = if is_pinned { &mut self.pinned } else { &mut self.order };
+                    order.swap(position, target as usize);
+                }
+            }
+        }
+    }
+}
+
+/// A failed `run_query`, identified like the `ResultTable` it would have produced.
+pub struct QueryError {
+    pub id: Uuid,
+    pub connection_id: Uuid,
+    pub query: String,
+    pub message: String,
+}
 pub fn render_result(ctx: &Context, app_state: &mut AppState) {
 
     for i in 0..app_state.query_result.len() {
@@ -21,25 +145,56 @@ pub fn render_result(ctx: &Context, app_state: &mut AppState) {
         let height = ctx.screen_rect().height() * 0.85;
 
         let mut is_open = app_state.query_result[i].is_open;
+        let mut column_action = None;
 
         Window::new(&app_state.query_result[i].query).open(&mut is_open).default_width(width).show(ctx, |ui| {
             Frame::NONE.show(ui, |ui| {
-                StripBuilder::new(ui).size(Size::exact(10.0)).size(Size::initial(height))
+                StripBuilder::new(ui).size(Size::exact(30.0)).size(Size::initial(height))
                     .vertical(|mut strip| {
                         strip.cell(|ui| {
-                            if app_state.query_result[i].data.total_pages > 1 {
-                                ui.add_space(5.0);
-                                render_pagination(ui, app_state, i);
-                                ui.add_space(5.0);
+                            ui.add_space(5.0);
+                            ui.horizontal(|ui| {
+                                if ui.button("⭐ Save query").clicked() {
+                                    let result = &app_state.query_result[i];
+                                    app_state.saved_queries.open_editor(result.connection_id, String::new(), result.query.clone());
+                                }
+                                render_export(ui, app_state, i);
+                                let can_explain = app_state.llm_client.is_some()
+                                    && app_state.conversation.id == Some(app_state.query_result[i].connection_id);
+                                let explain = ui.add_enabled(can_explain, egui::Button::new("💡 Explain"))
+                                    .on_hover_text("Summarize the results in the chat")
+                                    .on_disabled_hover_text("Open the chat of this connection to explain its results");
+                                if explain.clicked() {
+                                    request_explanation(app_state, i);
+                                }
                                 ui.separator();
-                            }
+                                let show_chart = app_state.query_result[i].view.show_chart;
+                                if ui.button(if show_chart { "📋 Table" } else { "📈 Chart" }).clicked() {
+                                    toggle_chart(app_state, i);
+                                }
+                                if show_chart {
+                                    return;
+                                }
+                                if let Some(action) = render_columns_menu(ui, &app_state.query_result[i]) {
+                                    column_action = Some(action);
+                                }
+                                if app_state.query_result[i].data.total_pages > 1 {
+                                    ui.separator();
+                                    render_pagination(ui, app_state, i);
+                                }
+                            });
+                            ui.add_space(5.0);
+                            ui.separator();
                         });
                         strip.cell(|ui| {
+                            if app_state.query_result[i].view.show_chart {
+                                render_chart(ui, app_state, i);
+                                return;
+                            }
                             ui.vertical_centered(|ui| {
-                                egui::ScrollArea::horizontal()
-                                    .show(ui, |ui| {
-                                        render_table(&app_state.query_result[i], ui);
-                                });
+                                if let Some(action) = render_table(ui, &mut app_state.query_result[i]) {
+                                    column_action = Some(action);
+                                }
                                 ui.separator();
                             });
                         });
@@ -47,28 +202,83 @@ pub fn render_result(ctx: &Context, app_state: &mut AppState) {
             });
         });
         app_state.query_result[i].is_open = is_open;
+        match column_action {
+            Some(ColumnAction::Sort(column)) => sort_by(app_state, i, column),
+            Some(action) => {
+                let count = app_state.query_result[i].data.columns.len();
+                app_state.query_result[i].view.apply(action, count);
+            }
+            None => {}
+        }
     }
 
-    app_state.query_result.retain(|r| r.is_open);
+    let (open, closed): (Vec<ResultTable>, Vec<ResultTable>) = std::mem::take(&mut app_state.query_result)
+        .into_iter()
+        .partition(|r| r.is_open);
+    app_state.query_result = open;
+    for mut result in closed {
+        stop_chart(app_state, result.view.chart.as_mut());
+    }
+    poll_exports(app_state);
 
     if let Ok(res) = app_state.query_rx.try_recv() {
+        let id = match &res {
+            Ok(result) => result.id,
+            Err(error) => error.id,
+        };
+        app_state.running_queries.borrow_mut().remove(&id);
+        let preview = app_state.schema_browser.preview_finished(&res);
+        app_state.query_history.invalidate();
+        let index = app_state.conversation.loading_query.borrow().iter().position(|item| *item == id);
+        if let Some(index) = index {
+            app_state.conversation.loading_query.borrow_mut().remove(index);
+        }
+
         match res {
-            Ok(result) => {
-                let index = app_state.conversation.loading_query.borrow().iter().position(|item| *item == result.id);
-                if let Some(index) = index {
-                    app_state.conversation.loading_query.borrow_mut().remove(index);
-                }
+            Ok(mut result) => {
+                app_state.record_execution(&result.connection_id, &result.id, QueryExecution {
+                    success: true,
+                    row_count: result.data.total_rows,
+                    error: None,
+                });
 
                 let index = app_state.query_result.iter().position(|r| r.id == result.id);
                 if let Some(index) = index {
-                    app_state.query_result[index] = result;
+                    // Another page of the same query keeps how the table is shown
+                    let previous = &mut app_state.query_result[index];
+                    if previous.query == result.query {
+                        result.view = std::mem::take(&mut previous.view);
+                    }
+                    let mut previous = std::mem::replace(&mut app_state.query_result[index], result);
+                    stop_chart(app_state, previous.view.chart.as_mut());
                 } else {
+                    // A new window opens in the chart the LLM suggested, when it fits the result
+                    let visualization = app_state.conversation.meta.get(&result.id).and_then(|meta| meta.visualization.as_ref());
+                    if let Some(visualization) = visualization {
+                        match ChartSpec::from_visualization(visualization, &result.data) {
+                            Ok(spec) => {
+                                result.view.chart = Some(ChartView::new(spec));
+                                result.view.show_chart = true;
+                            }
+                            Err(err) => warn!("Ignoring the suggested chart of {}: {}", result.id, err),
+                        }
+                    }
                     app_state.query_result.push(result);
                 }
 
             }
-            Err(error_msg) => {
-                let _ = format!("Connection test failed: {}", error_msg);
+            Err(error) => {
+                app_state.record_execution(&error.connection_id, &error.id, QueryExecution {
+                    success: false,
+                    row_count: 0,
+                    error: Some(error.message.clone()),
+                });
+                // The schema browser shows the errors of its previews
+                if !preview {
+                    // A window still showing the query means a page or sort rerun of a query that already worked
+                    let rerun = app_state.query_result.iter().any(|r| r.id == error.id && r.query == error.query);
+                    report_query_error(app_state, &error, rerun);
+                }
             }
         }
     }
@@ -80,9 +290,7 @@ fn render_pagination(ui: &mut Ui, app_state: &mut AppState, index: usize) {
     ui.horizontal(|ui| {
         if app_state.query_result[index].data.current_page > 1 {
             if ui.button("Prev").clicked() {
-                app_state.run_query(&app_state.query_result[index].connection_id,
-                                    &app_state.query_result[index].query,
-                                    &app_state.query_result[index].id, app_state.query_result[index].data.current_page - 1);
+                go_to_page(app_state, index, app_state.query_result[index].data.current_page - 1);
             }
         }
 
@@ -94,26 +302,100 @@ fn render_pagination(ui: &mut Ui, app_state: &mut AppState, index: usize) {
 
         if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter) && !i.modifiers.shift) {
             if app_state.query_result[index].edited_page != app_state.query_result[index].data.current_page {
-                app_state.run_query(&app_state.query_result[index].connection_id,
-                                    &app_state.query_result[index].query,
-                                    &app_state.query_result[index].id, app_state.query_result[index].edited_page);
+                go_to_page(app_state, index, app_state.query_result[index].edited_page);
             }
         }
 
         ui.label(format!("/ {}", app_state.query_result[index].data.total_pages));
         if app_state.query_result[index].data.current_page < app_state.query_result[index].data.total_pages {
             if ui.button("Next").clicked() {
-                app_state.run_query(&app_state.query_result[index].connection_id,
-                                    &app_state.query_result[index].query,
-                                    &app_state.query_result[index].id, app_state.query_result[index].data.current_page + 1);
+                go_to_page(app_state, index, app_state.query_result[index].data.current_page + 1);
             }
         }
     });
 }
-fn render_table(window: &ResultTable, ui: &mut Ui) {
+
+/// Reruns the query of the window for another page, in the order it's sorted by.
+fn go_to_page(app_state: &AppState, index: usize, page: usize) {
+    let result = &app_state.query_result[index];
+    app_state.run_sorted_query(&result.connection_id, &result.query, &result.id, page, result.view.sort.clone());
+}
+
+/// Cycles the sort of a column through ascending, descending and unsorted. A result of one
+/// page is sorted in place, otherwise the query is rerun ordered from the first page.
+fn sort_by(app_state: &mut AppState, index: usize, column: usize) {
+    let result = &mut app_state.query_result[index];
+    result.view.sort = match &result.view.sort {
+        Some(sort) if sort.column == column && !sort.descending => Some(SortOrder { column, descending: true }),
+        Some(sort) if sort.column == column => None,
+        _ => Some(SortOrder { column, descending: false }),
+    };
+    if result.data.total_pages > 1 {
+        go_to_page(app_state, index, 1);
+    }
+}
+
+fn render_columns_menu(ui: &mut Ui, window: &ResultTable) -> Option<ColumnAction> {
+    let mut action = None;
+    ui.menu_button("☰ Columns", |ui| {
+        let count = window.data.columns.len();
+        for column in window.view.order(count) {
+            ui.horizontal(|ui| {
+                let mut visible = !window.view.hidden.contains(&column);
+                if ui.checkbox(&mut visible, &window.data.columns[column]).changed() {
+                    action = Some(if visible { ColumnAction::Show(column) } else { ColumnAction::Hide(column) });
+                }
+                let pinned = window.view.pinned.contains(&column);
+                if ui.selectable_label(pinned, "📌").on_hover_text("Keep on the left while scrolling").clicked() {
+                    action = Some(ColumnAction::TogglePin(column));
+                }
+                if ui.small_button("⬆").clicked() {
+                    action = Some(ColumnAction::Move(column, -1));
+                }
+                if ui.small_button("⬇").clicked() {
+                    action = Some(ColumnAction::Move(column, 1));
+                }
+            });
+        }
+    });
+    action
+}
+
+/// Pinned columns get a table of their own next to the horizontally scrolled one. Both then
+/// share one vertical scroll area so their rows stay aligned.
+fn render_table(ui: &mut Ui, window: &mut ResultTable) -> Option<ColumnAction> {
+    let count = window.data.columns.len();
+    let (pinned, columns) = window.view.columns(count);
+    if pinned.is_empty() && columns.is_empty() && count > 0 {
+        ui.label(RichText::new("All columns are hidden").weak());
+        return None;
+    }
+    let rows = window.view.rows(&window.data, window.data.total_pages <= 1);
+
+    let mut action = None;
+    if pinned.is_empty() {
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            render_grid(ui, "columns", window, &columns, &rows, true, &mut action);
+        });
+    } else {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.horizontal_top(|ui| {
+                render_grid(ui, "pinned", window, &pinned, &rows, false, &mut action);
+                egui::ScrollArea::horizontal().show(ui, |ui| {
+                    render_grid(ui, "columns", window, &columns, &rows, false, &mut action);
+                });
+            });
+        });
+    }
+    action
+}
+
+fn render_grid(ui: &mut Ui, id_salt: &str, window: &mut ResultTable, columns: &[usize], rows: &[usize], vscroll: bool, action: &mut Option<ColumnAction>) {
     let mut table = TableBuilder::new(ui)
+        .id_salt(id_salt)
         .striped(true)
         .resizable(true)
+        .vscroll(vscroll)
         .cell_layout(egui::Layout::left_to_right(Align::LEFT));
 
 
@@ -124,20 +406,14 @@ fn render_table(window: &ResultTable, ui: &mut Ui) {
         ui.painter().rect_filled(gapless_rect, 0.0, bg);
     };
 
-    let mut sizes = vec![];
-
-    for col in &window.data.columns {
-        sizes.push(col.len());
-    }
-
+    let mut sizes: Vec<usize> = window.data.columns.iter().map(|col| col.len()).collect();
     for row in &window.data.rows {
-        let len = row.len();
-        for i in 0..len {
-            sizes[i] = row[i].len().max(sizes[i]);
+        for (i, cell) in row.iter().enumerate() {
+            sizes[i] = cell.to_string().len().max(sizes[i]);
         }
     }
 
-    for i in 0..window.data.columns.len() {
+    for &i in columns {
         table = table.column(
             if sizes[i] > 50 {
                 Column::initial(sizes[i].min(150) as f32).clip(true)
@@ -146,13 +422,48 @@ fn render_table(window: &ResultTable, ui: &mut Ui) {
             }
         );
     }
+
+    let ResultTable { data, view, .. } = window;
+    let filter_hint = if data.total_pages > 1 { "Filters the rows of this page" } else { "Filters the rows" };
+    view.filters.resize(data.columns.len(), String::new());
     table
-        .header(20.0, |mut header| {
-            for col in &window.data.columns {
+        .header(44.0, |mut header| {
+            for &i in columns {
                 header.col(|ui| {
                     paint_bg(ui);
                     ui.vertical(|ui| {
-                        ui.label(RichText::new(col).strong().color(Color32::BLACK));
+                        let arrow = match &view.sort {
+                            Some(sort) if sort.column == i && sort.descending => " ⬇",
+                            Some(sort) if sort.column == i => " ⬆",
+                            _ => "",
+                        };
+                        let response = ui.add(Label::new(RichText::new(format!("{}{}", data.columns[i], arrow)).strong().color(Color32::BLACK))
+                            .sense(Sense::click()))
+                            .on_hover_text("Click to sort, right-click for column options");
+                        if response.clicked() {
+                            *action = Some(ColumnAction::Sort(i));
+                        }
+                        response.context_menu(|ui| {
+                            if ui.button("Hide").clicked() {
+                                *action = Some(ColumnAction::Hide(i));
+                                ui.close_menu();
+                            }
+                            let pin = if view.pinned.contains(&i) { "Unpin" } else { "📌 Pin" };
+                            if ui.button(pin).clicked() {
+                                *action = Some(ColumnAction::TogglePin(i));
+                                ui.close_menu();
+                            }
+                            if ui.button("Move left").clicked() {
+                                *action = Some(ColumnAction::Move(i, -1));
+                                ui.close_menu();
+                            }
+                            if ui.button("Move right").clicked() {
+                                *action = Some(ColumnAction::Move(i, 1));
+                                ui.close_menu();
+                            }
+                        });
+                        ui.add(TextEdit::singleline(&mut view.filters[i]).hint_text("Filter").desired_width(80.0))
+                            .on_hover_text(filter_hint);
                         ui.painter().hline(
                             ui.available_rect_before_wrap().x_range(),
                             ui.available_rect_before_wrap().bottom(),
@@ -163,17 +474,50 @@ fn render_table(window: &ResultTable, ui: &mut Ui) {
                 });
             }
         })
-        .body(|mut body| {
-            for data_row in &window.data.rows {
-                body.row(20.0, |mut row| {
-                    for cell in data_row {
-                        row.col(|ui| {
+        .body(|body| {
+            body.rows(20.0, rows.len(), |mut row| {
+                let data_row = &data.rows[rows[row.index()]];
+                for &i in columns {
+                    let cell = &data_row[i];
+                    row.col(|ui| {
+                        if cell.is_null() {
+                            ui.label(RichText::new("NULL").italics().color(Color32::GRAY));
+                        } else if cell.as_f64().is_some() {
+                            ui.with_layout(egui::Layout::right_to_left(Align::Center), |ui| {
+                                ui.label(RichText::new(cell.to_string()));
+                            });
+                        } else {
                             ui.label(RichText::new(cell.to_string()));
-                        });
-                    }
-                })
-
-            }
+                        }
+                    });
+                }
+            });
         });
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::query_result::{ColumnAction, TableView};
+
+    #[test]
+    fn moved_columns_step_over_hidden_ones() {
+        let mut view = TableView::default();
+        view.apply(ColumnAction::Hide(1), 4);
+        view.apply(ColumnAction::Move(0, 1), 4);
+        assert_eq!(view.columns(4), (vec![], vec![2, 0, 3]));
+        assert_eq!(view.order, vec![2, 1, 0, 3]);
+
+        view.apply(ColumnAction::Move(0, -1), 4);
+        assert_eq!(view.columns(4), (vec![], vec![0, 2, 3]));
+
+        // Nothing shown further left, the column stays first
+        view.apply(ColumnAction::Move(0, -1), 4);
+        assert_eq!(view.columns(4), (vec![], vec![0, 2, 3]));
+
+        // A pinned column isn't in the way of the others
+        view.apply(ColumnAction::TogglePin(2), 4);
+        view.apply(ColumnAction::Move(0, 1), 4);
+        assert_eq!(view.columns(4), (vec![2], vec![3, 0]));
+    }
+}
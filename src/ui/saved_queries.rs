@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use crate::app::{AppMode, AppState};
+use crate::db_element::chat_storage::ChatStorage;
+use crate::db_element::saved_query::SavedQuery;
+use crate::ui::left_panel::open_connection;
+use crate::ui::sql_editor::sql_editor;
+use egui::{Color32, Context, RichText, TextEdit, Ui, Window};
+use log::error;
+use uuid::Uuid;
+
+/// Saved queries of every connection, with the dialogs to save and run them.
+pub struct SavedQueries {
+    /// Loaded from the chat store the first time a connection is listed.
+    lists: HashMap<Uuid, Vec<SavedQuery>>,
+    editor: Option<SaveDialog>,
+    params: Option<ParamsDialog>,
+    /// Query waiting for its connection to finish connecting.
+    pending: Option<(Uuid, String)>,
+}
+
+struct SaveDialog {
+    query: SavedQuery,
+    tags: String,
+    error: Option<String>,
+}
+
+struct ParamsDialog {
+    query: SavedQuery,
+    values: Vec<(String, String)>,
+}
+
+pub enum SavedAction {
+    Run(SavedQuery),
+    Edit(SavedQuery),
+    Delete(SavedQuery),
+}
+
+impl SavedQueries {
+    pub fn new() -> Self {
+        Self { lists: HashMap::new(), editor: None, params: None, pending: None }
+    }
+
+    /// Opens the save dialog for a new query.
+    pub fn open_editor(&mut self, connection_id: Uuid, name: String, sql: String) {
+        self.editor = Some(SaveDialog {
+            query: SavedQuery::new(connection_id, name, sql),
+            tags: String::new(),
+            error: None,
+        });
+    }
+
+    pub fn cancel_pending(&mut self) {
+        self.pending = None;
+    }
+
+    /// Collapsible list under a connection in the left panel, hidden while it's empty.
+    pub fn render_list(&mut self, ui: &mut Ui, storage: &ChatStorage, connection_id: &Uuid) -> Option<SavedAction> {
+        let queries = self.lists.entry(*connection_id).or_insert_with(|| {
+            storage.get_saved_queries(connection_id).unwrap_or_else(|err| {
+                error!("Failed to load saved queries: {}", err);
+                Vec::new()
+            })
+        });
+        if queries.is_empty() {
+            return None;
+        }
+
+        let mut action = None;
+        egui::CollapsingHeader::new(format!("⭐ Saved queries ({})", queries.len()))
+            .id_salt(("saved_queries", connection_id))
+            .show(ui, |ui| {
+                for query in queries.iter() {
+                    ui.horizontal(|ui| {
+                        let mut hover = query.sql.clone();
+                        if !query.tags.is_empty() {
+                            hover = format!("Tags: {}\n\n{}", query.tags.join(", "), hover);
+                        }
+                        let response = ui.add(egui::Button::new(&query.name).frame(false))
+                            .on_hover_text(format!("{}\n\nClick to run, right-click for more", hover));
+                        if response.clicked() {
+                            action = Some(SavedAction::Run(query.clone()));
+                        }
+                        response.context_menu(|ui| {
+                            if ui.button("▶ Run").clicked() {
+                                action = Some(SavedAction::Run(query.clone()));
+                                ui.close_menu();
+                            }
+                            if ui.button("✎ Edit").clicked() {
+                                action = Some(SavedAction::Edit(query.clone()));
+                                ui.close_menu();
+                            }
+                            if ui.button("🗑 Delete").clicked() {
+                                action = Some(SavedAction::Delete(query.clone()));
+                                ui.close_menu();
+                            }
+                        });
+                        if !query.tags.is_empty() {
+                            ui.label(RichText::new(query.tags.join(", ")).weak().small());
+                        }
+                    });
+                }
+            });
+        action
+    }
+}
+
+pub fn apply_saved_action(app_state: &mut AppState, action: SavedAction) {
+    match action {
+        SavedAction::Run(query) => {
+            let params = query.params();
+            if params.is_empty() {
+                run_saved_query(app_state, &query.connection_id, &query.sql);
+            } else {
+                app_state.saved_queries.params = Some(ParamsDialog {
+                    query,
+                    values: params.into_iter().map(|name| (name, String::new())).collect(),
+                });
+            }
+        }
+        SavedAction::Edit(query) => {
+            app_state.saved_queries.editor = Some(SaveDialog {
+                tags: query.tags.join(", "),
+                query,
+                error: None,
+            });
+        }
+        SavedAction::Delete(query) => {
+            if let Err(err) = app_state.chat_storage.remove_saved_query(&query.connection_id, &query.id) {
+                error!("Failed to remove saved query: {}", err);
+            }
+            app_state.saved_queries.lists.remove(&query.connection_id);
+        }
+    }
+}
+
+/// Runs into a new result window, opening the connection first when it isn't the active one.
+fn run_saved_query(app_state: &mut AppState, connection_id: &Uuid, sql: &str) {
+    if app_state.conversation.id != Some(*connection_id) {
+        let Some(con) = app_state.config.connections.iter().find(|c| c.uuid == *connection_id).cloned() else {
+            return;
+        };
+        if open_connection(app_state, &con) {
+            app_state.saved_queries.pending = Some((*connection_id, sql.to_string()));
+            app_state.mode = AppMode::Chat;
+        }
+        return;
+    }
+    app_state.run_query(connection_id, sql, &Uuid::new_v4(), 1);
+}
+
+pub fn render_saved_query_dialogs(ctx: &Context, app_state: &mut AppState) {
+    let ready = app_state.saved_queries.pending.as_ref()
+        .is_some_and(|(connection_id, _)| app_state.db_manager.is_connected(connection_id));
+    if ready {
+        if let Some((connection_id, sql)) = app_state.saved_queries.pending.take() {
+            app_state.run_query(&connection_id, &sql, &Uuid::new_v4(), 1);
+        }
+    }
+
+    render_editor(ctx, app_state);
+    render_params(ctx, app_state);
+}
+
+fn render_editor(ctx: &Context, app_state: &mut AppState) {
+    let Some(dialog) = app_state.saved_queries.editor.as_mut() else {
+        return;
+    };
+
+    let mut open = true;
+    let mut save = false;
+    let mut close = false;
+    Window::new("Save query")
+        .open(&mut open)
+        .collapsible(false)
+        .default_width(500.0)
+        .show(ctx, |ui| {
+            egui::Grid::new("save_query_form").num_columns(2).spacing([8.0, 8.0]).show(ui, |ui| {
+                ui.label("Name:");
+                ui.add(TextEdit::singleline(&mut dialog.query.name).desired_width(f32::INFINITY));
+                ui.end_row();
+                ui.label("Tags:");
+                ui.add(TextEdit::singleline(&mut dialog.tags).hint_text("Comma separated").desired_width(f32::INFINITY));
+                ui.end_row();
+            });
+            ui.add_space(4.0);
+            sql_editor(ui, &mut dialog.query.sql);
+            ui.label(RichText::new("Use :name placeholders for values asked for when the query runs.").weak().small());
+            if let Some(error) = &dialog.error {
+                ui.colored_label(Color32::from_rgb(230, 80, 80), error);
+            }
+            ui.horizontal(|ui| {
+                save = ui.button("💾 Save").clicked();
+                close = ui.button("Cancel").clicked();
+            });
+        });
+
+    if save {
+        dialog.query.name = dialog.query.name.trim().to_string();
+        dialog.query.sql = dialog.query.sql.trim().to_string();
+        dialog.query.tags = dialog.tags.split(',')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        if dialog.query.name.is_empty() || dialog.query.sql.is_empty() {
+            dialog.error = Some("A name and a query are required".to_string());
+            return;
+        }
+        match app_state.chat_storage.save_query(&dialog.query) {
+            Ok(()) => {
+                app_state.saved_queries.lists.remove(&dialog.query.connection_id);
+                close = true;
+            }
+            Err(err) => dialog.error = Some(format!("Failed to save the query: {}", err)),
+        }
+    }
+    if close || !open {
+        app_state.saved_queries.editor = None;
+    }
+}
+
+fn render_params(ctx: &Context, app_state: &mut AppState) {
+    let Some(dialog) = app_state.saved_queries.params.as_mut() else {
+        return;
+    };
+
+    let mut open = true;
+    let mut run = false;
+    let mut close = false;
+    Window::new(format!("Run {}", dialog.query.name))
+        .open(&mut open)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            egui::Grid::new("query_params").num_columns(2).spacing([8.0, 8.0]).show(ui, |ui| {
+                for (name, value) in &mut dialog.values {
+                    ui.label(format!(":{}", name));
+                    ui.text_edit_singleline(value);
+                    ui.end_row();
+                }
+            });
+            ui.label(RichText::new("Numbers are used as is, anything else as a quoted string.").weak().small());
+            ui.horizontal(|ui| {
+                run = ui.button("▶ Run").clicked();
+                close = ui.button("Cancel").clicked();
+            });
+        });
+
+    if run {
+        let values: HashMap<String, String> = dialog.values.iter().cloned().collect();
+        let connection_id = dialog.query.connection_id;
+        let Some(db_type) = app_state.config.connections.iter().find(|c| c.uuid == connection_id).map(|c| c.db_type.clone()) else {
+            return;
+        };
+        let sql = dialog.query.bind(&values, &db_type);
+        app_state.saved_queries.params = None;
+        run_saved_query(app_state, &connection_id, &sql);
+        return;
+    }
+    if close || !open {
+        app_state.saved_queries.params = None;
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+use crate::config::DbType;
+use crate::db_element::schema::{DatabaseSchema, Table, TableKind};
+use crate::ui::query_result::{QueryError, ResultTable};
+use egui::collapsing_header::CollapsingState;
+use egui::{Color32, RichText, TextEdit, Ui};
+use tokio::sync::mpsc::Receiver;
+use uuid::Uuid;
+
+/// Tree of the schemas, tables and columns of the open connection, shown in the left panel.
+pub struct SchemaBrowser {
+    pub connection_id: Option<Uuid>,
+    schema: Option<DatabaseSchema>,
+    loading: Option<Receiver<Result<DatabaseSchema, String>>>,
+    error: Option<String>,
+    filter: String,
+    /// Preview queries still running, their errors are shown here instead of in the chat.
+    previews: HashSet<Uuid>,
+}
+
+pub enum BrowserAction {
+    /// Insert a table name into the chat input.
+    Insert(String),
+    /// Run a query that previews the first rows of a table.
+    Preview(String),
+}
+
+impl SchemaBrowser {
+    pub fn new(connection_id: Option<Uuid>) -> Self {
+        Self { connection_id, schema: None, loading: None, error: None, filter: String::new(), previews: HashSet::new() }
+    }
+
+    /// Shows the schema of a connection once the background load finishes.
+    pub fn load(connection_id: Uuid, rx: Receiver<Result<DatabaseSchema, String>>) -> Self {
+        let mut browser = Self::new(Some(connection_id));
+        browser.loading = Some(rx);
+        browser
+    }
+
+    /// Replaces the tree after the schema of the shown connection was introspected again.
+    pub fn set_schema(&mut self, connection_id: &Uuid, schema: DatabaseSchema) {
+        if self.connection_id == Some(*connection_id) {
+            self.schema = Some(schema);
+            self.error = None;
+        }
+    }
+
+    /// Clears the tree when the connection it shows is edited or removed.
+    pub fn forget(&mut self, connection_id: &Uuid) {
+        if self.connection_id == Some(*connection_id) {
+            *self = Self::new(None);
+        }
+    }
+
+    pub fn add_preview(&mut self, id: Uuid) {
+        self.error = None;
+        self.previews.insert(id);
+    }
+
+    /// Whether the finished query was one of the previews.
+    pub fn preview_finished(&mut self, res: &Result<ResultTable, QueryError>) -> bool {
+        let (id, error) = match res {
+            Ok(result) => (result.id, None),
+            Err(error) => (error.id, Some(&error.message)),
+        };
+        if !self.previews.remove(&id) {
+            return false;
+        }
+        if let Some(error) = error {
+            self.error = Some(format!("Preview failed: {}", error));
+        }
+        true
+    }
+
+    pub fn render(&mut self, ui: &mut Ui, db_type: &DbType) -> Option<BrowserAction> {
+        if let Some(res) = self.loading.as_mut().and_then(|rx| rx.try_recv().ok()) {
+            self.loading = None;
+            match res {
+                Ok(schema) => self.schema = Some(schema),
+                Err(err) => self.error = Some(format!("Failed to load the schema: {}", err)),
+            }
+        }
+
+        let mut action = None;
+        ui.indent("schema_browser", |ui| {
+            if self.loading.is_some() {
+                ui.label(RichText::new("⏳ Loading schema...").weak());
+            }
+            if let Some(error) = &self.error {
+                ui.colored_label(Color32::from_rgb(230, 80, 80), error);
+            }
+            let Some(schema) = &self.schema else {
+                return;
+            };
+
+            ui.add(TextEdit::singleline(&mut self.filter).hint_text("Filter tables"));
+            let filter = self.filter.trim().to_lowercase();
+            let single_schema = schema.schemas.len() == 1;
+            for schema_info in &schema.schemas {
+                let tables: Vec<&Table> = schema_info.tables.iter()
+                    .filter(|table| filter.is_empty() || table.name.to_lowercase().contains(&filter))
+                    .collect();
+                if tables.is_empty() {
+                    continue;
+                }
+                egui::CollapsingHeader::new(format!("🗄 {}", schema_info.name))
+                    .id_salt(("schema", &schema_info.name))
+                    .default_open(single_schema)
+                    .show(ui, |ui| {
+                        for table in tables {
+                            if let Some(clicked) = table_node(ui, schema, table, db_type) {
+                                action = Some(clicked);
+                            }
+                        }
+                    });
+            }
+        });
+        action
+    }
+}
+
+fn table_node(ui: &mut Ui, schema: &DatabaseSchema, table: &Table, db_type: &DbType) -> Option<BrowserAction> {
+    let mut action = None;
+    let name = schema.table_name(&table.schema, &table.name);
+    let icon = match table.kind {
+        TableKind::Table => "▦",
+        TableKind::View | TableKind::MaterializedView => "👁",
+    };
+    let id = ui.make_persistent_id(("table", &table.schema, &table.name));
+    CollapsingState::load_with_default_open(ui.ctx(), id, false)
+        .show_header(ui, |ui| {
+            let mut hover = "Click to insert into the chat, right-click for more".to_string();
+            if let Some(comment) = &table.comment {
+                hover = format!("{}\n\n{}", comment, hover);
+            }
+            let response = ui.add(egui::Button::new(format!("{} {}", icon, table.name)).frame(false))
+                .on_hover_text(hover);
+            if response.clicked() {
+                action = Some(BrowserAction::Insert(name.clone()));
+            }
+            response.context_menu(|ui| {
+                if ui.button("Insert name").clicked() {
+                    action = Some(BrowserAction::Insert(name.clone()));
+                    ui.close_menu();
+                }
+                if ui.button("Preview 100 rows").clicked() {
+                    action = Some(BrowserAction::Preview(preview_query(db_type, schema, table)));
+                    ui.close_menu();
+                }
+            });
+        })
+        .body(|ui| {
+            for column in &table.columns {
+                let foreign_key = table.foreign_keys.iter().find(|fk| fk.columns.contains(&column.name));
+                let marker = if table.primary_key.contains(&column.name) {
+                    "🔑"
+                } else if foreign_key.is_some() {
+                    "🔗"
+                } else {
+                    "  "
+                };
+
+                let mut details = vec![if column.nullable { "NULL".to_string() } else { "NOT NULL".to_string() }];
+                if let Some(default) = &column.default {
+                    details.push(format!("DEFAULT {}", default));
+                }
+                if let Some(fk) = foreign_key {
+                    let position = fk.columns.iter().position(|c| *c == column.name).unwrap_or(0);
+                    let referenced = schema.table_name(&fk.referenced_schema, &fk.referenced_table);
+                    let referenced_column = fk.referenced_columns.get(position).map(String::as_str).unwrap_or("?");
+                    details.push(format!("-> {}.{}", referenced, referenced_column));
+                }
+                if let Some(comment) = &column.comment {
+                    details.push(comment.clone());
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label(marker);
+                    ui.label(&column.name);
+                    ui.label(RichText::new(&column.data_type).weak().small());
+                }).response.on_hover_text(details.join("\n"));
+            }
+        });
+    action
+}
+
+/// `SELECT` of the first 100 rows, which is exactly one result page.
+fn preview_query(db_type: &DbType, schema: &DatabaseSchema, table: &Table) -> String {
+    let name = if schema.qualify_names {
+        format!("{}.{}", db_type.quote_identifier(&table.schema), db_type.quote_identifier(&table.name))
+    } else {
+        db_type.quote_identifier(&table.name)
+    };
+    match db_type {
+        DbType::SQLServer => format!("SELECT TOP 100 * FROM {}", name),
+        _ => format!("SELECT * FROM {} LIMIT 100", name),
+    }
+}
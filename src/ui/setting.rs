@@ -1,15 +1,18 @@
 use crate::app::AppState;
-use crate::llm::claude::Model as ClaudeModel;
-use crate::llm::llm::Provider;
-use crate::llm::openai::Model as OpenAiModel;
+use crate::llm::llm::{find_provider, providers, stored_api_key};
 use egui::{Context, TextEdit};
 use log::info;
 
 #[derive(Clone)]
 pub struct Settings {
-    pub provider: Option<Provider>,
+    pub provider: Option<String>,
     pub model: String,
+    pub base_url: String,
     pub api_key: String,
+    pub history_turns: usize,
+    pub history_tokens: usize,
+    pub max_correction_attempts: usize,
+    pub max_schema_tables: usize,
     pub success_message: Option<String>,
     pub error_message: Option<String>,
 }
@@ -23,52 +26,78 @@ pub fn render_settings(ctx: &Context, app_state: &mut AppState) {
 
             let selected_provider = &mut app_state.settings.provider;
 
-            let model_names = if let Some(provider) = selected_provider {
-                match provider {
-                    Provider::OpenAI => OpenAiModel::variants_name(),
-                    Provider::Claude => ClaudeModel::variants_name(),
-                }
-            } else {
-                vec![]
-            };
-
-            let provider = vec![Provider::Claude, Provider::OpenAI];
+            let provider = selected_provider.as_deref().and_then(find_provider);
+            let model_names = provider.map(|provider| provider.models()).unwrap_or_default();
 
             ui.horizontal(|ui| {
                 ui.label("Provider:");
                 egui::ComboBox::new("provider", "")
-                    .selected_text(selected_provider.as_ref().map(|p: &Provider| p.name()).unwrap_or("Choose..."))
+                    .selected_text(provider.map(|p| p.name()).unwrap_or("Choose..."))
                     .show_ui(ui, |ui| {
-                        for p in provider {
-                            if ui.selectable_value(selected_provider, Some(p.clone()), p.name()).clicked() {
-                                if *selected_provider == Some(p.clone()) {
-                                    app_state.settings.model = "".to_owned();
-                                }
+                        for p in providers() {
+                            if ui.selectable_value(selected_provider, Some(p.id().to_string()), p.name()).clicked() {
+                                app_state.settings.model = "".to_owned();
+                                app_state.settings.base_url = "".to_owned();
+                                // Keys are per provider, never show or save one under another provider
+                                app_state.settings.api_key = stored_api_key(p).unwrap_or_default();
                             }
                         }
                     });
             });
 
+            if let Some(default_base_url) = provider.and_then(|p| p.default_base_url()) {
+                ui.horizontal(|ui| {
+                    ui.label("Base URL:");
+                    ui.add(TextEdit::singleline(&mut app_state.settings.base_url).hint_text(default_base_url));
+                });
+            }
+
             let model = &mut app_state.settings.model;
             ui.add_enabled_ui(selected_provider.is_some(), |ui| {
                 ui.horizontal(|ui| {
                     ui.label("Model:");
-                    egui::ComboBox::new("model", "")
-                        .selected_text(model.clone())
-                        .show_ui(ui, |ui| {
-                            for item in model_names {
-                                ui.selectable_value(model, item.parse().unwrap(), item);
-                            }
-                        })
+                    if selected_provider.is_some() && model_names.is_empty() {
+                        ui.add(TextEdit::singleline(model).hint_text("e.g. llama3.1"));
+                    } else {
+                        egui::ComboBox::new("model", "")
+                            .selected_text(model.clone())
+                            .show_ui(ui, |ui| {
+                                for item in model_names {
+                                    ui.selectable_value(model, item.parse().unwrap(), item);
+                                }
+                            });
+                    }
                 });
             });
 
 
             ui.horizontal(|ui| {
-                ui.label("API Key:");
+                ui.label(if provider.is_some_and(|p| !p.requires_api_key()) { "API Key (optional):" } else { "API Key:" });
                 ui.add(TextEdit::singleline(&mut app_state.settings.api_key).password(true));
             });
 
+            ui.horizontal(|ui| {
+                ui.label("Conversation history:");
+                ui.add(egui::DragValue::new(&mut app_state.settings.history_turns).range(0..=50).suffix(" turns"));
+                ui.add(egui::DragValue::new(&mut app_state.settings.history_tokens).range(0..=100_000).speed(100).suffix(" tokens"));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Auto-correct failed queries:");
+                ui.add(egui::DragValue::new(&mut app_state.settings.max_correction_attempts).range(0..=10).suffix(" attempts"));
+                if app_state.settings.max_correction_attempts == 0 {
+                    ui.label("(disabled)");
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Tables sent per question:");
+                ui.add(egui::DragValue::new(&mut app_state.settings.max_schema_tables).range(0..=1000).suffix(" tables"));
+                if app_state.settings.max_schema_tables == 0 {
+                    ui.label("(whole schema)");
+                }
+            });
+
             if ui.button("Save API Settings").clicked() {
                 if let Err(err) = app_state.save_settings() {
                     app_state.settings.success_message = None;
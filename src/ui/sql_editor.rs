@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId, Galley, TextEdit, TextStyle, Ui};
+
+const KEYWORDS: &[&str] = &[
+    "ALL", "AND", "AS", "ASC", "BETWEEN", "BY", "CASE", "CAST", "CROSS", "DESC", "DISTINCT", "ELSE",
+    "END", "EXCEPT", "EXISTS", "FALSE", "FETCH", "FIRST", "FROM", "FULL", "GROUP", "HAVING", "ILIKE",
+    "IN", "INNER", "INTERSECT", "IS", "JOIN", "LATERAL", "LEFT", "LIKE", "LIMIT", "NEXT", "NOT", "NULL",
+    "NULLS", "OFFSET", "ON", "ONLY", "OR", "ORDER", "OUTER", "OVER", "PARTITION", "RECURSIVE", "RIGHT",
+    "ROWS", "SELECT", "THEN", "TOP", "TRUE", "UNION", "USING", "VALUES", "WHEN", "WHERE", "WINDOW", "WITH",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    Keyword,
+    String,
+    Number,
+    Comment,
+    Other,
+}
+
+/// Multiline SQL editor with syntax highlighting, on a light background like the chat bubbles.
+pub fn sql_editor(ui: &mut Ui, sql: &mut String) -> egui::Response {
+    let mut layouter = |ui: &Ui, text: &str, wrap_width: f32| -> Arc<Galley> {
+        let mut job = highlight(text, TextStyle::Monospace.resolve(ui.style()));
+        job.wrap.max_width = wrap_width;
+        ui.fonts(|fonts| fonts.layout_job(job))
+    };
+    ui.add(
+        TextEdit::multiline(sql)
+            .code_editor()
+            .background_color(Color32::WHITE)
+            .desired_rows(4)
+            .desired_width(ui.available_width())
+            .layouter(&mut layouter),
+    )
+}
+
+/// Read-only highlighted SQL.
+pub fn sql_label(ui: &mut Ui, sql: &str) -> egui::Response {
+    let job = highlight(sql, TextStyle::Monospace.resolve(ui.style()));
+    ui.label(job)
+}
+
+fn highlight(sql: &str, font_id: FontId) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    for (kind, text) in tokens(sql) {
+        let color = match kind {
+            TokenKind::Keyword => Color32::from_rgb(0, 70, 170),
+            TokenKind::String => Color32::from_rgb(20, 120, 40),
+            TokenKind::Number => Color32::from_rgb(170, 80, 0),
+            TokenKind::Comment => Color32::from_rgb(120, 120, 120),
+            TokenKind::Other => Color32::BLACK,
+        };
+        job.append(text, 0.0, TextFormat::simple(font_id.clone(), color));
+    }
+    job
+}
+
+/// Splits SQL into highlighted pieces, together they're exactly the input so the cursor stays put.
+fn tokens(sql: &str) -> Vec<(TokenKind, &str)> {
+    let mut tokens = Vec::new();
+    let mut rest = sql;
+    while let Some(c) = rest.chars().next() {
+        let (kind, len) = if rest.starts_with("--") {
+            (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            (TokenKind::Comment, comment.find("*/").map(|end| end + 4).unwrap_or(rest.len()))
+        } else if c == '\'' {
+            (TokenKind::String, quoted_len(rest, '\''))
+        } else if c == '"' || c == '`' {
+            (TokenKind::Other, quoted_len(rest, c))
+        } else if c.is_ascii_digit() {
+            (TokenKind::Number, rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '.').unwrap_or(rest.len()))
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest.find(|c: char| !c.is_alphanumeric() && c != '_' && c != '$').unwrap_or(rest.len());
+            let word = rest[..len].to_uppercase();
+            let kind = if KEYWORDS.contains(&word.as_str()) { TokenKind::Keyword } else { TokenKind::Other };
+            (kind, len)
+        } else {
+            (TokenKind::Other, c.len_utf8())
+        };
+        tokens.push((kind, &rest[..len]));
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+/// Length of a quoted token, a doubled quote doesn't end it. Unterminated runs to the end.
+fn quoted_len(text: &str, quote: char) -> usize {
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == quote {
+            if chars.peek().is_some_and(|(_, next)| *next == quote) {
+                chars.next();
+            } else {
+                return i + c.len_utf8();
+            }
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let sql = "select name, 'it''s' -- note\nFROM \"my table\" WHERE id > 10.5 /* x */";
+        let tokens = tokens(sql);
+        assert_eq!(tokens.iter().map(|(_, text)| *text).collect::<String>(), sql);
+
+        let kind = |text: &str| tokens.iter().find(|(_, t)| *t == text).map(|(kind, _)| *kind);
+        assert_eq!(kind("select"), Some(TokenKind::Keyword));
+        assert_eq!(kind("FROM"), Some(TokenKind::Keyword));
+        assert_eq!(kind("name"), Some(TokenKind::Other));
+        assert_eq!(kind("'it''s'"), Some(TokenKind::String));
+        assert_eq!(kind("-- note"), Some(TokenKind::Comment));
+        assert_eq!(kind("\"my table\""), Some(TokenKind::Other));
+        assert_eq!(kind("10.5"), Some(TokenKind::Number));
+        assert_eq!(kind("/* x */"), Some(TokenKind::Comment));
+    }
+}
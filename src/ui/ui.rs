@@ -3,9 +3,12 @@ use egui::Context;
 use crate::app::{AppMode, AppState};
 use crate::ui::chat::render_chat;
 use crate::ui::connection::connection_ui;
+use crate::ui::console::render_console;
+use crate::ui::history::render_history;
 use crate::ui::home::render_home;
 use crate::ui::left_panel::left_panel_ui;
 use crate::ui::query_result::render_result;
+use crate::ui::saved_queries::render_saved_query_dialogs;
 use crate::ui::setting::render_settings;
 
 pub fn render_ui(ctx: &Context, app_state: &mut AppState) {
@@ -15,9 +18,12 @@ pub fn render_ui(ctx: &Context, app_state: &mut AppState) {
         AppMode::Home => render_home(),
         AppMode::Settings => render_settings(ctx, app_state),
         AppMode::Connections => connection_ui(ctx, app_state),
-        AppMode::Chat => render_chat(ctx, app_state)
+        AppMode::Chat => render_chat(ctx, app_state),
+        AppMode::Console => render_console(ctx, app_state),
+        AppMode::History => render_history(ctx, app_state),
     }
 
     render_result(ctx, app_state);
+    render_saved_query_dialogs(ctx, app_state);
 
 }
\ No newline at end of file
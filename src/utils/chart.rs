@@ -0,0 +1,652 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use chrono::{DateTime, NaiveTime};
+use egui::{pos2, vec2, Align2, Color32, Pos2, Rect, Vec2};
+use crate::db_element::chat::VisualizationSpec;
+use crate::db_element::db::QueryResult;
+use crate::db_element::value::CellValue;
+
+const PALETTE: [Color32; 10] = [
+    Color32::from_rgb(78, 121, 167),
+    Color32::from_rgb(242, 142, 43),
+    Color32::from_rgb(225, 87, 89),
+    Color32::from_rgb(118, 183, 178),
+    Color32::from_rgb(89, 161, 79),
+    Color32::from_rgb(237, 201, 72),
+    Color32::from_rgb(176, 122, 161),
+    Color32::from_rgb(255, 157, 167),
+    Color32::from_rgb(156, 117, 95),
+    Color32::from_rgb(186, 176, 172),
+];
+const AXIS: Color32 = Color32::from_rgb(90, 90, 90);
+const GRID: Color32 = Color32::from_rgb(225, 225, 225);
+pub const FONT_SIZE: f32 = 12.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartKind {
+    Line,
+    Bar,
+    Scatter,
+    Histogram,
+    Pie,
+}
+
+impl ChartKind {
+    pub const ALL: [ChartKind; 5] = [ChartKind::Line, ChartKind::Bar, ChartKind::Scatter, ChartKind::Histogram, ChartKind::Pie];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChartKind::Line => "Line",
+            ChartKind::Bar => "Bar",
+            ChartKind::Scatter => "Scatter",
+            ChartKind::Histogram => "Histogram",
+            ChartKind::Pie => "Pie",
+        }
+    }
+}
+
+/// What to draw, the columns by their position in the `QueryResult`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartSpec {
+    pub kind: ChartKind,
+    pub x: Option<usize>,
+    /// The values, binned for a histogram.
+    pub y: Option<usize>,
+    /// Splits the rows into one series per distinct value.
+    pub series: Option<usize>,
+    pub title: Option<String>,
+}
+
+impl ChartSpec {
+    /// The chart an LLM suggested, when its kind is known, its columns are in the result and
+    /// the chart can be built from the rows.
+    pub fn from_visualization(visualization: &VisualizationSpec, data: &QueryResult) -> Result<ChartSpec, String> {
+        let kind = ChartKind::ALL.into_iter()
+            .find(|kind| kind.label().eq_ignore_ascii_case(visualization.chart.trim()))
+            .ok_or_else(|| format!("Unknown chart type {}", visualization.chart))?;
+        let column = |name: &Option<String>| -> Result<Option<usize>, String> {
+            let Some(name) = name.as_deref().map(str::trim).filter(|name| !name.is_empty()) else {
+                return Ok(None);
+            };
+            data.columns.iter().position(|c| c == name)
+                .or_else(|| data.columns.iter().position(|c| c.eq_ignore_ascii_case(name)))
+                .map(Some)
+                .ok_or_else(|| format!("The result has no column {}", name))
+        };
+        let spec = ChartSpec {
+            kind,
+            x: column(&visualization.x)?,
+            y: column(&visualization.y)?,
+            series: column(&visualization.series)?,
+            title: visualization.title.clone().filter(|title| !title.trim().is_empty()),
+        };
+        Chart::build(data, &spec)?;
+        Ok(spec)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnKind {
+    Number,
+    Time,
+    Text,
+}
+
+/// Type of a column from its first non-NULL cell.
+fn column_kind(data: &QueryResult, column: usize) -> Option<ColumnKind> {
+    data.rows.iter().map(|row| &row[column]).find(|cell| !cell.is_null()).map(|cell| match cell {
+        CellValue::Integer(_) | CellValue::Float(_) | CellValue::Decimal(_) => ColumnKind::Number,
+        CellValue::Date(_) | CellValue::Timestamp(_) | CellValue::TimestampTz(_) => ColumnKind::Time,
+        _ => ColumnKind::Text,
+    })
+}
+
+/// A chart fitting the column types: values over time as a line, per category as bars,
+/// two measures as a scatter and a single one as a histogram.
+pub fn suggest(data: &QueryResult) -> Option<ChartSpec> {
+    let columns_of = |kind| (0..data.columns.len()).filter(|c| column_kind(data, *c) == Some(kind)).collect::<Vec<_>>();
+    let (numbers, times, texts) = (columns_of(ColumnKind::Number), columns_of(ColumnKind::Time), columns_of(ColumnKind::Text));
+    let spec = |kind, x: Option<&usize>, y: Option<&usize>, series: Option<&usize>| ChartSpec {
+        kind, x: x.copied(), y: y.copied(), series: series.copied(), title: None,
+    };
+    if !times.is_empty() && !numbers.is_empty() {
+        Some(spec(ChartKind::Line, times.first(), numbers.first(), texts.first()))
+    } else if !texts.is_empty() && !numbers.is_empty() {
+        Some(spec(ChartKind::Bar, texts.first(), numbers.first(), texts.get(1)))
+    } else if numbers.len() >= 2 {
+        Some(spec(ChartKind::Scatter, numbers.first(), numbers.get(1), None))
+    } else if numbers.len() == 1 {
+        Some(spec(ChartKind::Histogram, None, numbers.first(), None))
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Axis {
+    Number,
+    /// Seconds since the epoch.
+    Time,
+    /// Points sit at the index of their category.
+    Category(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Series {
+    name: String,
+    points: Vec<[f64; 2]>,
+}
+
+/// The values of a spec taken out of the rows, ready to be laid out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    kind: ChartKind,
+    x_axis: Axis,
+    title: Option<String>,
+    x_label: String,
+    y_label: String,
+    series: Vec<Series>,
+    /// Width of the bins of a histogram.
+    bin_width: f64,
+}
+
+fn time_value(cell: &CellValue) -> Option<f64> {
+    match cell {
+        CellValue::Date(v) => Some(v.and_time(NaiveTime::MIN).and_utc().timestamp() as f64),
+        CellValue::Timestamp(v) => Some(v.and_utc().timestamp_millis() as f64 / 1000.0),
+        CellValue::TimestampTz(v) => Some(v.timestamp_millis() as f64 / 1000.0),
+        _ => None,
+    }
+}
+
+/// Index of a category, added on first sight so categories keep the order of the rows.
+fn category(categories: &mut Vec<String>, name: String) -> usize {
+    categories.iter().position(|c| *c == name).unwrap_or_else(|| {
+        categories.push(name);
+        categories.len() - 1
+    })
+}
+
+impl Chart {
+    pub fn build(data: &QueryResult, spec: &ChartSpec) -> Result<Chart, String> {
+        let name = |column: Option<usize>| column.map(|c| data.columns[c].clone()).unwrap_or_default();
+        let y = spec.y.filter(|y| *y < data.columns.len()).ok_or("Pick a column for the values")?;
+        if column_kind(data, y).is_some_and(|kind| kind != ColumnKind::Number) {
+            return Err(format!("Column {} isn't numeric", data.columns[y]));
+        }
+        let mut chart = Chart {
+            kind: spec.kind,
+            x_axis: Axis::Number,
+            title: spec.title.clone(),
+            x_label: name(spec.x),
+            y_label: name(Some(y)),
+            series: Vec::new(),
+            bin_width: 0.0,
+        };
+
+        if spec.kind == ChartKind::Histogram {
+            let values: Vec<f64> = data.rows.iter().filter_map(|row| row[y].as_f64()).filter(|v| v.is_finite()).collect();
+            if values.is_empty() {
+                return Err("No rows with values to draw".to_string());
+            }
+            let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let bins = ((values.len() as f64).sqrt().ceil() as usize).clamp(1, 50);
+            chart.bin_width = if max > min { (max - min) / bins as f64 } else { 1.0 };
+            let mut counts = vec![0.0; bins];
+            for value in &values {
+                counts[(((value - min) / chart.bin_width) as usize).min(bins - 1)] += 1.0;
+            }
+            let points = counts.into_iter().enumerate().map(|(i, count)| [min + i as f64 * chart.bin_width, count]).collect();
+            chart.x_label = chart.y_label.clone();
+            chart.y_label = "Count".to_string();
+            chart.series.push(Series { name: chart.x_label.clone(), points });
+            return Ok(chart);
+        }
+
+        let x = spec.x.filter(|x| *x < data.columns.len()).ok_or("Pick a column for the X axis")?;
+        let x_kind = column_kind(data, x);
+        let mut categories = Vec::new();
+        if matches!(spec.kind, ChartKind::Bar | ChartKind::Pie) || x_kind == Some(ColumnKind::Text) {
+            chart.x_axis = Axis::Category(Vec::new());
+        } else if x_kind == Some(ColumnKind::Time) {
+            chart.x_axis = Axis::Time;
+        }
+        // A pie has a single series
+        let series_column = spec.series.filter(|s| *s < data.columns.len() && spec.kind != ChartKind::Pie);
+
+        let mut series: Vec<Series> = Vec::new();
+        let mut sums: HashMap<(usize, usize), usize> = HashMap::new();
+        for row in &data.rows {
+            let Some(value) = row[y].as_f64() else {
+                continue;
+            };
+            let position = match &chart.x_axis {
+                Axis::Category(_) if row[x].is_null() => continue,
+                Axis::Category(_) => category(&mut categories, row[x].to_string()) as f64,
+                Axis::Time => match time_value(&row[x]) {
+                    Some(v) => v,
+                    None => continue,
+                },
+                Axis::Number => match row[x].as_f64() {
+                    Some(v) => v,
+                    None => continue,
+                },
+            };
+            let series_name = series_column.map(|s| row[s].to_string()).unwrap_or_else(|| chart.y_label.clone());
+            let index = series.iter().position(|s| s.name == series_name).unwrap_or_else(|| {
+                series.push(Series { name: series_name, points: Vec::new() });
+                series.len() - 1
+            });
+            // Repeated categories of bars and pies add up
+            if matches!(chart.x_axis, Axis::Category(_)) && spec.kind != ChartKind::Scatter {
+                if let Some(point) = sums.get(&(index, position as usize)) {
+                    series[index].points[*point][1] += value;
+                    continue;
+                }
+                sums.insert((index, position as usize), series[index].points.len());
+            }
+            series[index].points.push([position, value]);
+        }
+        if spec.kind == ChartKind::Line {
+            for s in &mut series {
+                s.points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+            }
+        }
+        if let Axis::Category(names) = &mut chart.x_axis {
+            *names = categories;
+        }
+        if series.is_empty() {
+            return Err("No rows with values to draw".to_string());
+        }
+        chart.series = series;
+        Ok(chart)
+    }
+
+    /// Shapes of the chart in a `size` area with its origin at the top left.
+    pub fn layout(&self, size: Vec2) -> Vec<Mark> {
+        let mut marks = vec![Mark::Rect { rect: Rect::from_min_size(Pos2::ZERO, size), color: Color32::WHITE }];
+        let legend: Vec<(String, Color32)> = if self.kind == ChartKind::Pie {
+            let names = match &self.x_axis {
+                Axis::Category(names) => names.clone(),
+                _ => Vec::new(),
+            };
+            let total: f64 = self.series[0].points.iter().map(|p| p[1].max(0.0)).sum();
+            self.series[0].points.iter().enumerate().map(|(i, p)| {
+                let share = if total > 0.0 { p[1].max(0.0) / total * 100.0 } else { 0.0 };
+                (format!("{} ({:.1}%)", names.get(p[0] as usize).map(String::as_str).unwrap_or(""), share), color(i))
+            }).collect()
+        } else if self.series.len() > 1 {
+            self.series.iter().enumerate().map(|(i, s)| (s.name.clone(), color(i))).collect()
+        } else {
+            Vec::new()
+        };
+        let legend_width = if legend.is_empty() { 0.0 } else { 180.0 };
+        let top = if self.title.is_some() { 44.0 } else { 20.0 };
+        if let Some(title) = &self.title {
+            marks.push(Mark::Text { pos: pos2(size.x / 2.0, 8.0), text: title.clone(), align: Align2::CENTER_TOP, color: Color32::BLACK });
+        }
+        for (i, (name, color)) in legend.iter().enumerate() {
+            let top = top + i as f32 * 18.0;
+            if top > size.y - 20.0 {
+                break;
+            }
+            let left = size.x - legend_width + 10.0;
+            marks.push(Mark::Rect { rect: Rect::from_min_size(pos2(left, top), vec2(10.0, 10.0)), color: *color });
+            marks.push(Mark::Text { pos: pos2(left + 16.0, top + 5.0), text: shorten(name, 24), align: Align2::LEFT_CENTER, color: AXIS });
+        }
+
+        let plot = Rect::from_min_max(pos2(70.0, top), pos2((size.x - legend_width - 20.0).max(80.0), (size.y - 50.0).max(30.0)));
+        if self.kind == ChartKind::Pie {
+            self.layout_pie(plot, &mut marks);
+        } else {
+            self.layout_xy(plot, &mut marks);
+        }
+        marks
+    }
+
+    fn layout_pie(&self, plot: Rect, marks: &mut Vec<Mark>) {
+        let total: f64 = self.series[0].points.iter().map(|p| p[1].max(0.0)).sum();
+        if total <= 0.0 {
+            return;
+        }
+        let center = plot.center();
+        let radius = plot.width().min(plot.height()) / 2.0;
+        let mut start = -std::f32::consts::FRAC_PI_2;
+        for (i, point) in self.series[0].points.iter().enumerate() {
+            let sweep = (point[1].max(0.0) / total) as f32 * std::f32::consts::TAU;
+            // A fan of triangles from the center, a convex polygon at most a quarter turn each
+            let steps = ((sweep / 0.05).ceil() as usize).max(1);
+            let mut done = 0;
+            while done < steps {
+                let chunk = (steps - done).min(30);
+                let mut points = vec![center];
+                for step in done..=done + chunk {
+                    let angle = start + sweep * step as f32 / steps as f32;
+                    points.push(center + radius * vec2(angle.cos(), angle.sin()));
+                }
+                marks.push(Mark::Polygon { points, color: color(i) });
+                done += chunk;
+            }
+            start += sweep;
+        }
+    }
+
+    fn layout_xy(&self, plot: Rect, marks: &mut Vec<Mark>) {
+        let points = self.series.iter().flat_map(|s| s.points.iter());
+        let (mut x_min, mut x_max, mut y_min, mut y_max) = (f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY, f64::NEG_INFINITY);
+        for p in points {
+            x_min = x_min.min(p[0]);
+            x_max = x_max.max(p[0]);
+            y_min = y_min.min(p[1]);
+            y_max = y_max.max(p[1]);
+        }
+        match (&self.x_axis, self.kind) {
+            (Axis::Category(names), _) => {
+                x_min = -0.5;
+                x_max = names.len() as f64 - 0.5;
+            }
+            (_, ChartKind::Histogram) => x_max += self.bin_width,
+            // Room so the first and last points aren't on the border
+            _ => {
+                let padding = ((x_max - x_min) * 0.05).max(f64::EPSILON);
+                x_min -= padding;
+                x_max += padding;
+            }
+        }
+        // Bars grow from zero
+        if matches!(self.kind, ChartKind::Bar | ChartKind::Histogram) {
+            y_min = y_min.min(0.0);
+            y_max = y_max.max(0.0);
+        }
+        if x_max <= x_min {
+            x_min -= 1.0;
+            x_max += 1.0;
+        }
+        let y_ticks = nice_ticks(y_min, y_max);
+        y_min = y_min.min(y_ticks[0]);
+        y_max = y_max.max(*y_ticks.last().unwrap());
+        if y_max <= y_min {
+            y_max = y_min + 1.0;
+        }
+
+        let to_x = |v: f64| plot.left() + ((v - x_min) / (x_max - x_min)) as f32 * plot.width();
+        let to_y = |v: f64| plot.bottom() - ((v - y_min) / (y_max - y_min)) as f32 * plot.height();
+
+        for tick in &y_ticks {
+            let y = to_y(*tick);
+            marks.push(Mark::Line { points: vec![pos2(plot.left(), y), pos2(plot.right(), y)], color: GRID, width: 1.0 });
+            marks.push(Mark::Text { pos: pos2(plot.left() - 6.0, y), text: format_number(*tick), align: Align2::RIGHT_CENTER, color: AXIS });
+        }
+        let x_ticks: Vec<(f64, String)> = match &self.x_axis {
+            Axis::Category(names) => {
+                // Every label when they fit, otherwise every n-th
+                let step = ((names.len() as f32 * 70.0 / plot.width()).ceil() as usize).max(1);
+                names.iter().enumerate().step_by(step).map(|(i, name)| (i as f64, shorten(name, 12))).collect()
+            }
+            Axis::Time => {
+                // Whole days unless the range is only a few of them
+                let with_time = x_max - x_min < 3.0 * 86400.0;
+                let ticks = if with_time {
+                    nice_ticks(x_min, x_max)
+                } else {
+                    nice_ticks(x_min / 86400.0, x_max / 86400.0).into_iter().map(|day| day.round() * 86400.0).collect()
+                };
+                ticks.into_iter().filter(|t| *t >= x_min && *t <= x_max).map(|t| (t, format_time(t, with_time))).collect()
+            }
+            Axis::Number => nice_ticks(x_min, x_max).into_iter()
+                .filter(|t| *t >= x_min && *t <= x_max)
+                .map(|t| (t, format_number(t)))
+                .collect(),
+        };
+        for (tick, label) in x_ticks {
+            let x = to_x(tick);
+            marks.push(Mark::Line { points: vec![pos2(x, plot.bottom()), pos2(x, plot.bottom() + 4.0)], color: AXIS, width: 1.0 });
+            marks.push(Mark::Text { pos: pos2(x, plot.bottom() + 6.0), text: label, align: Align2::CENTER_TOP, color: AXIS });
+        }
+
+        let zero = to_y(0.0_f64.clamp(y_min, y_max));
+        match self.kind {
+            ChartKind::Bar => {
+                let group = plot.width() / (x_max - x_min) as f32 * 0.8;
+                let width = group / self.series.len() as f32;
+                for (i, series) in self.series.iter().enumerate() {
+                    for p in &series.points {
+                        let left = to_x(p[0]) - group / 2.0 + i as f32 * width;
+                        let y = to_y(p[1]);
+                        let rect = Rect::from_x_y_ranges(left..=left + width * 0.95, y.min(zero)..=y.max(zero));
+                        marks.push(Mark::Rect { rect, color: color(i) });
+                    }
+                }
+            }
+            ChartKind::Histogram => {
+                for p in &self.series[0].points {
+                    let (left, right) = (to_x(p[0]), to_x(p[0] + self.bin_width));
+                    let rect = Rect::from_x_y_ranges(left..=(right - 1.0).max(left), to_y(p[1])..=zero);
+                    marks.push(Mark::Rect { rect, color: color(0) });
+                }
+            }
+            ChartKind::Line | ChartKind::Scatter => {
+                for (i, series) in self.series.iter().enumerate() {
+                    let points: Vec<Pos2> = series.points.iter().map(|p| pos2(to_x(p[0]), to_y(p[1]))).collect();
+                    if self.kind == ChartKind::Line && points.len() > 1 {
+                        marks.push(Mark::Line { points: points.clone(), color: color(i), width: 2.0 });
+                    }
+                    let radius = if self.kind == ChartKind::Scatter { 3.5 } else { 2.5 };
+                    for center in points {
+                        marks.push(Mark::Circle { center, radius, color: color(i) });
+                    }
+                }
+            }
+            ChartKind::Pie => {}
+        }
+
+        marks.push(Mark::Line { points: vec![plot.left_bottom(), plot.right_bottom()], color: AXIS, width: 1.0 });
+        marks.push(Mark::Line { points: vec![plot.left_top(), plot.left_bottom()], color: AXIS, width: 1.0 });
+        marks.push(Mark::Text { pos: pos2(plot.center().x, plot.bottom() + 26.0), text: self.x_label.clone(), align: Align2::CENTER_TOP, color: AXIS });
+        marks.push(Mark::Text { pos: pos2(plot.left(), plot.top() - 16.0), text: self.y_label.clone(), align: Align2::LEFT_TOP, color: AXIS });
+    }
+}
+
+/// A shape of a laid out chart, painted by egui or written to SVG.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mark {
+    Line { points: Vec<Pos2>, color: Color32, width: f32 },
+    Rect { rect: Rect, color: Color32 },
+    Circle { center: Pos2, radius: f32, color: Color32 },
+    /// Convex, filled.
+    Polygon { points: Vec<Pos2>, color: Color32 },
+    Text { pos: Pos2, text: String, align: Align2, color: Color32 },
+}
+
+fn color(i: usize) -> Color32 {
+    PALETTE[i % PALETTE.len()]
+}
+
+fn shorten(text: &str, max: usize) -> String {
+    if text.chars().count() > max {
+        format!("{}…", text.chars().take(max - 1).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+/// Bounds the ticks of an axis whatever the range.
+const MAX_TICKS: usize = 20;
+
+/// About five round steps covering the range.
+fn nice_ticks(min: f64, max: f64) -> Vec<f64> {
+    if !min.is_finite() || !max.is_finite() || max <= min {
+        return vec![min.min(0.0), min.max(0.0) + 1.0];
+    }
+    let rough = (max - min) / 5.0;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0].iter().map(|m| m * magnitude).find(|step| *step >= rough).unwrap_or(10.0 * magnitude);
+    let first = (min / step).floor() * step;
+    let count = (((max - first) / step + 0.999).ceil() as usize).min(MAX_TICKS);
+    let mut ticks: Vec<f64> = (0..count).map(|i| first + i as f64 * step).collect();
+    // Large values that are close together can be less than a step apart in f64
+    ticks.dedup();
+    if ticks.len() < 2 || ticks[ticks.len() - 1] < max - step * 0.001 {
+        return vec![min, max];
+    }
+    ticks
+}
+
+fn format_number(value: f64) -> String {
+    if value.abs() >= 1e6 || (value != 0.0 && value.abs() < 1e-3) {
+        format!("{:.2e}", value)
+    } else if value.fract() == 0.0 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value).trim_end_matches('0').to_string()
+    }
+}
+
+fn format_time(seconds: f64, with_time: bool) -> String {
+    let Some(time) = DateTime::from_timestamp(seconds as i64, 0) else {
+        return String::new();
+    };
+    if with_time {
+        time.format("%m-%d %H:%M").to_string()
+    } else {
+        time.format("%Y-%m-%d").to_string()
+    }
+}
+
+fn svg_color(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+fn svg_points(points: &[Pos2]) -> String {
+    points.iter().map(|p| format!("{:.1},{:.1}", p.x, p.y)).collect::<Vec<_>>().join(" ")
+}
+
+/// The marks as a standalone SVG document.
+pub fn to_svg(marks: &[Mark], size: Vec2) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0:.0}\" height=\"{1:.0}\" viewBox=\"0 0 {0:.0} {1:.0}\" font-family=\"sans-serif\" font-size=\"{2}\">\n",
+        size.x, size.y, FONT_SIZE,
+    );
+    for mark in marks {
+        match mark {
+            Mark::Line { points, color, width } => {
+                writeln!(svg, "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>", svg_points(points), svg_color(*color), width).ok();
+            }
+            Mark::Rect { rect, color } => {
+                writeln!(svg, "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>",
+                         rect.left(), rect.top(), rect.width(), rect.height(), svg_color(*color)).ok();
+            }
+            Mark::Circle { center, radius, color } => {
+                writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{}\" fill=\"{}\"/>", center.x, center.y, radius, svg_color(*color)).ok();
+            }
+            Mark::Polygon { points, color } => {
+                writeln!(svg, "<polygon points=\"{}\" fill=\"{}\" stroke=\"{1}\" stroke-width=\"0.5\"/>", svg_points(points), svg_color(*color)).ok();
+            }
+            Mark::Text { pos, text, align, color } => {
+                let anchor = match align.x() {
+                    egui::Align::Min => "start",
+                    egui::Align::Center => "middle",
+                    egui::Align::Max => "end",
+                };
+                let baseline = match align.y() {
+                    egui::Align::Min => "hanging",
+                    egui::Align::Center => "middle",
+                    egui::Align::Max => "auto",
+                };
+                let text = text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+                writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"{}\" dominant-baseline=\"{}\" fill=\"{}\">{}</text>",
+                         pos.x, pos.y, anchor, baseline, svg_color(*color), text).ok();
+            }
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn result(columns: &[&str], rows: Vec<Vec<CellValue>>) -> QueryResult {
+        QueryResult {
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            total_rows: rows.len(),
+            rows,
+            current_page: 1,
+            total_pages: 1,
+            limit: 100,
+        }
+    }
+
+    #[test]
+    fn test_suggest_and_build() {
+        let month = |m| CellValue::Date(NaiveDate::from_ymd_opt(2024, m, 1).unwrap());
+        let text = |t: &str| CellValue::Text(t.to_string());
+        let data = result(&["month", "region", "revenue"], vec![
+            vec![month(2), text("EU"), CellValue::Decimal("20.5".to_string())],
+            vec![month(1), text("EU"), CellValue::Integer(10)],
+            vec![month(1), text("US"), CellValue::Null],
+            vec![month(1), text("US"), CellValue::Float(7.0)],
+        ]);
+        let spec = suggest(&data).unwrap();
+        assert_eq!(spec, ChartSpec { kind: ChartKind::Line, x: Some(0), y: Some(2), series: Some(1), title: None });
+
+        let chart = Chart::build(&data, &spec).unwrap();
+        assert_eq!(chart.x_axis, Axis::Time);
+        assert_eq!(chart.series.len(), 2);
+        // Sorted by time, the NULL revenue left out
+        assert_eq!(chart.series[0].points.iter().map(|p| p[1]).collect::<Vec<_>>(), vec![10.0, 20.5]);
+        assert_eq!(chart.series[1].points.len(), 1);
+
+        // Bars of the same category add up
+        let bars = Chart::build(&data, &ChartSpec { kind: ChartKind::Bar, x: Some(1), y: Some(2), series: None, title: None }).unwrap();
+        assert_eq!(bars.x_axis, Axis::Category(vec!["EU".to_string(), "US".to_string()]));
+        assert_eq!(bars.series[0].points, vec![[0.0, 30.5], [1.0, 7.0]]);
+
+        assert!(Chart::build(&data, &ChartSpec { kind: ChartKind::Bar, x: Some(0), y: Some(1), series: None, title: None }).is_err());
+
+        let svg = to_svg(&bars.layout(vec2(400.0, 300.0)), vec2(400.0, 300.0));
+        assert!(svg.starts_with("<svg") && svg.contains("<rect") && svg.contains(">EU</text>"));
+
+        let suggested = |chart: &str, x: &str, y: &str| VisualizationSpec {
+            chart: chart.to_string(),
+            x: Some(x.to_string()),
+            y: Some(y.to_string()),
+            series: None,
+            title: Some("Revenue".to_string()),
+        };
+        assert_eq!(
+            ChartSpec::from_visualization(&suggested("Bar", "REGION", "revenue"), &data),
+            Ok(ChartSpec { kind: ChartKind::Bar, x: Some(1), y: Some(2), series: None, title: Some("Revenue".to_string()) }),
+        );
+        assert!(ChartSpec::from_visualization(&suggested("donut", "region", "revenue"), &data).is_err());
+        assert!(ChartSpec::from_visualization(&suggested("bar", "country", "revenue"), &data).is_err());
+        // Values that aren't numbers can't be drawn
+        assert!(ChartSpec::from_visualization(&suggested("bar", "month", "region"), &data).is_err());
+    }
+
+    #[test]
+    fn test_histogram() {
+        let data = result(&["amount"], (0..100).map(|i| vec![CellValue::Integer(i)]).collect());
+        let spec = suggest(&data).unwrap();
+        assert_eq!(spec.kind, ChartKind::Histogram);
+        let chart = Chart::build(&data, &spec).unwrap();
+        assert_eq!(chart.series[0].points.len(), 10);
+        assert_eq!(chart.series[0].points.iter().map(|p| p[1]).sum::<f64>(), 100.0);
+    }
+
+    #[test]
+    fn test_ticks_of_large_close_values() {
+        // 1e17 + 16 is the next f64, a step of 5 doesn't move a tick
+        let ticks = nice_ticks(1e17, 1e17 + 16.0);
+        assert_eq!(ticks, vec![1e17, 1e17 + 16.0]);
+        let ticks = nice_ticks(1e17, 1e17 + 64.0);
+        assert!(ticks.len() >= 2 && ticks.len() <= MAX_TICKS);
+        assert!(ticks.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(ticks[0] <= 1e17 && ticks[ticks.len() - 1] >= 1e17 + 64.0);
+
+        assert_eq!(nice_ticks(0.0, 100.0), vec![0.0, 20.0, 40.0, 60.0, 80.0, 100.0]);
+    }
+}
@@ -1,17 +1,28 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use log::debug;
-use sqlx::{Column, ColumnIndex, Decode, MySqlPool, PgPool, Row, Type};
-use crate::db_element::db::QueryResult;
+use sqlx::mysql::MySqlRow;
+use std::net::IpAddr;
+use sqlx::postgres::types::{Oid, PgInterval, PgMoney};
+use sqlx::postgres::{PgRow, PgTypeKind, PgValueFormat};
+use sqlx::sqlite::SqliteRow;
+use sqlx::types::BigDecimal;
+use futures_util::TryStreamExt;
+use sqlx::{Column, ColumnIndex, Decode, Executor, MySqlConnection, PgConnection, Row, SqlitePool, Type, TypeInfo, ValueRef};
+use bb8_tiberius::rt::Client;
+use tiberius::{ColumnData, FromSql};
+use crate::db_element::db::{QueryResult, RowSink};
+use crate::db_element::value::CellValue;
 
-pub async fn mysql_query(pool: &MySqlPool, count_query: String, select_query: String, offset: usize, limit: usize) -> Result<QueryResult, String>
+pub async fn mysql_query(conn: &mut MySqlConnection, count_query: String, select_query: String, offset: usize, limit: usize) -> Result<QueryResult, String>
 
 {
     let total_rows :u64 = sqlx::query_scalar(&count_query)
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await
         .map_err(|e| e.to_string())?;
 
     let rows = sqlx::query(&select_query)
-        .fetch_all(pool)
+        .fetch_all(&mut *conn)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -19,6 +30,7 @@ pub async fn mysql_query(pool: &MySqlPool, count_query: String, select_query: St
         return Ok(QueryResult {
             columns: Vec::new(),
             rows: Vec::new(),
+            total_rows: 0,
             current_page: 0,
             total_pages: 0,
             limit: 0,
@@ -38,7 +50,7 @@ pub async fn mysql_query(pool: &MySqlPool, count_query: String, select_query: St
     let result_rows = rows
         .iter()
         .map(|row| {
-            process_row(row)
+            mysql_row(row)
         })
         .collect();
 
@@ -48,13 +60,66 @@ pub async fn mysql_query(pool: &MySqlPool, count_query: String, select_query: St
     Ok(QueryResult {
         columns,
         rows: result_rows,
+        total_rows: total_rows as usize,
         current_page: (offset / limit) + 1,
         total_pages: ((total_rows as f64 / limit as f64).ceil() as u64) as usize,
         limit,
     })
 }
 
-pub async fn postgres_query(pool: &PgPool, count_query: String, select_query: String, offset: usize, limit: usize) -> Result<QueryResult, String>
+pub async fn postgres_query(conn: &mut PgConnection, count_query: String, select_query: String, offset: usize, limit: usize) -> Result<QueryResult, String>
+{
+    let total_rows: i64 = sqlx::query_scalar(&count_query)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let rows = sqlx::query(&select_query)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if rows.is_empty() {
+        return Ok(QueryResult {
+            columns: Vec::new(),
+            rows: Vec::new(),
+            total_rows: 0,
+            current_page: 0,
+            total_pages: 0,
+            limit: 0,
+        });
+    }
+
+
+    // Extract column names
+    let columns = rows[0]
+        .columns()
+        .iter()
+        .map(|c| {
+            c.name().to_string()
+        })
+        .collect();
+
+    // Extract row data
+    let result_rows = rows
+        .iter()
+        .map(|row| {
+            postgres_row(row)
+        })
+        .collect();
+
+    debug!("Finish running query: {}", select_query);
+    Ok(QueryResult {
+        columns,
+        rows: result_rows,
+        total_rows: total_rows as usize,
+        current_page: (offset / limit) + 1,
+        total_pages: (total_rows as f64 / limit as f64).ceil() as usize,
+        limit,
+    })
+}
+
+
+pub async fn sqlite_query(pool: &SqlitePool, count_query: String, select_query: String, offset: usize, limit: usize) -> Result<QueryResult, String>
 {
     let total_rows: i64 = sqlx::query_scalar(&count_query)
         .fetch_one(pool)
@@ -69,12 +134,70 @@ pub async fn postgres_query(pool: &PgPool, count_query: String, select_query: St
         return Ok(QueryResult {
             columns: Vec::new(),
             rows: Vec::new(),
+            total_rows: 0,
             current_page: 0,
             total_pages: 0,
             limit: 0,
         });
     }
 
+    // Extract column names
+    let columns = rows[0]
+        .columns()
+        .iter()
+        .map(|c| {
+            c.name().to_string()
+        })
+        .collect();
+
+    // Extract row data
+    let result_rows = rows
+        .iter()
+        .map(|row| {
+            sqlite_row(row)
+        })
+        .collect();
+
+    debug!("Finish running query: {}", select_query);
+    Ok(QueryResult {
+        columns,
+        rows: result_rows,
+        total_rows: total_rows as usize,
+        current_page: (offset / limit) + 1,
+        total_pages: (total_rows as f64 / limit as f64).ceil() as usize,
+        limit,
+    })
+}
+
+pub async fn mssql_query(client: &mut Client, count_query: String, select_query: String, offset: usize, limit: usize) -> Result<QueryResult, String>
+{
+
+    let total_rows: i64 = client.simple_query(count_query.as_str())
+        .await
+        .map_err(|e| e.to_string())?
+        .into_row()
+        .await
+        .map_err(|e| e.to_string())?
+        .and_then(|row| row.get::<i64, _>(0))
+        .unwrap_or(0);
+
+    let rows = client.simple_query(select_query.as_str())
+        .await
+        .map_err(|e| e.to_string())?
+        .into_first_result()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if rows.is_empty() {
+        return Ok(QueryResult {
+            columns: Vec::new(),
+            rows: Vec::new(),
+            total_rows: 0,
+            current_page: 0,
+            total_pages: 0,
+            limit: 0,
+        });
+    }
 
     // Extract column names
     let columns = rows[0]
@@ -89,7 +212,7 @@ pub async fn postgres_query(pool: &PgPool, count_query: String, select_query: St
     let result_rows = rows
         .iter()
         .map(|row| {
-            process_row(row)
+            row.cells().map(|(_, data)| mssql_cell(data)).collect()
         })
         .collect();
 
@@ -97,53 +220,312 @@ pub async fn postgres_query(pool: &PgPool, count_query: String, select_query: St
     Ok(QueryResult {
         columns,
         rows: result_rows,
+        total_rows: total_rows as usize,
         current_page: (offset / limit) + 1,
         total_pages: (total_rows as f64 / limit as f64).ceil() as usize,
         limit,
     })
 }
 
+pub async fn mysql_stream(conn: &mut MySqlConnection, query: &str, sink: &mut dyn RowSink) -> Result<(), String> {
+    let describe = (&mut *conn).describe(query).await.map_err(|e| e.to_string())?;
+    // Described up front so a query without rows still gets its column names
+    sink.columns(describe.columns().iter().map(|c| c.name().to_string()).collect())?;
+    let mut rows = sqlx::query(query).fetch(&mut *conn);
+    while let Some(row) = rows.try_next().await.map_err(|e| e.to_string())? {
+        sink.row(mysql_row(&row))?;
+    }
+    debug!("Finish streaming query: {}", query);
+    Ok(())
+}
 
+pub async fn postgres_stream(conn: &mut PgConnection, query: &str, sink: &mut dyn RowSink) -> Result<(), String> {
+    let describe = (&mut *conn).describe(query).await.map_err(|e| e.to_string())?;
+    sink.columns(describe.columns().iter().map(|c| c.name().to_string()).collect())?;
+    let mut rows = sqlx::query(query).fetch(&mut *conn);
+    while let Some(row) = rows.try_next().await.map_err(|e| e.to_string())? {
+        sink.row(postgres_row(&row))?;
+    }
+    debug!("Finish streaming query: {}", query);
+    Ok(())
+}
 
+pub async fn sqlite_stream(pool: &SqlitePool, query: &str, sink: &mut dyn RowSink) -> Result<(), String> {
+    let describe = pool.describe(query).await.map_err(|e| e.to_string())?;
+    sink.columns(describe.columns().iter().map(|c| c.name().to_string()).collect())?;
+    let mut rows = sqlx::query(query).fetch(pool);
+    while let Some(row) = rows.try_next().await.map_err(|e| e.to_string())? {
+        sink.row(sqlite_row(&row))?;
+    }
+    debug!("Finish streaming query: {}", query);
+    Ok(())
+}
+
+pub async fn mssql_stream(client: &mut Client, query: &str, sink: &mut dyn RowSink) -> Result<(), String> {
+    let mut stream = client.simple_query(query).await.map_err(|e| e.to_string())?;
+    let columns = stream.columns().await.map_err(|e| e.to_string())?
+        .map(|columns| columns.iter().map(|c| c.name().to_string()).collect())
+        .unwrap_or_default();
+    sink.columns(columns)?;
+    let mut rows = stream.into_row_stream();
+    while let Some(row) = rows.try_next().await.map_err(|e| e.to_string())? {
+        sink.row(row.cells().map(|(_, data)| mssql_cell(data)).collect())?;
+    }
+    debug!("Finish streaming query: {}", query);
+    Ok(())
+}
+
+fn mssql_cell(data: &ColumnData<'static>) -> CellValue {
+    let value = match data {
+        ColumnData::U8(v) => v.map(|v| CellValue::Integer(v as i64)),
+        ColumnData::I16(v) => v.map(|v| CellValue::Integer(v as i64)),
+        ColumnData::I32(v) => v.map(|v| CellValue::Integer(v as i64)),
+        ColumnData::I64(v) => v.map(CellValue::Integer),
+        ColumnData::F32(v) => v.map(|v| CellValue::Float(v as f64)),
+        ColumnData::F64(v) => v.map(CellValue::Float),
+        ColumnData::Bit(v) => v.map(CellValue::Bool),
+        ColumnData::String(v) => v.as_ref().map(|v| CellValue::Text(v.to_string())),
+        ColumnData::Guid(v) => v.map(CellValue::Uuid),
+        ColumnData::Binary(v) => v.as_ref().map(|v| CellValue::Bytes(v.to_vec())),
+        ColumnData::Numeric(v) => v.map(|v| CellValue::Decimal(v.to_string())),
+        ColumnData::Xml(v) => v.as_ref().map(|v| CellValue::Text(v.to_string())),
+        ColumnData::DateTime(_) | ColumnData::SmallDateTime(_) | ColumnData::DateTime2(_) => {
+            NaiveDateTime::from_sql(data).ok().flatten().map(CellValue::Timestamp)
+        }
+        ColumnData::Time(_) => NaiveTime::from_sql(data).ok().flatten().map(CellValue::Time),
+        ColumnData::Date(_) => NaiveDate::from_sql(data).ok().flatten().map(CellValue::Date),
+        ColumnData::DateTimeOffset(_) => DateTime::<Utc>::from_sql(data).ok().flatten().map(CellValue::TimestampTz),
+    };
+    value.unwrap_or(CellValue::Null)
+}
+
+/// Decodes column `i` as `Option<T>`, `None` when the type doesn't match.
+fn decode<'r, R, T>(row: &'r R, i: usize, map: impl Fn(T) -> CellValue) -> Option<CellValue>
+where
+    R: Row,
+    T: Decode<'r, R::Database> + Type<R::Database>,
+    usize: ColumnIndex<R>,
+{
+    row.try_get::<Option<T>, _>(i)
+        .ok()
+        .map(|value| value.map(map).unwrap_or(CellValue::Null))
+}
 
-fn process_row<R: Row>(row: &R) -> Vec<String>
+fn decode_array<'r, R, T>(row: &'r R, i: usize, map: impl Fn(T) -> CellValue) -> Option<CellValue>
 where
     R: Row,
-    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
-    for<'r> Option<String>: Decode<'r, R::Database> + Type<R::Database>,
-    for<'r> i64: Decode<'r, R::Database> + Type<R::Database>,  // Handle integers
-    for<'r> i32: Decode<'r, R::Database> + Type<R::Database>,  // Handle integers
-    for<'r> f64: Decode<'r, R::Database> + Type<R::Database>,  // Handle floats
-    for<'r> Option<Vec<u8>>: Decode<'r, R::Database> + Type<R::Database>, usize: ColumnIndex<R> // Handle binary
+    Vec<Option<T>>: Decode<'r, R::Database> + Type<R::Database>,
+    usize: ColumnIndex<R>,
 {
-    (0..row.columns().len())
-        .map(|i| {
+    decode(row, i, |values: Vec<Option<T>>| {
+        CellValue::Array(values.into_iter().map(|v| v.map(&map).unwrap_or(CellValue::Null)).collect())
+    })
+}
 
-            if let Ok(value) = row.try_get::<String, _>(i) {
-                return value;
-            }
+fn unsupported(type_name: &str) -> CellValue {
+    CellValue::Text(format!("<{}>", type_name))
+}
 
-            if let Ok(value) = row.try_get::<i32, _>(i) {
-                return value.to_string();
-            }
+fn mysql_row(row: &MySqlRow) -> Vec<CellValue> {
+    (0..row.columns().len()).map(|i| mysql_cell(row, i)).collect()
+}
 
-            if let Ok(value) = row.try_get::<i64, _>(i) {
-                return value.to_string();
-            }
+fn mysql_cell(row: &MySqlRow, i: usize) -> CellValue {
+    let type_name = row.column(i).type_info().name();
+    let value = match type_name {
+        "NULL" => Some(CellValue::Null),
+        "BOOLEAN" => decode(row, i, CellValue::Bool),
+        "TINYINT" | "SMALLINT" | "INT" | "MEDIUMINT" | "BIGINT" => decode(row, i, CellValue::Integer),
+        "TINYINT UNSIGNED" | "SMALLINT UNSIGNED" | "INT UNSIGNED" | "MEDIUMINT UNSIGNED" | "BIGINT UNSIGNED" => {
+            decode(row, i, |v: u64| i64::try_from(v).map(CellValue::Integer).unwrap_or_else(|_| CellValue::Decimal(v.to_string())))
+        }
+        "YEAR" => decode(row, i, |v: u16| CellValue::Integer(v as i64)),
+        "FLOAT" => decode(row, i, |v: f32| CellValue::Float(v as f64)),
+        "DOUBLE" => decode(row, i, CellValue::Float),
+        "DECIMAL" => decode(row, i, |v: BigDecimal| CellValue::Decimal(v.to_string())),
+        "DATE" => decode(row, i, CellValue::Date),
+        "TIME" => decode(row, i, CellValue::Time),
+        "DATETIME" => decode(row, i, CellValue::Timestamp),
+        "TIMESTAMP" => decode(row, i, CellValue::TimestampTz),
+        "JSON" => decode(row, i, CellValue::Json),
+        "BINARY" | "VARBINARY" | "TINYBLOB" | "BLOB" | "MEDIUMBLOB" | "LONGBLOB" | "BIT" | "GEOMETRY" => {
+            decode(row, i, CellValue::Bytes)
+        }
+        _ => None,
+    };
+    value
+        .or_else(|| decode(row, i, CellValue::Text))
+        .unwrap_or_else(|| unsupported(type_name))
+}
 
-            if let Ok(value) = row.try_get::<f64, _>(i) {
-                return value.to_string();
-            }
+fn postgres_row(row: &PgRow) -> Vec<CellValue> {
+    (0..row.columns().len()).map(|i| postgres_cell(row, i)).collect()
+}
 
-            if let Ok(Some(value)) = row.try_get::<Option<String>, _>(i) {
-                return value;
-            }
+fn postgres_cell(row: &PgRow, i: usize) -> CellValue {
+    let type_name = row.column(i).type_info().name();
+    let value = match type_name {
+        "VOID" => Some(CellValue::Null),
+        "BOOL" => decode(row, i, CellValue::Bool),
+        "INT2" => decode(row, i, |v: i16| CellValue::Integer(v as i64)),
+        "INT4" => decode(row, i, |v: i32| CellValue::Integer(v as i64)),
+        "INT8" => decode(row, i, CellValue::Integer),
+        "OID" => decode(row, i, |v: Oid| CellValue::Integer(v.0 as i64)),
+        "FLOAT4" => decode(row, i, |v: f32| CellValue::Float(v as f64)),
+        "FLOAT8" => decode(row, i, CellValue::Float),
+        "NUMERIC" => decode(row, i, |v: BigDecimal| CellValue::Decimal(v.to_string())),
+        "DATE" => decode(row, i, CellValue::Date),
+        "TIME" => decode(row, i, CellValue::Time),
+        "TIMESTAMP" => decode(row, i, CellValue::Timestamp),
+        "TIMESTAMPTZ" => decode(row, i, CellValue::TimestampTz),
+        "JSON" | "JSONB" => decode(row, i, CellValue::Json),
+        "UUID" => decode(row, i, CellValue::Uuid),
+        "BYTEA" => decode(row, i, CellValue::Bytes),
+        "BOOL[]" => decode_array(row, i, CellValue::Bool),
+        "INT2[]" => decode_array(row, i, |v: i16| CellValue::Integer(v as i64)),
+        "INT4[]" => decode_array(row, i, |v: i32| CellValue::Integer(v as i64)),
+        "INT8[]" => decode_array(row, i, CellValue::Integer),
+        "FLOAT4[]" => decode_array(row, i, |v: f32| CellValue::Float(v as f64)),
+        "FLOAT8[]" => decode_array(row, i, CellValue::Float),
+        "NUMERIC[]" => decode_array(row, i, |v: BigDecimal| CellValue::Decimal(v.to_string())),
+        "TEXT[]" | "VARCHAR[]" | "CHAR[]" | "NAME[]" => decode_array(row, i, CellValue::Text),
+        "DATE[]" => decode_array(row, i, CellValue::Date),
+        "TIMESTAMP[]" => decode_array(row, i, CellValue::Timestamp),
+        "TIMESTAMPTZ[]" => decode_array(row, i, CellValue::TimestampTz),
+        "JSON[]" | "JSONB[]" => decode_array(row, i, CellValue::Json),
+        "UUID[]" => decode_array(row, i, CellValue::Uuid),
+        "INTERVAL" => decode(row, i, |v: PgInterval| CellValue::Text(format_interval(&v))),
+        "MONEY" => decode(row, i, |v: PgMoney| CellValue::Decimal(v.to_bigdecimal(2).to_string())),
+        _ => None,
+    };
+    value
+        .or_else(|| decode(row, i, CellValue::Text))
+        .or_else(|| postgres_raw_cell(row, i))
+        .unwrap_or_else(|| unsupported(type_name))
+}
 
-            if let Ok(Some(_bytes)) = row.try_get::<Option<Vec<u8>>, _>(i) {
-                return "<binary>".to_string();
-            }
+/// Types without a decoder. Their bytes are only text in the text format, or for enums, the rest
+/// of the binary formats would show up as garbage.
+fn postgres_raw_cell(row: &PgRow, i: usize) -> Option<CellValue> {
+    let raw = row.try_get_raw(i).ok()?;
+    if raw.is_null() {
+        return Some(CellValue::Null);
+    }
+    let type_info = raw.type_info();
+    if raw.format() == PgValueFormat::Text || matches!(type_info.kind(), PgTypeKind::Enum(_)) {
+        return raw.as_str().ok().map(|v| CellValue::Text(v.to_string()));
+    }
+    match type_info.name() {
+        "INET" | "CIDR" => raw.as_bytes().ok().and_then(format_inet).map(CellValue::Text),
+        _ => None,
+    }
+}
 
-            "<unknown>".to_string()
-        })
-        .collect()
-}
\ No newline at end of file
+/// Postgres style interval, `1 year 2 mons 3 days 04:05:06.5`.
+fn format_interval(interval: &PgInterval) -> String {
+    let plural = |n: i32| if n == 1 { "" } else { "s" };
+    let mut parts = Vec::new();
+    let (years, months) = (interval.months / 12, interval.months % 12);
+    if years != 0 {
+        parts.push(format!("{} year{}", years, plural(years)));
+    }
+    if months != 0 {
+        parts.push(format!("{} mon{}", months, plural(months)));
+    }
+    if interval.days != 0 {
+        parts.push(format!("{} day{}", interval.days, plural(interval.days)));
+    }
+    if interval.microseconds != 0 || parts.is_empty() {
+        let sign = if interval.microseconds < 0 { "-" } else { "" };
+        let micros = interval.microseconds.unsigned_abs();
+        let seconds = micros / 1_000_000;
+        let mut time = format!("{}{:02}:{:02}:{:02}", sign, seconds / 3600, seconds / 60 % 60, seconds % 60);
+        if !micros.is_multiple_of(1_000_000) {
+            time.push_str(format!(".{:06}", micros % 1_000_000).trim_end_matches('0'));
+        }
+        parts.push(time);
+    }
+    parts.join(" ")
+}
+
+/// Binary inet and cidr: family, prefix length, is cidr, address length, then the address.
+fn format_inet(bytes: &[u8]) -> Option<String> {
+    let [family, bits, is_cidr, _, address @ ..] = bytes else {
+        return None;
+    };
+    let (address, max_bits) = match address.len() {
+        4 if *family == 2 => (IpAddr::from(<[u8; 4]>::try_from(address).ok()?), 32),
+        16 if *family == 3 => (IpAddr::from(<[u8; 16]>::try_from(address).ok()?), 128),
+        _ => return None,
+    };
+    Some(if *is_cidr != 0 || *bits != max_bits {
+        format!("{}/{}", address, bits)
+    } else {
+        address.to_string()
+    })
+}
+
+fn sqlite_row(row: &SqliteRow) -> Vec<CellValue> {
+    (0..row.columns().len()).map(|i| sqlite_cell(row, i)).collect()
+}
+
+fn sqlite_cell(row: &SqliteRow, i: usize) -> CellValue {
+    // SQLite is dynamically typed, so go by the storage class of the value itself
+    let Ok(raw) = row.try_get_raw(i) else {
+        return CellValue::Null;
+    };
+    if raw.is_null() {
+        return CellValue::Null;
+    }
+    let type_name = raw.type_info().name().to_string();
+    let declared = row.column(i).type_info().name();
+    let value = match type_name.as_str() {
+        "INTEGER" if declared == "BOOLEAN" => decode(row, i, CellValue::Bool),
+        "INTEGER" => decode(row, i, CellValue::Integer),
+        "REAL" => decode(row, i, CellValue::Float),
+        "BLOB" => decode(row, i, CellValue::Bytes),
+        _ => None,
+    };
+    value
+        .or_else(|| decode(row, i, CellValue::Text))
+        .unwrap_or_else(|| unsupported(&type_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sqlite_typed_cells() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let result = sqlite_query(
+            &pool,
+            "SELECT COUNT(*) FROM (SELECT 1)".to_string(),
+            "SELECT 1 AS i, 2.5 AS f, 'NULL' AS t, NULL AS n, x'beef' AS b".to_string(),
+            0,
+            100,
+        ).await.unwrap();
+
+        assert_eq!(result.rows[0], vec![
+            CellValue::Integer(1),
+            CellValue::Float(2.5),
+            CellValue::Text("NULL".to_string()),
+            CellValue::Null,
+            CellValue::Bytes(vec![0xbe, 0xef]),
+        ]);
+    }
+
+    #[test]
+    fn test_postgres_binary_text() {
+        assert_eq!(format_interval(&PgInterval { months: 14, days: 3, microseconds: 14_706_500_000 }), "1 year 2 mons 3 days 04:05:06.5");
+        assert_eq!(format_interval(&PgInterval { months: 0, days: -1, microseconds: 0 }), "-1 days");
+        assert_eq!(format_interval(&PgInterval { months: 0, days: 0, microseconds: 0 }), "00:00:00");
+
+        assert_eq!(format_inet(&[2, 32, 0, 4, 192, 168, 0, 1]).as_deref(), Some("192.168.0.1"));
+        assert_eq!(format_inet(&[2, 24, 1, 4, 10, 0, 0, 0]).as_deref(), Some("10.0.0.0/24"));
+        let mut v6 = vec![3, 64, 1, 16, 0x20, 0x01, 0x0d, 0xb8];
+        v6.extend([0; 12]);
+        assert_eq!(format_inet(&v6).as_deref(), Some("2001:db8::/64"));
+        assert_eq!(format_inet(&[2, 32]), None);
+    }
+}
@@ -0,0 +1,540 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use arrow_array::builder::{BinaryBuilder, BooleanBuilder, Date32Builder, Float64Builder, Int64Builder, StringBuilder, Time64MicrosecondBuilder, TimestampMicrosecondBuilder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::{NaiveDate, Timelike};
+use arrow_cast::cast;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use rust_xlsxwriter::{Format, Workbook};
+use crate::db_element::db::RowSink;
+use crate::db_element::value::CellValue;
+
+/// Rows per Parquet batch.
+const PARQUET_BATCH: usize = 8192;
+/// Rows of an Excel sheet, the header included.
+const XLSX_MAX_ROWS: u32 = 1_048_576;
+/// Largest integer an Excel number (a double) holds exactly.
+const XLSX_MAX_EXACT: i64 = 1 << 53;
+/// Significant decimal digits an Excel number keeps.
+const XLSX_DIGITS: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Parquet,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 4] = [ExportFormat::Csv, ExportFormat::Ndjson, ExportFormat::Parquet, ExportFormat::Xlsx];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Ndjson => "NDJSON",
+            ExportFormat::Parquet => "Parquet",
+            ExportFormat::Xlsx => "Excel (XLSX)",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+trait FormatWriter: Send {
+    fn write_row(&mut self, row: &[CellValue]) -> Result<(), String>;
+    fn finish(self: Box<Self>) -> Result<(), String>;
+}
+
+/// Writes the streamed rows of a query to a file, counting them in `progress`.
+pub struct FileExporter {
+    format: ExportFormat,
+    path: PathBuf,
+    writer: Option<Box<dyn FormatWriter>>,
+    progress: Arc<AtomicUsize>,
+}
+
+impl FileExporter {
+    pub fn new(format: ExportFormat, path: PathBuf, progress: Arc<AtomicUsize>) -> Self {
+        Self { format, path, writer: None, progress }
+    }
+
+    /// Flushes what's buffered and closes the file.
+    pub fn finish(self) -> Result<(), String> {
+        match self.writer {
+            Some(writer) => writer.finish(),
+            None => Err("The query returned no columns".to_string()),
+        }
+    }
+}
+
+impl RowSink for FileExporter {
+    fn columns(&mut self, columns: Vec<String>) -> Result<(), String> {
+        self.writer = Some(match self.format {
+            ExportFormat::Csv => Box::new(CsvWriter::new(&self.path, &columns)?),
+            ExportFormat::Ndjson => Box::new(NdjsonWriter::new(&self.path, columns)?),
+            ExportFormat::Parquet => Box::new(ParquetWriter::new(&self.path, columns)?),
+            ExportFormat::Xlsx => Box::new(XlsxWriter::new(&self.path, &columns)?),
+        });
+        Ok(())
+    }
+
+    fn row(&mut self, row: Vec<CellValue>) -> Result<(), String> {
+        let writer = self.writer.as_mut().ok_or("Row received before the columns")?;
+        writer.write_row(&row)?;
+        self.progress.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Full text of a value. Unlike `Display` bytes aren't cut short and arrays are JSON.
+fn text(value: &CellValue) -> String {
+    match value {
+        CellValue::Bytes(bytes) => {
+            let mut text = String::from("0x");
+            for byte in bytes {
+                text.push_str(&format!("{:02x}", byte));
+            }
+            text
+        }
+        CellValue::Array(_) => json(value),
+        other => other.to_string(),
+    }
+}
+
+/// A value as JSON text. Decimals stay numbers written digit for digit, not rounded through a float.
+fn json(value: &CellValue) -> String {
+    match value {
+        CellValue::Null => "null".to_string(),
+        CellValue::Bool(v) => v.to_string(),
+        CellValue::Integer(v) => v.to_string(),
+        CellValue::Decimal(v) if v.parse::<f64>().is_ok_and(f64::is_finite) => v.clone(),
+        CellValue::Float(v) if v.is_finite() => v.to_string(),
+        // NaN and infinity have no JSON number
+        CellValue::Float(_) => "null".to_string(),
+        CellValue::Json(v) => v.to_string(),
+        CellValue::Array(values) => format!("[{}]", values.iter().map(json).collect::<Vec<_>>().join(",")),
+        other => serde_json::Value::String(text(other)).to_string(),
+    }
+}
+
+/// RFC 4180 CSV. NULL is an unquoted empty field, an empty string a quoted one, like Postgres `COPY`.
+struct CsvWriter {
+    out: BufWriter<File>,
+}
+
+impl CsvWriter {
+    fn new(path: &Path, columns: &[String]) -> Result<Self, String> {
+        let mut writer = Self { out: BufWriter::new(File::create(path).map_err(|e| e.to_string())?) };
+        let header: Vec<String> = columns.iter().map(|column| csv_field(column)).collect();
+        writeln!(writer.out, "{}", header.join(",")).map_err(|e| e.to_string())?;
+        Ok(writer)
+    }
+}
+
+impl FormatWriter for CsvWriter {
+    fn write_row(&mut self, row: &[CellValue]) -> Result<(), String> {
+        let fields: Vec<String> = row.iter().map(|value| match value {
+            CellValue::Null => String::new(),
+            other => csv_field(&text(other)),
+        }).collect();
+        writeln!(self.out, "{}", fields.join(",")).map_err(|e| e.to_string())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), String> {
+        self.out.flush().map_err(|e| e.to_string())
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.is_empty() || text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// One JSON object per line, keyed by column name.
+struct NdjsonWriter {
+    keys: Vec<String>,
+    out: BufWriter<File>,
+}
+
+impl NdjsonWriter {
+    fn new(path: &Path, columns: Vec<String>) -> Result<Self, String> {
+        let out = BufWriter::new(File::create(path).map_err(|e| e.to_string())?);
+        let keys = columns.into_iter().map(|column| serde_json::Value::String(column).to_string()).collect();
+        Ok(Self { keys, out })
+    }
+}
+
+impl FormatWriter for NdjsonWriter {
+    fn write_row(&mut self, row: &[CellValue]) -> Result<(), String> {
+        let fields: Vec<String> = self.keys.iter().zip(row).map(|(key, value)| format!("{}:{}", key, json(value))).collect();
+        writeln!(self.out, "{{{}}}", fields.join(",")).map_err(|e| e.to_string())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), String> {
+        self.out.flush().map_err(|e| e.to_string())
+    }
+}
+
+struct ParquetWriter {
+    path: PathBuf,
+    columns: Vec<String>,
+    batch: Vec<Vec<CellValue>>,
+    /// Type of each column so far, `None` while it only held NULLs.
+    types: Vec<Option<DataType>>,
+    writer: Option<(ArrowWriter<File>, Arc<Schema>)>,
+}
+
+impl ParquetWriter {
+    fn new(path: &Path, columns: Vec<String>) -> Result<Self, String> {
+        let types = vec![None; columns.len()];
+        Ok(Self { path: path.to_path_buf(), columns, batch: Vec::with_capacity(PARQUET_BATCH), types, writer: None })
+    }
+
+    /// Column types come from the values: integers mixed with floats become floats, other mixes
+    /// become text. Decimals are written as text so no digit is lost.
+    fn schema(&self) -> Arc<Schema> {
+        let fields: Vec<Field> = self.columns.iter().zip(&self.types)
+            .map(|(name, data_type)| Field::new(name, data_type.clone().unwrap_or(DataType::Utf8), true))
+            .collect();
+        Arc::new(Schema::new(fields))
+    }
+
+    fn flush_batch(&mut self) -> Result<(), String> {
+        if self.batch.is_empty() && self.writer.is_some() {
+            return Ok(());
+        }
+        for (i, data_type) in self.types.iter_mut().enumerate() {
+            for value in self.batch.iter().map(|row| &row[i]) {
+                *data_type = match (data_type.take(), value) {
+                    (current, CellValue::Null) => current,
+                    (None, value) => Some(parquet_type(value)),
+                    (Some(current), value) => Some(match (current, parquet_type(value)) {
+                        (current, new) if current == new => current,
+                        (DataType::Int64, DataType::Float64) | (DataType::Float64, DataType::Int64) => DataType::Float64,
+                        _ => DataType::Utf8,
+                    }),
+                };
+            }
+        }
+
+        let schema = self.schema();
+        match &self.writer {
+            None => {
+                let writer = ArrowWriter::try_new(File::create(&self.path).map_err(|e| e.to_string())?, schema.clone(), Some(parquet_properties()))
+                    .map_err(|e| e.to_string())?;
+                self.writer = Some((writer, schema.clone()));
+            }
+            Some((_, current)) if *current != schema => self.rewrite(schema.clone())?,
+            Some(_) => {}
+        }
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+
+        let arrays = schema.fields().iter().enumerate()
+            .map(|(i, field)| parquet_array(field, self.batch.iter().map(|row| &row[i])))
+            .collect::<Result<Vec<ArrayRef>, String>>()?;
+        let batch = RecordBatch::try_new(schema, arrays).map_err(|e| e.to_string())?;
+        if let Some((writer, _)) = self.writer.as_mut() {
+            writer.write(&batch).map_err(|e| e.to_string())?;
+        }
+        self.batch.clear();
+        Ok(())
+    }
+
+    /// A Parquet file has a single schema. When a column's type widens, the rows already written
+    /// are read back and written again with the new one.
+    fn rewrite(&mut self, schema: Arc<Schema>) -> Result<(), String> {
+        if let Some((writer, _)) = self.writer.take() {
+            writer.close().map_err(|e| e.to_string())?;
+        }
+        let previous = self.path.with_extension("parquet.partial");
+        std::fs::rename(&self.path, &previous).map_err(|e| e.to_string())?;
+
+        let res: Result<ArrowWriter<File>, String> = (|| {
+            let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&previous).map_err(|e| e.to_string())?)
+                .and_then(|builder| builder.build())
+                .map_err(|e| e.to_string())?;
+            let mut writer = ArrowWriter::try_new(File::create(&self.path).map_err(|e| e.to_string())?, schema.clone(), Some(parquet_properties()))
+                .map_err(|e| e.to_string())?;
+            for batch in reader {
+                let batch = batch.map_err(|e| e.to_string())?;
+                let columns = schema.fields().iter().zip(batch.columns())
+                    .map(|(field, column)| cast(column, field.data_type()))
+                    .collect::<Result<Vec<ArrayRef>, _>>()
+                    .map_err(|e| e.to_string())?;
+                let batch = RecordBatch::try_new(schema.clone(), columns).map_err(|e| e.to_string())?;
+                writer.write(&batch).map_err(|e| e.to_string())?;
+            }
+            Ok(writer)
+        })();
+        std::fs::remove_file(&previous).ok();
+        self.writer = Some((res?, schema));
+        Ok(())
+    }
+}
+
+impl FormatWriter for ParquetWriter {
+    fn write_row(&mut self, row: &[CellValue]) -> Result<(), String> {
+        self.batch.push(row.to_vec());
+        if self.batch.len() >= PARQUET_BATCH {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), String> {
+        self.flush_batch()?;
+        if let Some((writer, _)) = self.writer.take() {
+            writer.close().map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+fn parquet_properties() -> WriterProperties {
+    WriterProperties::builder().set_compression(Compression::SNAPPY).build()
+}
+
+fn parquet_type(value: &CellValue) -> DataType {
+    match value {
+        CellValue::Bool(_) => DataType::Boolean,
+        CellValue::Integer(_) => DataType::Int64,
+        CellValue::Float(_) => DataType::Float64,
+        CellValue::Date(_) => DataType::Date32,
+        CellValue::Time(_) => DataType::Time64(TimeUnit::Microsecond),
+        CellValue::Timestamp(_) => DataType::Timestamp(TimeUnit::Microsecond, None),
+        CellValue::TimestampTz(_) => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        CellValue::Bytes(_) => DataType::Binary,
+        _ => DataType::Utf8,
+    }
+}
+
+fn parquet_array<'a>(field: &Field, values: impl Iterator<Item = &'a CellValue>) -> Result<ArrayRef, String> {
+    let mismatch = |value: &CellValue| format!(
+        "Column {} mixes types, \"{}\" doesn't fit its Parquet type {}", field.name(), value, field.data_type(),
+    );
+    // Builds the array of one type, NULL cells become nulls and `$cell` maps the others
+    macro_rules! build {
+        ($builder:expr, $cell:expr) => {{
+            let mut builder = $builder;
+            for value in values {
+                match value {
+                    CellValue::Null => builder.append_null(),
+                    value => builder.append_value($cell(value).ok_or_else(|| mismatch(value))?),
+                }
+            }
+            Arc::new(builder.finish()) as ArrayRef
+        }};
+    }
+
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+    Ok(match field.data_type() {
+        DataType::Boolean => build!(BooleanBuilder::new(), |v: &CellValue| match v {
+            CellValue::Bool(v) => Some(*v),
+            _ => None,
+        }),
+        DataType::Int64 => build!(Int64Builder::new(), |v: &CellValue| match v {
+            CellValue::Integer(v) => Some(*v),
+            _ => None,
+        }),
+        DataType::Float64 => build!(Float64Builder::new(), |v: &CellValue| match v {
+            CellValue::Integer(v) => Some(*v as f64),
+            CellValue::Float(v) => Some(*v),
+            _ => None,
+        }),
+        DataType::Date32 => build!(Date32Builder::new(), |v: &CellValue| match v {
+            CellValue::Date(v) => Some((*v - epoch).num_days() as i32),
+            _ => None,
+        }),
+        DataType::Time64(_) => build!(Time64MicrosecondBuilder::new(), |v: &CellValue| match v {
+            CellValue::Time(v) => Some(v.num_seconds_from_midnight() as i64 * 1_000_000 + v.nanosecond() as i64 / 1000),
+            _ => None,
+        }),
+        DataType::Timestamp(_, None) => build!(TimestampMicrosecondBuilder::new(), |v: &CellValue| match v {
+            CellValue::Timestamp(v) => Some(v.and_utc().timestamp_micros()),
+            _ => None,
+        }),
+        DataType::Timestamp(_, Some(_)) => build!(TimestampMicrosecondBuilder::new().with_timezone("UTC"), |v: &CellValue| match v {
+            CellValue::TimestampTz(v) => Some(v.timestamp_micros()),
+            _ => None,
+        }),
+        DataType::Binary => build!(BinaryBuilder::new(), |v: &CellValue| match v {
+            CellValue::Bytes(v) => Some(v.clone()),
+            _ => None,
+        }),
+        _ => build!(StringBuilder::new(), |v: &CellValue| Some(text(v))),
+    })
+}
+
+struct XlsxWriter {
+    path: PathBuf,
+    workbook: Workbook,
+    row: u32,
+    date: Format,
+    time: Format,
+    timestamp: Format,
+}
+
+impl XlsxWriter {
+    fn new(path: &Path, columns: &[String]) -> Result<Self, String> {
+        let mut workbook = Workbook::new();
+        // Rows go straight to a temporary file instead of piling up in memory
+        let sheet = workbook.add_worksheet_with_constant_memory();
+        let bold = Format::new().set_bold();
+        for (col, name) in columns.iter().enumerate() {
+            sheet.write_string_with_format(0, col as u16, name, &bold).map_err(|e| e.to_string())?;
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            workbook,
+            row: 0,
+            date: Format::new().set_num_format("yyyy-mm-dd"),
+            time: Format::new().set_num_format("hh:mm:ss"),
+            timestamp: Format::new().set_num_format("yyyy-mm-dd hh:mm:ss"),
+        })
+    }
+}
+
+impl FormatWriter for XlsxWriter {
+    fn write_row(&mut self, row: &[CellValue]) -> Result<(), String> {
+        self.row += 1;
+        if self.row >= XLSX_MAX_ROWS {
+            return Err(format!("An Excel sheet holds at most {} rows, export to CSV or Parquet instead", XLSX_MAX_ROWS - 1));
+        }
+        let sheet = self.workbook.worksheet_from_index(0).map_err(|e| e.to_string())?;
+        for (col, value) in row.iter().enumerate() {
+            let col = col as u16;
+            let result = match value {
+                // An empty cell
+                CellValue::Null => continue,
+                CellValue::Bool(v) => sheet.write_boolean(self.row, col, *v),
+                CellValue::Integer(v) if v.unsigned_abs() <= XLSX_MAX_EXACT as u64 => sheet.write_number(self.row, col, *v as f64),
+                CellValue::Float(v) if v.is_finite() => sheet.write_number(self.row, col, *v),
+                CellValue::Decimal(v) => match xlsx_number(v) {
+                    Some(number) => sheet.write_number(self.row, col, number),
+                    None => sheet.write_string(self.row, col, v),
+                },
+                CellValue::Date(v) => sheet.write_datetime_with_format(self.row, col, v, &self.date),
+                CellValue::Time(v) => sheet.write_datetime_with_format(self.row, col, v, &self.time),
+                CellValue::Timestamp(v) => sheet.write_datetime_with_format(self.row, col, v, &self.timestamp),
+                // Excel has no time zones, written in UTC
+                CellValue::TimestampTz(v) => sheet.write_datetime_with_format(self.row, col, v.naive_utc(), &self.timestamp),
+                // Integers a double can't hold exactly are kept as text, as are such decimals
+                other => sheet.write_string(self.row, col, text(other)),
+            };
+            result.map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), String> {
+        self.workbook.save(&self.path).map_err(|e| e.to_string())
+    }
+}
+
+/// A decimal as an Excel number, when a double holds it exactly: up to 15 significant digits
+/// always come back the same.
+fn xlsx_number(decimal: &str) -> Option<f64> {
+    let number = decimal.parse::<f64>().ok().filter(|number| number.is_finite())?;
+    let mantissa = decimal.split(['e', 'E']).next().unwrap_or(decimal);
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    let significant = digits.trim_start_matches('0').trim_end_matches('0');
+    (significant.len() <= XLSX_DIGITS).then_some(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::SqlitePool;
+    use crate::utils::db_utils::sqlite_stream;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_export_formats() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let query = "SELECT 1 AS id, 'a,b' AS name, NULL AS note, 2.5 AS price UNION ALL SELECT 2, '', 'x', NULL";
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let dir = temp_dir.path();
+
+        for format in ExportFormat::ALL {
+            let path = dir.join(format!("out.{}", format.extension()));
+            let progress = Arc::new(AtomicUsize::new(0));
+            let mut exporter = FileExporter::new(format, path.clone(), progress.clone());
+            sqlite_stream(&pool, query, &mut exporter).await.unwrap();
+            exporter.finish().unwrap();
+            assert_eq!(progress.load(Ordering::Relaxed), 2);
+            assert!(std::fs::metadata(&path).unwrap().len() > 0);
+        }
+
+        let csv = std::fs::read_to_string(dir.join("out.csv")).unwrap();
+        assert_eq!(csv, "id,name,note,price\n1,\"a,b\",,2.5\n2,\"\",x,\n");
+        let ndjson = std::fs::read_to_string(dir.join("out.ndjson")).unwrap();
+        assert_eq!(ndjson, "{\"id\":1,\"name\":\"a,b\",\"note\":null,\"price\":2.5}\n{\"id\":2,\"name\":\"\",\"note\":\"x\",\"price\":null}\n");
+    }
+
+    #[test]
+    fn test_parquet_types_widen_after_the_first_batch() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("out.parquet");
+        let rows = PARQUET_BATCH + 10;
+        let mut exporter = FileExporter::new(ExportFormat::Parquet, path.clone(), Arc::new(AtomicUsize::new(0)));
+        exporter.columns(vec!["amount".to_string(), "late".to_string(), "id".to_string()]).unwrap();
+        for i in 0..rows as i64 {
+            exporter.row(vec![
+                if i == PARQUET_BATCH as i64 { CellValue::Float(0.5) } else { CellValue::Integer(i) },
+                if i < PARQUET_BATCH as i64 { CellValue::Null } else { CellValue::Integer(i) },
+                CellValue::Integer(i),
+            ]).unwrap();
+        }
+        exporter.finish().unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap().build().unwrap();
+        let batches: Vec<RecordBatch> = reader.map(|batch| batch.unwrap()).collect();
+        let schema = batches[0].schema();
+        assert_eq!(schema.field(0).data_type(), &DataType::Float64);
+        assert_eq!(schema.field(1).data_type(), &DataType::Int64);
+        assert_eq!(schema.field(2).data_type(), &DataType::Int64);
+        assert_eq!(batches.iter().map(|batch| batch.num_rows()).sum::<usize>(), rows);
+    }
+
+    #[test]
+    fn test_xlsx_only_writes_exact_decimals_as_numbers() {
+        assert_eq!(xlsx_number("12.50"), Some(12.5));
+        assert_eq!(xlsx_number("-0.000123"), Some(-0.000123));
+        assert_eq!(xlsx_number("123456789012345"), Some(123456789012345.0));
+        assert_eq!(xlsx_number("1000000000000000000000"), Some(1e21));
+        // More digits than a double keeps, or an unsigned BIGINT above i64
+        assert_eq!(xlsx_number("1234567890123456"), None);
+        assert_eq!(xlsx_number("0.1234567890123456789"), None);
+        assert_eq!(xlsx_number("18446744073709551615"), None);
+        assert_eq!(xlsx_number("NaN"), None);
+        assert_eq!(xlsx_number("Infinity"), None);
+    }
+
+    #[test]
+    fn test_json_keeps_decimals_exact() {
+        assert_eq!(json(&CellValue::Decimal("12345678901234567890.10".to_string())), "12345678901234567890.10");
+        assert_eq!(json(&CellValue::Decimal("NaN".to_string())), "\"NaN\"");
+        assert_eq!(json(&CellValue::Float(f64::NAN)), "null");
+        assert_eq!(json(&CellValue::Array(vec![CellValue::Integer(1), CellValue::Null])), "[1,null]");
+    }
+}
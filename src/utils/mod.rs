@@ -1 +1,5 @@
-pub mod db_utils;
\ No newline at end of file
+pub mod db_utils;
+pub mod sql_guard;
+pub mod export;
+pub mod chart;
+pub mod pagination;
@@ -0,0 +1,135 @@
+use sqlparser::ast::{Expr, Ident, Query, Select, SelectItem, SetExpr, Statement, TopQuantity};
+use sqlparser::dialect::MsSqlDialect;
+use sqlparser::parser::Parser;
+use crate::db_element::db::SortOrder;
+
+/// Count and page queries for SQL Server. T-SQL has no LIMIT, a page is an ORDER BY followed by
+/// OFFSET ... FETCH NEXT, which is added to the query itself: a derived table can't hold an ORDER BY,
+/// a CTE or unnamed columns.
+pub fn mssql_pagination(query: &str, sort: Option<&SortOrder>, offset: usize, limit: usize) -> Result<(String, String), String> {
+    let mut statements = Parser::parse_sql(&MsSqlDialect {}, query).map_err(|e| e.to_string())?;
+    let Some(Statement::Query(query)) = statements.pop() else {
+        return Err("Only queries can be paginated".to_string());
+    };
+    Ok((count_query(&query), page_query(*query, sort, offset, limit)))
+}
+
+fn count_query(query: &Query) -> String {
+    let mut inner = query.clone();
+    let with = inner.with.take().map(|with| format!("{} ", with)).unwrap_or_default();
+    // The order doesn't change the count, and is only allowed in a derived table next to TOP or OFFSET
+    if top(&mut inner).is_none() && inner.limit_clause.is_none() && inner.fetch.is_none() {
+        inner.order_by = None;
+    }
+    // Every column of a derived table needs a distinct name
+    if let Some(select) = first_select(&mut inner.body) {
+        for (i, item) in select.projection.iter_mut().enumerate() {
+            if let SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } = item {
+                *item = SelectItem::ExprWithAlias { expr: expr.clone(), alias: Ident::new(format!("c{}", i + 1)) };
+            }
+        }
+    }
+    format!("{}SELECT COUNT_BIG(*) FROM ({}) AS subquery", with, inner)
+}
+
+fn page_query(mut query: Query, sort: Option<&SortOrder>, offset: usize, limit: usize) -> String {
+    let page = |fetch: usize| format!("OFFSET {} ROWS FETCH NEXT {} ROWS ONLY", offset, fetch);
+    // By position, result columns may be unnamed or share a name
+    let sort = sort.map(|sort| format!("ORDER BY {}{}", sort.column + 1, if sort.descending { " DESC" } else { " ASC" }));
+    let limited = query.limit_clause.is_some() || query.fetch.is_some() || query.for_clause.is_some();
+
+    match top(&mut query) {
+        // TOP n ... ORDER BY becomes a window of the first n rows in the query's own order
+        Some(Some(top)) if sort.is_none() && !limited && query.order_by.is_some() && offset < top => {
+            if let SetExpr::Select(select) = query.body.as_mut() {
+                select.top = None;
+            }
+            format!("{} {}", query, page(limit.min(top - offset)))
+        }
+        None if !limited => match sort {
+            Some(sort) => {
+                query.order_by = None;
+                format!("{} {} {}", query, sort, page(limit))
+            }
+            None if query.order_by.is_some() => format!("{} {}", query, page(limit)),
+            None => format!("{} ORDER BY 1 {}", query, page(limit)),
+        },
+        // TOP or OFFSET can't be combined with another OFFSET, the query is paged from the outside
+        _ => {
+            let with = query.with.take().map(|with| format!("{} ", with)).unwrap_or_default();
+            if let Some(select) = first_select(&mut query.body) {
+                for (i, item) in select.projection.iter_mut().enumerate() {
+                    if let SelectItem::UnnamedExpr(expr) = item {
+                        if !matches!(expr, Expr::Identifier(_) | Expr::CompoundIdentifier(_)) {
+                            *item = SelectItem::ExprWithAlias { expr: expr.clone(), alias: Ident::new(format!("column{}", i + 1)) };
+                        }
+                    }
+                }
+            }
+            format!("{}SELECT * FROM ({}) AS subquery {} {}", with, query, sort.as_deref().unwrap_or("ORDER BY 1"), page(limit))
+        }
+    }
+}
+
+/// `Some` when the query's select has a TOP, with its row count when it's a plain number.
+fn top(query: &mut Query) -> Option<Option<usize>> {
+    let SetExpr::Select(select) = query.body.as_mut() else {
+        return None;
+    };
+    let top = select.top.as_ref()?;
+    if top.percent || top.with_ties {
+        return Some(None);
+    }
+    Some(match &top.quantity {
+        Some(TopQuantity::Constant(rows)) => Some(*rows as usize),
+        Some(TopQuantity::Expr(rows)) => rows.to_string().parse().ok(),
+        None => None,
+    })
+}
+
+/// The select naming the columns of the result, the first one of a UNION.
+fn first_select(body: &mut SetExpr) -> Option<&mut Select> {
+    match body {
+        SetExpr::Select(select) => Some(select),
+        SetExpr::SetOperation { left, .. } => first_select(left),
+        SetExpr::Query(query) => first_select(&mut query.body),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_element::db::SortOrder;
+    use crate::utils::pagination::mssql_pagination;
+
+    #[test]
+    fn mssql_pages_keep_the_query_order() {
+        let (count, page) = mssql_pagination("SELECT name, total FROM orders ORDER BY total DESC", None, 100, 100).unwrap();
+        assert_eq!(count, "SELECT COUNT_BIG(*) FROM (SELECT name AS c1, total AS c2 FROM orders) AS subquery");
+        assert_eq!(page, "SELECT name, total FROM orders ORDER BY total DESC OFFSET 100 ROWS FETCH NEXT 100 ROWS ONLY");
+
+        let (count, page) = mssql_pagination("SELECT COUNT(*) FROM orders", None, 0, 100).unwrap();
+        assert_eq!(count, "SELECT COUNT_BIG(*) FROM (SELECT COUNT(*) AS c1 FROM orders) AS subquery");
+        assert_eq!(page, "SELECT COUNT(*) FROM orders ORDER BY 1 OFFSET 0 ROWS FETCH NEXT 100 ROWS ONLY");
+
+        let sort = SortOrder { column: 1, descending: true };
+        let (_, page) = mssql_pagination("SELECT name, total FROM orders ORDER BY name", Some(&sort), 0, 100).unwrap();
+        assert_eq!(page, "SELECT name, total FROM orders ORDER BY 2 DESC OFFSET 0 ROWS FETCH NEXT 100 ROWS ONLY");
+    }
+
+    #[test]
+    fn mssql_pages_of_ctes_and_top() {
+        let query = "WITH recent AS (SELECT * FROM orders WHERE year = 2024) SELECT region, SUM(total) FROM recent GROUP BY region";
+        let (count, page) = mssql_pagination(query, None, 0, 100).unwrap();
+        assert_eq!(count, "WITH recent AS (SELECT * FROM orders WHERE year = 2024) SELECT COUNT_BIG(*) FROM (SELECT region AS c1, SUM(total) AS c2 FROM recent GROUP BY region) AS subquery");
+        assert_eq!(page, format!("{} ORDER BY 1 OFFSET 0 ROWS FETCH NEXT 100 ROWS ONLY", query));
+
+        let (count, page) = mssql_pagination("SELECT TOP 150 name FROM users ORDER BY created_at DESC", None, 100, 100).unwrap();
+        assert_eq!(count, "SELECT COUNT_BIG(*) FROM (SELECT TOP 150 name AS c1 FROM users ORDER BY created_at DESC) AS subquery");
+        assert_eq!(page, "SELECT name FROM users ORDER BY created_at DESC OFFSET 100 ROWS FETCH NEXT 50 ROWS ONLY");
+
+        let sort = SortOrder { column: 0, descending: false };
+        let (_, page) = mssql_pagination("SELECT TOP 10 name, LEN(name) FROM users ORDER BY 2", Some(&sort), 0, 100).unwrap();
+        assert_eq!(page, "SELECT * FROM (SELECT TOP 10 name, LEN(name) AS column2 FROM users ORDER BY 2) AS subquery ORDER BY 1 ASC OFFSET 0 ROWS FETCH NEXT 100 ROWS ONLY");
+    }
+}
@@ -0,0 +1,192 @@
+use std::ops::ControlFlow;
+use sqlparser::ast::{Expr, ObjectName, Query, Select, SetExpr, Statement, TableFactor, Visit, Visitor};
+use sqlparser::dialect::{Dialect, MsSqlDialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect};
+use sqlparser::parser::Parser;
+use crate::config::DbType;
+
+/// Functions that write data, change server state or reach outside of the database.
+const SIDE_EFFECT_FUNCTIONS: &[&str] = &[
+    // PostgreSQL
+    "nextval", "setval", "set_config", "txid_current", "pg_current_xact_id",
+    "pg_terminate_backend", "pg_cancel_backend", "pg_reload_conf", "pg_rotate_logfile",
+    "pg_switch_wal", "pg_create_restore_point", "pg_promote", "pg_logical_emit_message",
+    "pg_create_logical_replication_slot", "pg_create_physical_replication_slot", "pg_drop_replication_slot",
+    "pg_read_file", "pg_read_binary_file", "pg_ls_dir", "pg_stat_file",
+    "pg_advisory_lock", "pg_advisory_xact_lock", "pg_try_advisory_lock", "pg_try_advisory_xact_lock",
+    "pg_advisory_lock_shared", "pg_advisory_xact_lock_shared", "pg_notify",
+    "lo_import", "lo_export", "lo_create", "lo_unlink", "lo_put", "lo_from_bytea",
+    "dblink", "dblink_exec", "query_to_xml", "query_to_xml_and_xmlschema",
+    // MySQL
+    "sleep", "benchmark", "get_lock", "release_lock", "release_all_locks", "load_file",
+    "sys_exec", "sys_eval", "master_pos_wait", "source_pos_wait",
+    // SQL Server
+    "openrowset", "opendatasource", "openquery", "xp_cmdshell",
+    // SQLite
+    "load_extension", "writefile", "readfile", "edit",
+];
+
+/// Table hints that take write locks in SQL Server.
+const LOCKING_HINTS: &[&str] = &["updlock", "xlock", "tablockx", "holdlock"];
+
+fn dialect(db_type: &DbType) -> Box<dyn Dialect> {
+    match db_type {
+        DbType::MySQL => Box::new(MySqlDialect {}),
+        DbType::PostgreSQL => Box::new(PostgreSqlDialect {}),
+        DbType::SQLite => Box::new(SQLiteDialect {}),
+        DbType::SQLServer => Box::new(MsSqlDialect {}),
+    }
+}
+
+fn function_name(name: &ObjectName) -> String {
+    name.0
+        .last()
+        .and_then(|part| part.as_ident())
+        .map(|ident| ident.value.to_lowercase())
+        .unwrap_or_default()
+}
+
+fn statement_keyword(statement: &Statement) -> String {
+    statement
+        .to_string()
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_uppercase()
+}
+
+struct ReadOnlyVisitor;
+
+impl ReadOnlyVisitor {
+    fn check_set_expr(&self, set_expr: &SetExpr) -> ControlFlow<String> {
+        match set_expr {
+            SetExpr::Insert(statement) | SetExpr::Update(statement) | SetExpr::Delete(statement) | SetExpr::Merge(statement) => {
+                ControlFlow::Break(format!("{} statement", statement_keyword(statement)))
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.check_set_expr(left)?;
+                self.check_set_expr(right)
+            }
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+impl Visitor for ReadOnlyVisitor {
+    type Break = String;
+
+    fn pre_visit_statement(&mut self, statement: &Statement) -> ControlFlow<Self::Break> {
+        match statement {
+            Statement::Query(_) => ControlFlow::Continue(()),
+            statement => ControlFlow::Break(format!("{} statement", statement_keyword(statement))),
+        }
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        if let Some(lock) = query.locks.first() {
+            return ControlFlow::Break(format!("locking clause FOR {}", lock.lock_type));
+        }
+        self.check_set_expr(&query.body)
+    }
+
+    fn pre_visit_select(&mut self, select: &Select) -> ControlFlow<Self::Break> {
+        if select.into.is_some() {
+            return ControlFlow::Break("SELECT ... INTO".to_string());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, table_factor: &TableFactor) -> ControlFlow<Self::Break> {
+        match table_factor {
+            TableFactor::Table { name, args: Some(_), .. } | TableFactor::Function { name, .. } => {
+                let name = function_name(name);
+                if SIDE_EFFECT_FUNCTIONS.contains(&name.as_str()) {
+                    return ControlFlow::Break(format!("call to function {}()", name));
+                }
+            }
+            TableFactor::Table { with_hints, .. } => {
+                for hint in with_hints {
+                    let hint = hint.to_string().to_lowercase();
+                    if LOCKING_HINTS.contains(&hint.as_str()) {
+                        return ControlFlow::Break(format!("locking table hint {}", hint.to_uppercase()));
+                    }
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        if let Expr::Function(function) = expr {
+            let name = function_name(&function.name);
+            if SIDE_EFFECT_FUNCTIONS.contains(&name.as_str()) {
+                return ControlFlow::Break(format!("call to function {}()", name));
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Parses `query` with the connection dialect and rejects anything that is not a single pure read.
+pub fn ensure_read_only(query: &str, db_type: &DbType) -> Result<(), String> {
+    let statements = Parser::parse_sql(dialect(db_type).as_ref(), query)
+        .map_err(|e| format!("Query rejected, it could not be parsed as {} SQL: {}", db_type.dialect_name(), e))?;
+
+    match statements.len() {
+        0 => return Err("Query rejected, it is empty".to_string()),
+        1 => {}
+        n => return Err(format!("Query rejected, only one statement can run at a time but found {}", n)),
+    }
+
+    if let ControlFlow::Break(construct) = statements[0].visit(&mut ReadOnlyVisitor) {
+        return Err(format!("Query rejected, only read-only queries are allowed but it contains a {}", construct));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::DbType;
+    use crate::utils::sql_guard::ensure_read_only;
+
+    fn rejected(query: &str, db_type: DbType) -> String {
+        ensure_read_only(query, &db_type).expect_err("Query should be rejected")
+    }
+
+    #[test]
+    fn allows_reads() {
+        for db_type in [DbType::MySQL, DbType::PostgreSQL, DbType::SQLite, DbType::SQLServer] {
+            assert!(ensure_read_only("SELECT * FROM users", &db_type).is_ok());
+            assert!(ensure_read_only("SELECT u.name, COUNT(*) FROM users u JOIN orders o ON o.user_id = u.id GROUP BY u.name;", &db_type).is_ok());
+            assert!(ensure_read_only("WITH recent AS (SELECT * FROM orders) SELECT * FROM recent UNION SELECT * FROM orders", &db_type).is_ok());
+        }
+        assert!(ensure_read_only("SELECT date_trunc('month', created_at), sum(total) FROM billing.invoices GROUP BY 1", &DbType::PostgreSQL).is_ok());
+        assert!(ensure_read_only("SELECT TOP 10 * FROM dbo.orders WITH (NOLOCK)", &DbType::SQLServer).is_ok());
+    }
+
+    #[test]
+    fn rejects_writes() {
+        assert!(rejected("DELETE FROM users", DbType::MySQL).contains("DELETE statement"));
+        assert!(rejected("UPDATE users SET name = 'x'", DbType::PostgreSQL).contains("UPDATE statement"));
+        assert!(rejected("INSERT INTO users (id) VALUES (1)", DbType::SQLite).contains("INSERT statement"));
+        assert!(rejected("DROP TABLE users", DbType::SQLServer).contains("DROP statement"));
+        assert!(rejected("CREATE TABLE t (id INT)", DbType::MySQL).contains("CREATE statement"));
+        assert!(rejected("SELECT 1; DELETE FROM users", DbType::PostgreSQL).contains("only one statement"));
+    }
+
+    #[test]
+    fn rejects_hidden_side_effects() {
+        assert!(rejected("SELECT * INTO backup FROM users", DbType::PostgreSQL).contains("SELECT ... INTO"));
+        assert!(rejected("SELECT * FROM users FOR UPDATE", DbType::PostgreSQL).contains("FOR UPDATE"));
+        assert!(rejected("SELECT * FROM users FOR SHARE", DbType::PostgreSQL).contains("FOR SHARE"));
+        assert!(rejected("SELECT nextval('users_id_seq')", DbType::PostgreSQL).contains("nextval()"));
+        assert!(rejected("SELECT id FROM users WHERE pg_catalog.pg_terminate_backend(1)", DbType::PostgreSQL).contains("pg_terminate_backend()"));
+        assert!(rejected("SELECT SLEEP(10)", DbType::MySQL).contains("sleep()"));
+        assert!(rejected("SELECT * FROM orders WITH (UPDLOCK)", DbType::SQLServer).contains("UPDLOCK"));
+    }
+
+    #[test]
+    fn rejects_unparsable_sql() {
+        assert!(rejected("SELEC * FROM users", DbType::MySQL).contains("could not be parsed as MySQL"));
+    }
+}